@@ -0,0 +1,95 @@
+//! Benchmarks of the word filter
+//!
+//! The inputs of `tests/test_textfilter.rs` are filtered one by one and, repeated to a corpus of
+//! about a megabyte, at once. Run with `cargo bench`.
+#[macro_use]
+extern crate criterion;
+extern crate craft;
+extern crate isolang;
+
+use craft::textfilter::*;
+use criterion::{Benchmark, Criterion, Throughput};
+use isolang::Language;
+use std::io;
+
+/// inputs of the word filter tests
+static TEST_CASES: &[&str] = &["test this", "\nok\t worked ", "1990 was a special year",
+    "my 1st test", "Haus_NOUN bauen_VERB", "However, I like it. :)",
+    "this is a non-alcoholic drink", "Using hard- and software", "(ignore that, ok?)",
+    "[ignore that, ok?]", "{ignore that, ok?}", "I'm not sure, O'raggley", "ab; cd", "ab;cd",
+    "jo (''.) moo", "zu Deutsch „die Hauptstadt“.", "abc \x07 def", "مرحبا، عالم؟",
+    "यह घर है। वह", "「東京」。", "¿Qué tal? ¡Hola!", "‘quoted’ O’Neill’s"];
+
+/// size of the generated corpora
+const CORPUS_SIZE: usize = 1 << 20;
+
+// repeat the given paragraphs, separated by the line break escape, up to the corpus size
+fn corpus(paragraphs: &[&str]) -> String {
+    let mut text = String::with_capacity(CORPUS_SIZE + 100);
+    for paragraph in paragraphs.iter().cycle() {
+        if text.len() >= CORPUS_SIZE {
+            break;
+        }
+        text.push_str(paragraph);
+        text.push_str(" \x07 ");
+    }
+    text
+}
+
+fn bench_test_cases(c: &mut Criterion) {
+    c.bench_function("text2words test cases", |b| b.iter(|| {
+        for case in TEST_CASES {
+            text2words(case.to_string(), None);
+        }
+    }));
+    c.bench_function("text2words_into test cases", |b| b.iter(|| {
+        for case in TEST_CASES {
+            text2words_into(case, &FilterOptions::default(), &mut io::sink()).unwrap();
+        }
+    }));
+}
+
+fn bench_large_inputs(c: &mut Criterion) {
+    let text = corpus(TEST_CASES);
+    let length = text.len() as u32;
+    c.bench("large input", Benchmark::new("text2words", move |b|
+            b.iter_with_setup(|| text.clone(), |text| text2words(text, None)))
+        .throughput(Throughput::Bytes(length))
+        .sample_size(20));
+
+    let text = corpus(TEST_CASES);
+    c.bench("large input", Benchmark::new("text2words_into", move |b|
+            b.iter(|| text2words_into(&text, &FilterOptions::default(), &mut io::sink())
+                .unwrap()))
+        .throughput(Throughput::Bytes(length))
+        .sample_size(20));
+
+    let text = corpus(&["L'homme qu’il aime, c'est l'ami d’Artagnan.",
+        "« Jusqu'à demain », dit-il, « n’oubliez pas l'heure ! »"]);
+    let length = text.len() as u32;
+    c.bench("large input", Benchmark::new("elision", move |b| {
+            let mut characters = CharClasses::for_language(&Language::Fra);
+            characters.elision = AffixHandling::Split;
+            let options = FilterOptions { characters: Some(&characters),
+                ..FilterOptions::default() };
+            b.iter(|| text2words_into(&text, &options, &mut io::sink()).unwrap())
+        })
+        .throughput(Throughput::Bytes(length))
+        .sample_size(20));
+
+    let text = corpus(&["我爱北京天安门。", "我用Rust编程，东京タワーへ。"]);
+    let length = text.len() as u32;
+    c.bench("large input", Benchmark::new("lexicon segmentation", move |b| {
+            let lexicon = Lexicon::from_reader("北京\n天安门\n编程\n東京\nタワー\n".as_bytes())
+                .unwrap();
+            let segmenter = Segmenter::Dictionary(lexicon);
+            let options = FilterOptions { segmenter: Some(&segmenter),
+                ..FilterOptions::default() };
+            b.iter(|| text2words_into(&text, &options, &mut io::sink()).unwrap())
+        })
+        .throughput(Throughput::Bytes(length))
+        .sample_size(20));
+}
+
+criterion_group!(benches, bench_test_cases, bench_large_inputs);
+criterion_main!(benches);
@@ -0,0 +1,394 @@
+//! Bilingual alignment of word vectors
+//!
+//! Word vectors trained separately on corpora of two languages have a similar geometry, so one
+//! space can be rotated onto the other (Mikolov et al., 2013; Xing et al., 2015). The rotation is
+//! learnt from a seed dictionary, e.g. an existing FreeDict dictionary, by solving the orthogonal
+//! Procrustes problem: for the matrices `X` and `Y` holding the vectors of the seed pairs, the
+//! orthogonal `W` minimising `|XW - Y|` is `UVᵀ`, where `UΣVᵀ` is the singular value
+//! decomposition of `XᵀY`.
+//!
+//! After mapping, the nearest target words of a source word are its translation candidates.
+//! Instead of the plain cosine similarity, candidates are ranked by the cross-domain similarity
+//! local scaling (CSLS, Conneau et al., 2018), which penalises "hubs" being close to many words:
+//! `CSLS(x, y) = 2 cos(Wx, y) - r_T(Wx) - r_S(y)`, where `r_T` and `r_S` are the mean similarity
+//! of a vector to its `k` nearest neighbours in the other space. The neighbours are looked up in
+//! approximate nearest-neighbour [`Index`](../thesaurus/struct.Index.html)es.
+//!
+//! A part of the seed dictionary can be held out to measure the precision@k of the mapping.
+
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::Path;
+
+use common;
+use input_source::{PositionType, Result, TransformationError};
+use modules::freedict;
+use thesaurus::{Index, IndexConfig};
+use word2vec::Model;
+
+/// Number of nearest neighbours over which `r_T` and `r_S` of the CSLS are averaged, unless
+/// configured otherwise.
+pub static DEFAULT_NEIGHBOURHOOD: usize = 10;
+
+// number of cosine nearest neighbours retrieved per requested CSLS candidate
+static RETRIEVAL_FACTOR: usize = 4;
+
+/// A source word and one of its translations
+pub type SeedPair = (String, String);
+
+/// Read seed pairs, either from a FreeDict dictionary (`.tei`), pairing each headword with all
+/// its translations, or from a file with a source and a target word per line, separated by a
+/// tab.
+pub fn read_seed_pairs(path: &Path) -> Result<Vec<SeedPair>> {
+    let mut pairs = Vec::new();
+    if path.extension().map(|e| e == "tei").unwrap_or(false) {
+        for entry in freedict::read_dictionary(path)? {
+            let entry = entry?;
+            for headword in &entry.headwords {
+                for sense in &entry.senses {
+                    for translation in &sense.translations {
+                        pairs.push((headword.clone(), translation.clone()));
+                    }
+                }
+            }
+        }
+        return Ok(pairs);
+    }
+    for line in BufReader::new(common::open_file(path)?).lines() {
+        let line = line?;
+        let mut columns = line.split('\t').map(|c| c.trim());
+        match (columns.next(), columns.next()) {
+            (Some(source), Some(target)) if !source.is_empty() && !target.is_empty() =>
+                pairs.push((source.to_string(), target.to_string())),
+            _ => (),
+        }
+    }
+    Ok(pairs)
+}
+
+// FNV-1a hash, stable across runs and platforms
+fn stable_hash(text: &str, seed: u64) -> u64 {
+    text.bytes().fold(0xcbf2_9ce4_8422_2325 ^ seed, |hash, byte|
+            (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3))
+}
+
+/// Split seed pairs into a training and a test part.
+///
+/// All pairs of a source word end up in the same part; about `test_fraction` of the source words
+/// are held out. The split only depends on the words and the seed.
+pub fn split_seed(pairs: Vec<SeedPair>, test_fraction: f64, seed: u64)
+        -> (Vec<SeedPair>, Vec<SeedPair>) {
+    pairs.into_iter().partition(|(source, _)| {
+        (stable_hash(source, seed) % 10_000) as f64 >= test_fraction * 10_000.0
+    })
+}
+
+/// An orthogonal linear map between two vector spaces
+#[derive(Clone, Debug)]
+pub struct Mapping {
+    pub dimensions: usize,
+    /// row-major matrix W; a vector x is mapped to xW
+    pub matrix: Vec<f32>,
+}
+
+impl Mapping {
+    /// Map a vector of the source space into the target space.
+    pub fn apply(&self, vector: &[f32]) -> Vec<f32> {
+        let d = self.dimensions;
+        let mut mapped = vec![0.0f32; d];
+        for (i, x) in vector.iter().enumerate() {
+            for (m, w) in mapped.iter_mut().zip(&self.matrix[i * d..(i + 1) * d]) {
+                *m += x * w;
+            }
+        }
+        mapped
+    }
+}
+
+// singular value decomposition of a square matrix (row-major) with the one-sided Jacobi method;
+// returns U and V with the singular vectors as columns (row-major) and the singular values
+fn svd(matrix: &[f64], n: usize) -> (Vec<f64>, Vec<f64>, Vec<f64>) {
+    // columns of A and V, stored contiguously
+    let mut a: Vec<f64> = (0..n * n).map(|i| matrix[(i % n) * n + i / n]).collect();
+    let mut v: Vec<f64> = (0..n * n).map(|i| match i / n == i % n { true => 1.0, false => 0.0 })
+        .collect();
+    for _sweep in 0..100 {
+        let mut rotated = false;
+        for p in 0..n {
+            for q in p + 1..n {
+                let (mut alpha, mut beta, mut gamma) = (0.0, 0.0, 0.0);
+                for i in 0..n {
+                    let (x, y) = (a[p * n + i], a[q * n + i]);
+                    alpha += x * x;
+                    beta += y * y;
+                    gamma += x * y;
+                }
+                if gamma.abs() <= 1e-15 * (alpha * beta).sqrt() || gamma == 0.0 {
+                    continue;
+                }
+                rotated = true;
+                let zeta = (beta - alpha) / (2.0 * gamma);
+                let t = zeta.signum() / (zeta.abs() + (1.0 + zeta * zeta).sqrt());
+                let c = 1.0 / (1.0 + t * t).sqrt();
+                let s = c * t;
+                for columns in &mut [&mut a, &mut v] {
+                    for i in 0..n {
+                        let (x, y) = (columns[p * n + i], columns[q * n + i]);
+                        columns[p * n + i] = c * x - s * y;
+                        columns[q * n + i] = s * x + c * y;
+                    }
+                }
+            }
+        }
+        if !rotated {
+            break;
+        }
+    }
+    let sigma: Vec<f64> = (0..n).map(|j| a[j * n..(j + 1) * n].iter().map(|x| x * x).sum::<f64>()
+            .sqrt()).collect();
+    let largest = sigma.iter().cloned().fold(0.0, f64::max);
+    // columns of U; those of (nearly) vanishing singular values are completed later
+    let mut u_columns: Vec<Option<Vec<f64>>> = (0..n).map(|j| match sigma[j] > largest * 1e-10
+            && sigma[j] > 0.0 {
+        true => Some(a[j * n..(j + 1) * n].iter().map(|x| x / sigma[j]).collect()),
+        false => None,
+    }).collect();
+    complete_orthonormal(&mut u_columns, n);
+    let mut u = vec![0.0; n * n];
+    let mut v_rows = vec![0.0; n * n];
+    for j in 0..n {
+        let column = u_columns[j].as_ref().unwrap();
+        for i in 0..n {
+            u[i * n + j] = column[i];
+            v_rows[i * n + j] = v[j * n + i];
+        }
+    }
+    (u, sigma, v_rows)
+}
+
+// replace missing columns by unit vectors orthogonal to all others (Gram-Schmidt)
+fn complete_orthonormal(columns: &mut [Option<Vec<f64>>], n: usize) {
+    let mut basis = 0;
+    for j in 0..n {
+        while columns[j].is_none() && basis < n {
+            let mut candidate: Vec<f64> = (0..n).map(|i| match i == basis {
+                true => 1.0,
+                false => 0.0,
+            }).collect();
+            basis += 1;
+            for other in columns.iter().filter_map(|c| c.as_ref()) {
+                let dot: f64 = candidate.iter().zip(other).map(|(x, y)| x * y).sum();
+                for (x, y) in candidate.iter_mut().zip(other) {
+                    *x -= dot * y;
+                }
+            }
+            let norm = candidate.iter().map(|x| x * x).sum::<f64>().sqrt();
+            if norm > 1e-6 {
+                columns[j] = Some(candidate.into_iter().map(|x| x / norm).collect());
+            }
+        }
+    }
+}
+
+/// Compute the orthogonal mapping of the source onto the target vectors of the given pairs.
+pub fn procrustes(pairs: &[(&[f32], &[f32])], dimensions: usize) -> Mapping {
+    let d = dimensions;
+    // M = XᵀY
+    let mut m = vec![0.0f64; d * d];
+    for &(source, target) in pairs {
+        for (i, x) in source.iter().enumerate() {
+            for (cell, y) in m[i * d..(i + 1) * d].iter_mut().zip(target) {
+                *cell += (*x as f64) * (*y as f64);
+            }
+        }
+    }
+    let (u, _, v) = svd(&m, d);
+    // W = UVᵀ
+    let mut matrix = vec![0.0f32; d * d];
+    for i in 0..d {
+        for j in 0..d {
+            matrix[i * d + j] = (0..d).map(|k| u[i * d + k] * v[j * d + k]).sum::<f64>() as f32;
+        }
+    }
+    Mapping { dimensions: d, matrix }
+}
+
+fn normalise(vector: &mut [f32]) {
+    let norm = vector.iter().map(|v| v * v).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        for v in vector.iter_mut() {
+            *v /= norm;
+        }
+    }
+}
+
+// copy of a model with vectors of unit length
+fn normalised(model: &Model) -> Model {
+    let mut vectors = model.vectors.clone();
+    for vector in vectors.chunks_mut(model.dimensions) {
+        normalise(vector);
+    }
+    Model { words: model.words.clone(), dimensions: model.dimensions, vectors }
+}
+
+fn vector_of(model: &Model, index: usize) -> &[f32] {
+    &model.vectors[index * model.dimensions..(index + 1) * model.dimensions]
+}
+
+fn mean_similarity(results: &[(usize, f32)], k: usize) -> f32 {
+    let neighbours = results.iter().take(k).collect::<Vec<_>>();
+    match neighbours.len() {
+        0 => 0.0,
+        count => neighbours.iter().map(|r| r.1).sum::<f32>() / count as f32,
+    }
+}
+
+/// Mapped source and target vectors, ready to rank translation candidates
+pub struct Alignment {
+    /// source vectors mapped into the target space, normalised
+    source: Model,
+    /// target vectors, normalised
+    target: Model,
+    source_words: HashMap<String, usize>,
+    source_index: Index,
+    target_index: Index,
+    neighbourhood: usize,
+    /// r_S of target words, computed on demand
+    source_density: RefCell<HashMap<usize, f32>>,
+}
+
+impl Alignment {
+    /// Learn the mapping from the seed pairs found in both vocabularies and index both spaces.
+    pub fn new(source: &Model, target: &Model, seed: &[SeedPair], neighbourhood: usize)
+            -> Result<Alignment> {
+        if source.dimensions != target.dimensions {
+            return Err(TransformationError::ErrorneousStructure(format!(
+                    "source vectors have {} dimensions, target vectors {}", source.dimensions,
+                    target.dimensions), PositionType::None));
+        }
+        let source = normalised(source);
+        let target = normalised(target);
+        let source_words: HashMap<String, usize> = source.words.iter().enumerate()
+            .map(|(i, w)| (w.clone(), i)).collect();
+        let target_words: HashMap<&str, usize> = target.words.iter().enumerate()
+            .map(|(i, w)| (w.as_str(), i)).collect();
+        let pairs: Vec<(&[f32], &[f32])> = seed.iter().filter_map(|(s, t)| {
+            match (source_words.get(s), target_words.get(t.as_str())) {
+                (Some(&s), Some(&t)) => Some((vector_of(&source, s), vector_of(&target, t))),
+                _ => None,
+            }
+        }).collect();
+        if pairs.is_empty() {
+            return Err(TransformationError::ErrorneousStructure(
+                    "no seed pair occurs in both vocabularies".into(), PositionType::None));
+        }
+        let mapping = procrustes(&pairs, source.dimensions);
+        let mut mapped = Vec::with_capacity(source.vectors.len());
+        for vector in source.vectors.chunks(source.dimensions) {
+            mapped.extend(mapping.apply(vector));
+        }
+        let source = Model { words: source.words, dimensions: source.dimensions,
+            vectors: mapped };
+        let source_index = Index::build(&source, IndexConfig::default());
+        let target_index = Index::build(&target, IndexConfig::default());
+        Ok(Alignment { source, target, source_words, source_index, target_index,
+            neighbourhood, source_density: RefCell::new(HashMap::new()) })
+    }
+
+    // mean similarity of a target word to its nearest mapped source words
+    fn source_density(&self, target: usize) -> f32 {
+        if let Some(density) = self.source_density.borrow().get(&target) {
+            return *density;
+        }
+        let results = self.source_index.search(vector_of(&self.target, target),
+                self.neighbourhood);
+        let density = mean_similarity(&results, self.neighbourhood);
+        self.source_density.borrow_mut().insert(target, density);
+        density
+    }
+
+    /// The `count` best translation candidates of a source word with their CSLS score, best
+    /// first; empty if the word is unknown.
+    pub fn translate(&self, word: &str, count: usize) -> Vec<(String, f32)> {
+        let source = match self.source_words.get(word) {
+            Some(&source) => source,
+            None => return Vec::new(),
+        };
+        let query = vector_of(&self.source, source);
+        let retrieved = self.target_index.search(query,
+                (count * RETRIEVAL_FACTOR).max(self.neighbourhood));
+        let target_density = mean_similarity(&retrieved, self.neighbourhood);
+        let mut candidates: Vec<(usize, f32)> = retrieved.iter().map(|&(target, similarity)|
+                (target, 2.0 * similarity - target_density - self.source_density(target)))
+            .collect();
+        candidates.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(::std::cmp::Ordering::Equal));
+        candidates.into_iter().take(count)
+            .map(|(target, score)| (self.target.words[target].clone(), score)).collect()
+    }
+
+    /// Words of the source vocabulary, most frequent first.
+    pub fn source_words(&self) -> &[String] {
+        &self.source.words
+    }
+}
+
+/// Write translation candidates as tab-separated values: source word, candidate and CSLS score.
+pub fn write_candidates<W: Write>(candidates: &[(String, Vec<(String, f32)>)], output: &mut W)
+        -> io::Result<()> {
+    for (source, translations) in candidates {
+        for (translation, score) in translations {
+            writeln!(output, "{}\t{}\t{:.4}", source, translation, score)?;
+        }
+    }
+    Ok(())
+}
+
+/// Precision@k of the translations of held-out pairs
+#[derive(Debug)]
+pub struct Evaluation {
+    /// number of source words evaluated (those known in the source space)
+    pub evaluated: usize,
+    /// k and the fraction of source words with a correct translation among the top k
+    pub precision: Vec<(usize, f64)>,
+}
+
+/// Evaluate the alignment on held-out pairs at the given values of k.
+pub fn evaluate(alignment: &Alignment, test: &[SeedPair], ks: &[usize]) -> Evaluation {
+    let mut gold: BTreeMap<&str, HashSet<&str>> = BTreeMap::new();
+    for (source, target) in test {
+        gold.entry(source.as_str()).or_default().insert(target.as_str());
+    }
+    let max_k = ks.iter().cloned().max().unwrap_or(1);
+    let mut evaluated = 0;
+    let mut hits = vec![0; ks.len()];
+    for (source, targets) in gold {
+        let candidates = alignment.translate(source, max_k);
+        if candidates.is_empty() {
+            continue;
+        }
+        evaluated += 1;
+        let first_hit = candidates.iter().position(|(c, _)| targets.contains(c.as_str()));
+        for (hit, &k) in hits.iter_mut().zip(ks) {
+            if first_hit.map(|position| position < k).unwrap_or(false) {
+                *hit += 1;
+            }
+        }
+    }
+    let precision = ks.iter().zip(hits).map(|(&k, hit)| (k, match evaluated {
+        0 => 0.0,
+        total => hit as f64 / total as f64,
+    })).collect();
+    Evaluation { evaluated, precision }
+}
+
+impl fmt::Display for Evaluation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "evaluated source words: {}", self.evaluated)?;
+        for &(k, precision) in &self.precision {
+            writeln!(f, "  precision@{:<3} {:.1} %", k, precision * 100.0)?;
+        }
+        Ok(())
+    }
+}
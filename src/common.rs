@@ -3,11 +3,18 @@
 //! This module contains common functions, macros and iterators used by both modules and the CRAFT
 //! implementation.
 
+use bzip2::read::BzDecoder;
+use flate2::read::GzDecoder;
 use htmlstream;
+use std::cmp;
 use std::ffi::OsString;
 use std::fs;
-use std::io::{Read};
+use std::io::{self, BufReader, Read};
 use std::path;
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::thread;
+use zip::read::ZipArchive;
+use zip::result::ZipError;
 
 use super::input_source::*;
 
@@ -115,6 +122,38 @@ impl Iterator for Files {
 }
 
 
+/// Open a file, decompressing it on the fly if it ends on `.gz` or `.bz2`.
+pub fn open_file(path: &path::Path) -> Result<Box<Read>> {
+    let file = BufReader::new(fs::File::open(path).map_err(|e|
+            TransformationError::IoError(e, PositionType::InDirectory(path.to_path_buf())))?);
+    Ok(match path.extension().and_then(|e| e.to_str()) {
+        Some("gz") => Box::new(GzDecoder::new(file)?),
+        Some("bz2") => Box::new(BzDecoder::new(file)),
+        _ => Box::new(file),
+    })
+}
+
+/// Recursively collect all files with the given extension below a directory
+///
+/// In contrast to [`Files`](struct.Files.html), sub directories are descended into. The paths
+/// are returned in sorted order, so that the processing order is stable across runs.
+pub fn find_files(directory: &path::Path, extension: &str) -> Result<Vec<path::PathBuf>> {
+    let mut found = Vec::new();
+    let mut directories = vec![directory.to_path_buf()];
+    while let Some(directory) = directories.pop() {
+        for entry in fs::read_dir(&directory)? {
+            let entry_path = entry?.path();
+            if entry_path.is_dir() {
+                directories.push(entry_path);
+            } else if entry_path.extension().map(|e| e == extension).unwrap_or(false) {
+                found.push(entry_path);
+            }
+        }
+    }
+    found.sort();
+    Ok(found)
+}
+
 /// Return content of a file found below given path
 ///
 /// This function creates an iterator which recurses all files in a given
@@ -136,6 +175,312 @@ pub fn read_files(input: path::PathBuf, extension: OsString)
 
 
 
+/// Streaming UTF-16 to UTF-8 decoder
+///
+/// This reader wraps another reader providing UTF-16 encoded data and emits the data as UTF-8.
+/// The byte order is determined from the byte order mark (BOM) at the beginning of the stream; if
+/// it is missing, little endian is assumed. The BOM itself is not part of the output. Only a small
+/// buffer is held in memory, so that arbitrarily large files can be decoded, e.g. to feed them into
+/// an XML parser.
+pub struct Utf16Reader<R: Read> {
+    inner: R,
+    big_endian: bool,
+    /// whether the beginning of the stream has been examined for a BOM
+    bom_checked: bool,
+    /// raw bytes read, but not decoded yet (e.g. half a code unit)
+    undecoded: Vec<u8>,
+    /// a high surrogate waiting for its low counterpart
+    high_surrogate: Option<u16>,
+    /// decoded UTF-8 data, not yet returned to the caller
+    decoded: Vec<u8>,
+    decoded_position: usize,
+    end_of_input: bool,
+}
+
+impl<R: Read> Utf16Reader<R> {
+    pub fn new(inner: R) -> Utf16Reader<R> {
+        Utf16Reader::with_byte_order(inner, false)
+    }
+
+    /// Create a decoder assuming the given byte order, if the stream doesn't start with a BOM.
+    pub fn with_byte_order(inner: R, big_endian: bool) -> Utf16Reader<R> {
+        Utf16Reader { inner, big_endian, bom_checked: false,
+            undecoded: Vec::with_capacity(8192), high_surrogate: None,
+            decoded: Vec::with_capacity(8192), decoded_position: 0, end_of_input: false }
+    }
+
+    // read another chunk from the underlying reader and decode it
+    fn fill_buffer(&mut self) -> io::Result<()> {
+        let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg.to_string());
+        let mut chunk = [0u8; 8192];
+        let bytes_read = self.inner.read(&mut chunk)?;
+        if bytes_read == 0 {
+            self.end_of_input = true;
+            if !self.undecoded.is_empty() || self.high_surrogate.is_some() {
+                return Err(invalid("UTF-16 stream ended within a character"));
+            }
+            return Ok(());
+        }
+        self.undecoded.extend_from_slice(&chunk[..bytes_read]);
+        if !self.bom_checked {
+            if self.undecoded.len() < 2 {
+                return Ok(()); // wait for more data
+            }
+            match (self.undecoded[0], self.undecoded[1]) {
+                (0xFF, 0xFE) => { self.undecoded.drain(..2); },
+                (0xFE, 0xFF) => {
+                    self.big_endian = true;
+                    self.undecoded.drain(..2);
+                },
+                _ => (),
+            }
+            self.bom_checked = true;
+        }
+
+        self.decoded.clear();
+        self.decoded_position = 0;
+        let usable = self.undecoded.len() - (self.undecoded.len() % 2);
+        let mut encoded = [0u8; 4];
+        for pair in self.undecoded[..usable].chunks(2) {
+            let unit = match self.big_endian {
+                true => ((pair[0] as u16) << 8) | pair[1] as u16,
+                false => ((pair[1] as u16) << 8) | pair[0] as u16,
+            };
+            let code_point = match (self.high_surrogate.take(), unit) {
+                (Some(high), 0xDC00..=0xDFFF) =>
+                    0x10000 + (((high as u32) - 0xD800) << 10) + ((unit as u32) - 0xDC00),
+                (Some(_), _) => return Err(invalid("unpaired UTF-16 high surrogate")),
+                (None, 0xD800..=0xDBFF) => {
+                    self.high_surrogate = Some(unit);
+                    continue;
+                },
+                (None, 0xDC00..=0xDFFF) => return Err(invalid("unpaired UTF-16 low surrogate")),
+                (None, _) => unit as u32,
+            };
+            // all surrogates are handled above, so the code point is valid
+            let character = ::std::char::from_u32(code_point).unwrap();
+            self.decoded.extend_from_slice(character.encode_utf8(&mut encoded).as_bytes());
+        }
+        self.undecoded.drain(..usable);
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for Utf16Reader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.decoded_position >= self.decoded.len() {
+            if self.end_of_input {
+                return Ok(0);
+            }
+            self.fill_buffer()?;
+        }
+        let available = &self.decoded[self.decoded_position..];
+        let count = ::std::cmp::min(available.len(), buf.len());
+        buf[..count].copy_from_slice(&available[..count]);
+        self.decoded_position += count;
+        Ok(count)
+    }
+}
+
+
+// Windows-1252 characters for the bytes 0x80 - 0x9F, unassigned bytes are mapped to the
+// corresponding C1 control characters like ISO 8859-1 does
+static WINDOWS_1252: [char; 32] = ['€', '\u{81}', '‚', 'ƒ', '„', '…', '†', '‡', 'ˆ', '‰', 'Š',
+    '‹', 'Œ', '\u{8D}', 'Ž', '\u{8F}', '\u{90}', '‘', '’', '“', '”', '•', '–', '—', '˜', '™', 'š',
+    '›', 'œ', '\u{9D}', 'ž', 'Ÿ'];
+
+/// Streaming ISO 8859-1 or Windows-1252 to UTF-8 decoder
+pub struct SingleByteReader<R: Read> {
+    inner: R,
+    windows_1252: bool,
+    decoded: Vec<u8>,
+    decoded_position: usize,
+}
+
+impl<R: Read> SingleByteReader<R> {
+    /// Create a new decoder; if `windows_1252` is false, ISO 8859-1 is assumed.
+    pub fn new(inner: R, windows_1252: bool) -> SingleByteReader<R> {
+        SingleByteReader { inner, windows_1252, decoded: Vec::with_capacity(8192),
+            decoded_position: 0 }
+    }
+}
+
+impl<R: Read> Read for SingleByteReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.decoded_position >= self.decoded.len() {
+            let mut chunk = [0u8; 4096];
+            let bytes_read = self.inner.read(&mut chunk)?;
+            if bytes_read == 0 {
+                return Ok(0);
+            }
+            self.decoded.clear();
+            self.decoded_position = 0;
+            let mut encoded = [0u8; 4];
+            for &byte in chunk[..bytes_read].iter() {
+                let character = match byte {
+                    0x80..=0x9F if self.windows_1252 => WINDOWS_1252[(byte - 0x80) as usize],
+                    _ => byte as char,
+                };
+                self.decoded.extend_from_slice(character.encode_utf8(&mut encoded).as_bytes());
+            }
+        }
+        let available = &self.decoded[self.decoded_position..];
+        let count = ::std::cmp::min(available.len(), buf.len());
+        buf[..count].copy_from_slice(&available[..count]);
+        self.decoded_position += count;
+        Ok(count)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum XmlEncoding {
+    /// UTF-8 with the number of bytes to skip for the BOM
+    Utf8(u64),
+    /// UTF-16, big endian if true
+    Utf16(bool),
+    Latin1,
+    Windows1252,
+}
+
+// detect the encoding of an XML document from its first bytes
+fn detect_xml_encoding(head: &[u8]) -> io::Result<XmlEncoding> {
+    if head.starts_with(&[0xEF, 0xBB, 0xBF]) {
+        return Ok(XmlEncoding::Utf8(3));
+    } else if head.starts_with(&[0xFF, 0xFE]) || head.starts_with(&[b'<', 0]) {
+        return Ok(XmlEncoding::Utf16(false));
+    } else if head.starts_with(&[0xFE, 0xFF]) || head.starts_with(&[0, b'<']) {
+        return Ok(XmlEncoding::Utf16(true));
+    }
+    // ASCII-compatible encoding, look at the XML declaration
+    let head = String::from_utf8_lossy(head);
+    let declaration = match (head.starts_with("<?xml"), head.find("?>")) {
+        (true, Some(end)) => &head[..end],
+        _ => return Ok(XmlEncoding::Utf8(0)),
+    };
+    let encoding = match declaration.find("encoding") {
+        Some(start) => declaration[start + 8..].trim_start_matches(|c: char|
+                c == '=' || c == '"' || c == '\'' || c.is_whitespace())
+            .split(|c: char| c == '"' || c == '\'' || c.is_whitespace())
+            .next().unwrap_or("").to_lowercase(),
+        None => return Ok(XmlEncoding::Utf8(0)),
+    };
+    match encoding.as_str() {
+        "utf-8" | "utf8" | "us-ascii" | "ascii" | "utf-16" => Ok(XmlEncoding::Utf8(0)),
+        "iso-8859-1" | "iso8859-1" | "iso_8859-1" | "latin1" | "latin-1" =>
+            Ok(XmlEncoding::Latin1),
+        "windows-1252" | "cp1252" => Ok(XmlEncoding::Windows1252),
+        other => Err(io::Error::new(io::ErrorKind::InvalidData,
+                format!("unsupported XML encoding: {}", other))),
+    }
+}
+
+/// Wrap a reader providing an XML document, so that it emits UTF-8
+///
+/// The XML parser only understands UTF-8. This function detects the encoding of a document from
+/// its byte order mark, the byte pattern of the first `<` or the encoding from the XML
+/// declaration and decodes the document on the fly. UTF-8, UTF-16 (both byte orders), ISO 8859-1
+/// and Windows-1252 are supported.
+pub fn decoding_reader<'a, R: Read + 'a>(mut input: R) -> io::Result<Box<Read + 'a>> {
+    let mut head = vec![0u8; 512];
+    let mut length = 0;
+    while length < head.len() {
+        match input.read(&mut head[length..])? {
+            0 => break,
+            bytes_read => length += bytes_read,
+        }
+    }
+    head.truncate(length);
+    let encoding = detect_xml_encoding(&head)?;
+    let mut head = io::Cursor::new(head);
+    Ok(match encoding {
+        XmlEncoding::Utf8(skip) => {
+            head.set_position(skip);
+            Box::new(head.chain(input))
+        },
+        XmlEncoding::Utf16(big_endian) =>
+            Box::new(Utf16Reader::with_byte_order(head.chain(input), big_endian)),
+        XmlEncoding::Latin1 => Box::new(SingleByteReader::new(head.chain(input), false)),
+        XmlEncoding::Windows1252 => Box::new(SingleByteReader::new(head.chain(input), true)),
+    })
+}
+
+// size of the blocks in which zip entries are passed on
+const ZIP_BLOCK_SIZE: usize = 65536;
+// number of blocks which may be decompressed ahead of the reader
+const ZIP_BLOCKS_AHEAD: usize = 4;
+
+/// Streaming reader of a single zip archive entry
+///
+/// An entry of a `ZipArchive` borrows the archive, so it cannot be kept in an iterator next to
+/// the archive it belongs to. This reader opens the archive itself and decompresses the entry in
+/// a background thread, which passes the data on in blocks of 64 KiB. Only a few blocks are held
+/// in memory at any time, so that even the largest entries, e.g. the DGT translation memories,
+/// are never read into RAM as a whole. Errors of the archive are reported on read.
+pub struct ZipEntryReader {
+    blocks: Receiver<io::Result<Vec<u8>>>,
+    block: Vec<u8>,
+    position: usize,
+}
+
+impl ZipEntryReader {
+    /// Open the entry with the given index of a zip archive.
+    pub fn new(archive: &path::Path, index: usize) -> ZipEntryReader {
+        let (sender, blocks) = mpsc::sync_channel(ZIP_BLOCKS_AHEAD);
+        let archive = archive.to_path_buf();
+        thread::spawn(move || {
+            if let Err(e) = send_zip_entry(&archive, index, &sender) {
+                // fails only if the reader has been dropped already
+                let _ = sender.send(Err(e));
+            }
+        });
+        ZipEntryReader { blocks, block: Vec::new(), position: 0 }
+    }
+}
+
+fn zip_to_io_error(error: ZipError) -> io::Error {
+    match error {
+        ZipError::Io(e) => e,
+        other => io::Error::new(io::ErrorKind::InvalidData, other.to_string()),
+    }
+}
+
+// decompress a zip entry block by block; stops once the reader has been dropped
+fn send_zip_entry(archive: &path::Path, index: usize, sender: &SyncSender<io::Result<Vec<u8>>>)
+        -> io::Result<()> {
+    let mut archive = ZipArchive::new(fs::File::open(archive)?).map_err(zip_to_io_error)?;
+    let mut entry = archive.by_index(index).map_err(zip_to_io_error)?;
+    loop {
+        let mut block = vec![0u8; ZIP_BLOCK_SIZE];
+        let length = entry.read(&mut block)?;
+        if length == 0 {
+            return Ok(());
+        }
+        block.truncate(length);
+        if sender.send(Ok(block)).is_err() {
+            return Ok(());
+        }
+    }
+}
+
+impl Read for ZipEntryReader {
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        while self.position >= self.block.len() {
+            match self.blocks.recv() {
+                Ok(block) => {
+                    self.block = block?;
+                    self.position = 0;
+                },
+                Err(_) => return Ok(0), // entry completely read
+            }
+        }
+        let length = cmp::min(buffer.len(), self.block.len() - self.position);
+        buffer[..length].copy_from_slice(&self.block[self.position..self.position + length]);
+        self.position += length;
+        Ok(length)
+    }
+}
+
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -154,5 +499,85 @@ mod tests {
         let links = extract_links(html);
         assert_eq!(links.len(), 1);
     }
-}
 
+    fn decode_utf16(data: &[u8]) -> io::Result<String> {
+        let mut output = String::new();
+        Utf16Reader::new(data).read_to_string(&mut output)?;
+        Ok(output)
+    }
+
+    #[test]
+    fn test_that_utf16_byte_order_is_detected_from_bom() {
+        assert_eq!(decode_utf16(&[0xFF, 0xFE, b'a', 0, 0xFC, 0]).unwrap(), "aü");
+        assert_eq!(decode_utf16(&[0xFE, 0xFF, 0, b'a', 0, 0xFC]).unwrap(), "aü");
+        // no BOM: little endian
+        assert_eq!(decode_utf16(&[b'a', 0]).unwrap(), "a");
+    }
+
+    #[test]
+    fn test_that_utf16_surrogate_pairs_are_decoded() {
+        // U+1D11E MUSICAL SYMBOL G CLEF
+        assert_eq!(decode_utf16(&[0xFF, 0xFE, 0x34, 0xD8, 0x1E, 0xDD]).unwrap(), "\u{1D11E}");
+    }
+
+    #[test]
+    fn test_that_truncated_utf16_is_an_error() {
+        assert!(decode_utf16(&[0xFF, 0xFE, b'a']).is_err());
+        assert!(decode_utf16(&[0xFF, 0xFE, 0x34, 0xD8]).is_err());
+    }
+
+    fn decode_xml(data: &[u8]) -> String {
+        let mut output = String::new();
+        decoding_reader(data).unwrap().read_to_string(&mut output).unwrap();
+        output
+    }
+
+    #[test]
+    fn test_that_xml_encoding_is_detected() {
+        assert_eq!(decode_xml(b"\xEF\xBB\xBF<a/>"), "<a/>");
+        assert_eq!(decode_xml(&[b'<', 0, b'a', 0, b'/', 0, b'>', 0]), "<a/>");
+        assert_eq!(decode_xml(b"<?xml version=\"1.0\" encoding=\"ISO-8859-1\"?><a>\xFC</a>"),
+                "<?xml version=\"1.0\" encoding=\"ISO-8859-1\"?><a>ü</a>");
+        assert_eq!(decode_xml(b"<?xml version='1.0' encoding='windows-1252'?><a>\x80</a>"),
+                "<?xml version='1.0' encoding='windows-1252'?><a>€</a>");
+    }
+
+    #[test]
+    fn test_that_unknown_xml_encodings_are_rejected() {
+        assert!(decoding_reader(&b"<?xml version=\"1.0\" encoding=\"KOI8-R\"?><a/>"[..])
+                .is_err());
+    }
+
+    fn write_zip(name: &str, entries: &[(&str, &[u8])]) -> path::PathBuf {
+        use std::io::Write;
+        let path = ::std::env::temp_dir().join(name);
+        let mut zip = ::zip::ZipWriter::new(fs::File::create(&path).unwrap());
+        for &(entry, data) in entries {
+            zip.start_file(entry, ::zip::write::FileOptions::default()).unwrap();
+            zip.write_all(data).unwrap();
+        }
+        zip.finish().unwrap();
+        path
+    }
+
+    #[test]
+    fn test_that_zip_entries_are_streamed() {
+        let large: Vec<u8> = (0..200_000u32).map(|i| (i % 251) as u8).collect();
+        let path = write_zip("craft_common_entries.zip", &[("a.txt", b"first"),
+            ("b.bin", &large)]);
+        let mut output = Vec::new();
+        ZipEntryReader::new(&path, 1).read_to_end(&mut output).unwrap();
+        assert_eq!(output, large);
+        let mut output = String::new();
+        ZipEntryReader::new(&path, 0).read_to_string(&mut output).unwrap();
+        assert_eq!(output, "first");
+    }
+
+    #[test]
+    fn test_that_missing_zip_entries_are_an_error() {
+        let path = write_zip("craft_common_missing.zip", &[("a.txt", b"first")]);
+        assert!(ZipEntryReader::new(&path, 1).read_to_end(&mut Vec::new()).is_err());
+        let missing = ::std::env::temp_dir().join("craft_common_does_not_exist.zip");
+        assert!(ZipEntryReader::new(&missing, 0).read_to_end(&mut Vec::new()).is_err());
+    }
+}
@@ -0,0 +1,224 @@
+//! Dictionary coverage of a corpus
+//!
+//! Word2vec only learns vectors for words occurring at least `min_count` times. When a corpus is
+//! built for a FreeDict language pair, it is therefore of interest how many of the headwords of
+//! the dictionary occur often enough. A [`Vocabulary`](struct.Vocabulary.html) counts the words
+//! of the corpus per input module; a [`CoverageReport`](struct.CoverageReport.html) compares it
+//! against a headword list.
+//!
+//! Headwords are matched case-sensitively, as the corpus is not lower-cased. Headwords consisting
+//! of several words are looked up with the words joined by `_`, as written by phrase detection.
+
+use isolang::Language;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+
+use common;
+use input_source::{PositionType, Result, TransformationError};
+use modules::{freedict, tmx};
+use textfilter::RETURN_ESCAPE_SEQUENCE;
+
+/// Minimum counts at which the coverage is reported, unless configured otherwise.
+pub static DEFAULT_THRESHOLDS: [u64; 5] = [1, 5, 10, 50, 100];
+
+/// Word counts of a corpus, per input module.
+#[derive(Debug, Default)]
+pub struct Vocabulary {
+    /// module name → word → count
+    counts: BTreeMap<String, HashMap<String, u64>>,
+}
+
+impl Vocabulary {
+    pub fn new() -> Vocabulary {
+        Vocabulary::default()
+    }
+
+    /// Count the words of a text (as returned by `text2words`) for the given module.
+    pub fn add_text(&mut self, module: &str, text: &str) {
+        let counts = self.counts.entry(module.to_string()).or_insert_with(HashMap::new);
+        for word in text.split_whitespace() {
+            if word.starts_with(RETURN_ESCAPE_SEQUENCE) {
+                continue;
+            }
+            *counts.entry(word.to_string()).or_insert(0) += 1;
+        }
+    }
+
+    /// Add the given number of occurrences of a word to the counts of a module.
+    pub fn add_count(&mut self, module: &str, word: &str, count: u64) {
+        *self.counts.entry(module.to_string()).or_insert_with(HashMap::new)
+            .entry(word.to_string()).or_insert(0) += count;
+    }
+
+    /// Number of occurrences of a word in the whole corpus.
+    pub fn count(&self, word: &str) -> u64 {
+        self.counts.values().map(|c| c.get(word).cloned().unwrap_or(0)).sum()
+    }
+
+    /// Number of occurrences of a word in the text of one module.
+    pub fn count_in(&self, module: &str, word: &str) -> u64 {
+        self.counts.get(module).and_then(|c| c.get(word)).cloned().unwrap_or(0)
+    }
+
+    /// Names of all modules which contributed text, sorted.
+    pub fn modules(&self) -> Vec<&str> {
+        self.counts.keys().map(|m| m.as_str()).collect()
+    }
+
+    /// Write the vocabulary as tab-separated `module`, `word` and `count` columns.
+    pub fn write<W: Write>(&self, output: &mut W) -> Result<()> {
+        for (module, counts) in &self.counts {
+            let mut words: Vec<(&String, &u64)> = counts.iter().collect();
+            words.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+            for (word, count) in words {
+                writeln!(output, "{}\t{}\t{}", module, word, count)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Read a vocabulary written by [`write`](#method.write).
+    pub fn read<R: BufRead>(input: R) -> Result<Vocabulary> {
+        let mut vocabulary = Vocabulary::new();
+        for line in input.lines() {
+            let line = line?;
+            let columns: Vec<&str> = line.split('\t').collect();
+            let count = match columns.len() {
+                3 => columns[2].parse::<u64>().ok(),
+                _ => None,
+            };
+            match count {
+                Some(count) => vocabulary.add_count(columns[0], columns[1], count),
+                None if line.trim().is_empty() => (),
+                None => return Err(TransformationError::ErrorneousStructure(format!(
+                        "expected module, word and count, got: {}", line),
+                        PositionType::None)),
+            }
+        }
+        Ok(vocabulary)
+    }
+}
+
+/// Read a headword list, either a FreeDict dictionary (`.tei`), a TBX term base (`.tbx` or
+/// `.tbx.gz`) or a plain text file with one headword per line.
+///
+/// Empty lines and lines starting with `#` are ignored in plain text lists. If a language is
+/// given and the dictionary has another source language, an empty list is returned; of a term
+/// base, only the terms of that language are read.
+pub fn read_headwords(path: &Path, language: Option<&Language>) -> Result<BTreeSet<String>> {
+    let name = path.file_name().map(|n| n.to_string_lossy().to_lowercase()).unwrap_or_default();
+    if name.ends_with(".tbx") || name.ends_with(".tbx.gz") {
+        return Ok(tmx::read_tbx_terms(path, language)?.into_iter().collect());
+    }
+    let is_tei = path.extension().map(|e| e == "tei").unwrap_or(false);
+    if is_tei {
+        return match language {
+            Some(language) => freedict::read_headwords(path, language),
+            None => {
+                let mut headwords = BTreeSet::new();
+                for entry in freedict::read_dictionary(path)? {
+                    headwords.extend(entry?.headwords);
+                }
+                Ok(headwords)
+            },
+        };
+    }
+    let mut headwords = BTreeSet::new();
+    for line in BufReader::new(common::open_file(path)?).lines() {
+        let line = line?;
+        let line = line.trim();
+        if !line.is_empty() && !line.starts_with('#') {
+            headwords.insert(line.to_string());
+        }
+    }
+    Ok(headwords)
+}
+
+/// Form of a headword as found in the corpus: the words of multiword headwords are joined by
+/// underscores, as done by `crafted phrases`.
+pub fn corpus_form(headword: &str) -> String {
+    headword.split_whitespace().collect::<Vec<_>>().join("_")
+}
+
+/// Coverage of a headword list by a corpus vocabulary
+#[derive(Debug)]
+pub struct CoverageReport {
+    /// number of headwords
+    pub headwords: usize,
+    /// minimum count and number of headwords occurring at least that often
+    pub thresholds: Vec<(u64, usize)>,
+    /// for each module, the number of headwords occurring at least at the lowest threshold
+    pub modules: Vec<(String, usize)>,
+    /// headwords occurring less often than the lowest threshold
+    pub missing: Vec<String>,
+}
+
+impl CoverageReport {
+    /// Compute the coverage of the given headwords at each of the given minimum counts.
+    pub fn new(headwords: &BTreeSet<String>, vocabulary: &Vocabulary, thresholds: &[u64])
+            -> CoverageReport {
+        let mut thresholds = thresholds.to_vec();
+        thresholds.sort();
+        thresholds.dedup();
+        let lowest = thresholds.first().cloned().unwrap_or(1);
+        let mut covered = vec![0; thresholds.len()];
+        let mut missing = Vec::new();
+        let modules = vocabulary.modules();
+        let mut module_coverage = vec![0; modules.len()];
+        for headword in headwords {
+            let form = corpus_form(headword);
+            let count = vocabulary.count(&form);
+            for (index, threshold) in thresholds.iter().enumerate() {
+                if count >= *threshold {
+                    covered[index] += 1;
+                }
+            }
+            if count < lowest {
+                missing.push(headword.clone());
+            }
+            for (index, module) in modules.iter().enumerate() {
+                if vocabulary.count_in(module, &form) >= lowest {
+                    module_coverage[index] += 1;
+                }
+            }
+        }
+        CoverageReport {
+            headwords: headwords.len(),
+            thresholds: thresholds.into_iter().zip(covered).collect(),
+            modules: modules.into_iter().map(String::from).zip(module_coverage).collect(),
+            missing,
+        }
+    }
+
+    /// Percentage of headwords with the given number of covered headwords.
+    pub fn percentage(&self, covered: usize) -> f64 {
+        match self.headwords {
+            0 => 0.0,
+            total => covered as f64 * 100.0 / total as f64,
+        }
+    }
+}
+
+impl fmt::Display for CoverageReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "headwords: {}", self.headwords)?;
+        writeln!(f, "\ncoverage by minimum count:")?;
+        for &(threshold, covered) in &self.thresholds {
+            writeln!(f, "  >= {:<6} {:>8} ({:.1} %)", threshold, covered,
+                     self.percentage(covered))?;
+        }
+        if let Some(&(lowest, _)) = self.thresholds.first() {
+            writeln!(f, "\ncoverage by module (minimum count {}):", lowest)?;
+        }
+        for &(ref module, covered) in &self.modules {
+            writeln!(f, "  {:<15} {:>8} ({:.1} %)", module, covered, self.percentage(covered))?;
+        }
+        writeln!(f, "\nmissing headwords ({}):", self.missing.len())?;
+        for headword in &self.missing {
+            writeln!(f, "{}", headword)?;
+        }
+        Ok(())
+    }
+}
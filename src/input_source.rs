@@ -160,25 +160,46 @@ impl From<::zip::result::ZipError> for TransformationError {
     }
 }
 
+/// Descriptive information about an entity.
+///
+/// Only few input sources ship metadata alongside their texts, hence all fields are optional.
+/// Values are kept as found in the source, no normalisation takes place.
+#[derive(Clone, Debug, Default)]
+pub struct Metadata {
+    /// title of the article, book or chapter
+    pub title: Option<String>,
+    /// publication date
+    pub date: Option<String>,
+    /// language as stated by the source (not necessarily an ISO code)
+    pub language: Option<String>,
+    /// institution or project which provided the text
+    pub provider: Option<String>,
+}
+
 /// Smallest unit of processing.
 ///
 /// An entity is the smallest unit of processing. An entity can be an article, a
-/// whole book or whatever seems feasible as a split point. It has two
-/// characteristics: it holds the actual data and information about where the
-/// entity came from.
+/// whole book or whatever seems feasible as a split point. It has three
+/// characteristics: it holds the actual data, information about where the
+/// entity came from and optionally some metadata.
 pub struct Entity {
     pub content: String,
     pub position: PositionType,
+    pub metadata: Metadata,
 }
 
 impl Entity {
+    pub fn new(content: String, position: PositionType) -> Entity {
+        Entity { content, position, metadata: Metadata::default() }
+    }
+
     pub fn with_path(c: String, p: PathBuf) -> Entity {
-        Entity { content: c, position: PositionType::InDirectory(p) }
+        Entity::new(c, PositionType::InDirectory(p))
     }
 
     pub fn with_exact_pos(content: String, path: PathBuf, line: u64, col: u64)
             -> Entity {
-        Entity { content, position: PositionType::InFile(path, line, col) }
+        Entity::new(content, PositionType::InFile(path, line, col))
     }
 
     /// Update the String content of the entity.
@@ -188,6 +209,18 @@ impl Entity {
 }
 
 
+/// A sentence (or text segment) and its translation.
+///
+/// Parallel corpora and translation memories can provide aligned pairs instead of monolingual
+/// text, e.g. to train bilingual models.
+pub struct AlignedPair {
+    /// text in the configured language
+    pub source: String,
+    /// text in the language to align with
+    pub target: String,
+    pub position: PositionType,
+}
+
 /// Return the corresponding iterator for a given input source.
 /// Strip formatting from a document
 ///
@@ -210,5 +243,14 @@ pub trait Unformatter {
     /// easier. Preprocessing functions might also strip parts of the documents, which are not
     /// intended for the corpus.
     fn preprocess(&self, input: &Entity) -> Result<Entity>;
+
+    /// Return the file pandoc should read the entity from.
+    ///
+    /// Binary formats like DOCX or ODT cannot be piped to pandoc as a string. Unformatters for
+    /// these formats return the path of the document here; the content of the entity is ignored
+    /// and no preprocessing takes place. By default, the content is piped to pandoc.
+    fn input_file(&self, _input: &Entity) -> Result<Option<PathBuf>> {
+        Ok(None)
+    }
 }
 
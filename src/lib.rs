@@ -4,12 +4,16 @@
 //! networks, more precisely for Word2vec. While it has been developed to work for the thesaurus
 //! generator **Alt**, it can be used for any text processing purposes.
 extern crate bzip2;
+extern crate flate2;
 extern crate isolang;
 extern crate json;
 extern crate htmlstream;
 #[macro_use]
 extern crate log;
 extern crate pandoc;
+extern crate rust_stemmers;
+extern crate unic_ucd_category;
+extern crate unicode_segmentation;
 extern crate xml;
 extern crate zip;
 
@@ -22,3 +26,18 @@ pub mod textfilter;
 /// input sources
 pub mod modules;
 
+
+/// dictionary coverage of the corpus vocabulary
+pub mod coverage;
+
+/// phrase detection
+pub mod phrases;
+
+/// word2vec training
+pub mod word2vec;
+
+/// thesaurus generation from word vectors
+pub mod thesaurus;
+
+/// bilingual alignment of word vectors
+pub mod alignment;
@@ -13,6 +13,7 @@
 /// - 24: error in configuration file
 
 extern crate craft;
+extern crate getopts;
 extern crate isolang;
 #[macro_use]
 extern crate log;
@@ -30,12 +31,13 @@ use log4rs::file::{Deserializers, RawConfig};
 use std::collections::{HashMap, HashSet};
 use std::env;
 use std::fs::File;
-use std::io::Write;
+use std::io::{self, BufRead, BufReader, Read, Write};
 use std::path::PathBuf;
+use std::str::FromStr;
 
-use craft::{common, textfilter};
+use craft::{alignment, common, coverage, phrases, textfilter, thesaurus, word2vec};
 use craft::modules::*;
-use craft::input_source::{self, Entity, Unformatter};
+use craft::input_source::{self, AlignedPair, Entity, Unformatter};
 
 macro_rules! trylog(
     ($thing:expr, $msg:expr, $ret:expr) => (match $thing {
@@ -102,11 +104,92 @@ fn error_exit(msg: &str, exit_code: i32) {
 #[derive(Deserialize)]
 struct LanguageCfg {
     wikipedia: Option<PathBuf>,
+    /// bzip2-compressed Wikisource dump
+    wikisource: Option<PathBuf>,
+    /// names of header templates to remove from Wikisource works
+    wikisource_header_templates: Option<Vec<String>>,
+    /// bzip2-compressed Wiktionary dump
+    wiktionary: Option<PathBuf>,
+    /// name of the language sections, defaults to the English name of the language
+    wiktionary_section: Option<String>,
+    /// file to write a headword/part of speech/gloss table to
+    wiktionary_glosses: Option<PathBuf>,
     gutenberg: Option<PathBuf>,
     dgt: Option<PathBuf>,
     europeana: Option<PathBuf>,
+    /// minimum OCR quality score (0-1) of Europeana articles
+    europeana_min_quality: Option<f32>,
+    alto: Option<PathBuf>,
+    /// minimum OCR word confidence (0-1) of words from ALTO files
+    alto_min_confidence: Option<f32>,
+    /// TMX file or directory with TMX files
+    tmx: Option<PathBuf>,
+    /// ISO 639-3 code; if given, aligned pairs are written instead of monolingual text
+    tmx_align_with: Option<String>,
+    /// directory with line-aligned corpus files (Moses format)
+    moses: Option<PathBuf>,
+    /// file suffix of the corpus files of this language, defaults to the ISO 639-1 code
+    moses_suffix: Option<String>,
+    /// ISO 639-3 code; if given, aligned pairs are written instead of monolingual text
+    moses_align_with: Option<String>,
+    /// file suffix of the aligned files, defaults to the ISO 639-1 code of moses_align_with
+    moses_target_suffix: Option<String>,
+    /// Tatoeba export: directory with sentences.csv and links.csv
+    tatoeba: Option<PathBuf>,
+    /// ISO 639-3 code; if given, aligned pairs are written instead of monolingual text
+    tatoeba_align_with: Option<String>,
+    /// directory with EPUB files
+    epub: Option<PathBuf>,
+    /// if given, only books whose rights statement contains one of these terms are read
+    epub_rights: Option<Vec<String>>,
+    /// directory with HTML files and WARC archives
+    html: Option<PathBuf>,
+    /// minimum number of words of a text block to be considered content
+    html_min_words: Option<usize>,
+    /// directory with office documents (.odt, .docx, .rtf)
+    documents: Option<PathBuf>,
+    /// directory with CoNLL-U treebanks
+    conllu: Option<PathBuf>,
+    /// tokens to write: surface (default), lemma or lemma_pos
+    conllu_tokens: Option<String>,
+    /// directory with subtitles (.srt, .vtt, OpenSubtitles .xml/.xml.gz)
+    subtitles: Option<PathBuf>,
+    /// silence between two cues (in seconds) which starts a new scene
+    subtitles_scene_gap: Option<f64>,
+    /// directory with TEI P5 documents (.xml, .tei)
+    tei: Option<PathBuf>,
+    /// FreeDict dictionary (.tei) or directory with dictionaries; example quotes are extracted
+    freedict: Option<PathBuf>,
+    /// file to write the headwords of the FreeDict dictionaries to, one per line
+    freedict_headwords: Option<PathBuf>,
+    /// headword list (plain text, FreeDict .tei or TBX) to report the coverage of the corpus
+    coverage_headwords: Option<PathBuf>,
+    /// minimum counts at which the coverage is reported
+    coverage_thresholds: Option<Vec<u64>>,
     codecivil: Option<PathBuf>,
     stopwords: Option<String>,
+    /// normalisation of inflected forms: stem (Snowball) or lemmatize (requires lemma_list)
+    normalizer: Option<String>,
+    /// tab-separated form and lemma list for the lemmatizer
+    lemma_list: Option<PathBuf>,
+    /// word list for the segmentation of languages written without spaces (Chinese, Japanese,
+    /// Thai, ...)
+    lexicon: Option<PathBuf>,
+    /// elided articles (l'homme): keep, split (l' homme) or drop (homme)
+    elision: Option<String>,
+    /// elided prefixes without apostrophe, replacing the predefined ones of the language
+    elision_prefixes: Option<Vec<String>>,
+    /// clitics (John's): keep, split (John 's) or drop (John)
+    clitics: Option<String>,
+    /// clitic suffixes with apostrophe, replacing the predefined ones of the language
+    clitic_suffixes: Option<Vec<String>>,
+    /// characters to treat as punctuation, enclosing characters (quotes, parenthesis),
+    /// apostrophes or as joiners within words (like the hyphen), overriding their Unicode general
+    /// category
+    punctuation_chars: Option<String>,
+    enclosing_chars: Option<String>,
+    apostrophe_chars: Option<String>,
+    word_chars: Option<String>,
 }
 
 impl LanguageCfg {
@@ -124,6 +207,12 @@ impl LanguageCfg {
             if self.wikipedia.is_some() {
                 add("Wikipedia");
             }
+            if self.wikisource.is_some() {
+                add("Wikisource");
+            }
+            if self.wiktionary.is_some() {
+                add("Wiktionary");
+            }
             if self.gutenberg.is_some() {
                 add("Gutenberg");
             }
@@ -136,6 +225,39 @@ impl LanguageCfg {
             if self.codecivil.is_some() {
                 add("Code Civil");
             }
+            if self.alto.is_some() {
+                add("ALTO news papers");
+            }
+            if self.tmx.is_some() {
+                add("TMX translation memories");
+            }
+            if self.moses.is_some() {
+                add("Moses parallel corpora");
+            }
+            if self.tatoeba.is_some() {
+                add("Tatoeba");
+            }
+            if self.epub.is_some() {
+                add("EPUB books");
+            }
+            if self.html.is_some() {
+                add("web pages");
+            }
+            if self.documents.is_some() {
+                add("office documents");
+            }
+            if self.conllu.is_some() {
+                add("CoNLL-U treebanks");
+            }
+            if self.subtitles.is_some() {
+                add("subtitles");
+            }
+            if self.tei.is_some() {
+                add("TEI documents");
+            }
+            if self.freedict.is_some() {
+                add("FreeDict examples");
+            }
         }
         active
     }
@@ -147,6 +269,75 @@ struct JointConfig {
     log4rs: log4rs::file::RawConfig,
 }
 
+// parse the ISO 639-3 code of a language to align with, exit on invalid codes
+fn parse_target_language(code: &Option<String>) -> Option<Language> {
+    code.as_ref().map(|code| match Language::from_639_3(code) {
+        Some(lang) => lang,
+        None => {
+            error_exit(&format!("Invalid language to align with in configuration: {}",
+                    code), 24);
+            unreachable!();
+        }
+    })
+}
+
+// default file suffix of a language in line-aligned corpora: ISO 639-1 or ISO 639-3 code
+fn language_suffix(lang: &Language) -> String {
+    lang.to_639_1().unwrap_or(lang.to_639_3()).to_string()
+}
+
+// path of a per-language report file, derived from the output path
+fn report_path(output_path: &PathBuf, lang: &Language, extension: &str) -> PathBuf {
+    PathBuf::from(format!("{}.{}.{}", output_path.to_string_lossy(), lang.to_639_3(),
+            extension))
+}
+
+// path of the file holding the aligned pairs of a module for two languages, derived from the
+// output path
+fn pairs_path(output_path: &PathBuf, module: &str, lang: &Language, other: &Language)
+        -> PathBuf {
+    PathBuf::from(format!("{}.{}-{}.{}.tsv", output_path.to_string_lossy(), lang.to_639_3(),
+            other.to_639_3(), module))
+}
+
+// parse the comma-separated stop word list of a language
+fn stopword_set(stopwords: &Option<String>) -> Option<HashSet<String>> {
+    stopwords.as_ref().map(|words| words.split(",")
+            .map(|x| x.trim().into()).collect::<HashSet<String>>())
+}
+
+// character classes and affix handling of a language, exit on invalid configuration values
+fn char_classes(lang: &Language, lconf: &LanguageCfg) -> textfilter::CharClasses {
+    let mut classes = textfilter::CharClasses::for_language(lang);
+    let handling = |value: &Option<String>, key: &str| value.as_ref().map(|name|
+            match textfilter::AffixHandling::from_name(name) {
+        Some(handling) => handling,
+        None => {
+            error_exit(&format!("Invalid configuration: unknown value {} for {}, expected \
+                    keep, split or drop", name, key), 24);
+            unreachable!();
+        },
+    });
+    classes.elision = handling(&lconf.elision, "elision").unwrap_or(classes.elision);
+    classes.clitics = handling(&lconf.clitics, "clitics").unwrap_or(classes.clitics);
+    let overrides = [(&lconf.punctuation_chars, Some(textfilter::CharClass::Punctuation)),
+        (&lconf.enclosing_chars, Some(textfilter::CharClass::Enclosing)),
+        (&lconf.apostrophe_chars, Some(textfilter::CharClass::Apostrophe)),
+        (&lconf.word_chars, Some(textfilter::CharClass::Joiner))];
+    for (chars, class) in overrides.iter() {
+        for c in chars.iter().flat_map(|chars| chars.chars()) {
+            classes.set_class(c, *class);
+        }
+    }
+    if let Some(ref prefixes) = lconf.elision_prefixes {
+        classes.set_elisions(prefixes);
+    }
+    if let Some(ref suffixes) = lconf.clitic_suffixes {
+        classes.set_clitics(suffixes);
+    }
+    classes
+}
+
 fn setup_config(log_conf: &PathBuf) -> HashMap<Language, LanguageCfg> {
     let cfg = ::serde_yaml::from_reader::<File, JointConfig>(
         File::open(log_conf).expect("Couldn't open log file for reading"))
@@ -195,6 +386,15 @@ fn handle_error<E: ::std::error::Error + ?Sized>(e: &E) {
 
 fn main() {
     let args: Vec<String> = env::args().collect();
+    match args.get(1).map(|a| a.as_str()) {
+        Some("coverage") => return run_coverage(&args[0], &args[2..]),
+        Some("train") => return run_train(&args[0], &args[2..]),
+        Some("thesaurus") => return run_thesaurus(&args[0], &args[2..]),
+        Some("align") => return run_align(&args[0], &args[2..]),
+        Some("phrases") => return run_phrases(&args[0], &args[2..]),
+        Some("compounds") => return run_compounds(&args[0], &args[2..]),
+        _ => (),
+    }
 
     let (config_path, output_path) = trylog!(parse_cmd(&args[0], &args[1..]),
         "Errorneous command line", 1);
@@ -208,7 +408,9 @@ fn main() {
                 error_exit("please make sure that the output file is writable", 22);
                 unreachable!();
             },
-        Ok(f) => f
+        Ok(f) => CorpusWriter { output: CorpusFile::new(f), normalizer: None,
+            segmenter: textfilter::Segmenter::Whitespace,
+            characters: textfilter::CharClasses::default() },
     };
 
     macro_rules! canonicalize(
@@ -224,7 +426,33 @@ fn main() {
     for (lang, lconf) in config {
         info!("processing {}, active modules: {}", lang.to_name(),
             lconf.get_active_modules());
+        let stopwords = stopword_set(&lconf.stopwords);
+        if lconf.coverage_headwords.is_some() {
+            result_file.output.vocabulary = Some(coverage::Vocabulary::new());
+        }
+        result_file.normalizer = match lconf.normalizer.as_ref().map(|n| n.as_str()) {
+            Some("stem") => Some(Box::new(trylog!(textfilter::SnowballStemmer::new(&lang),
+                    "Unable to create stemmer", 24))),
+            Some("lemmatize") => {
+                let lemma_list = trylog!(canonicalize!(lconf.lemma_list.clone()).ok_or(
+                        "lemma_list is required for the lemmatizer"),
+                        "Invalid configuration", 24);
+                Some(Box::new(trylog!(textfilter::Lemmatizer::from_file(&lemma_list),
+                        "Unable to read lemma list", 2)))
+            },
+            Some(other) => {
+                error_exit(&format!("Invalid configuration: unknown normalizer {}, expected \
+                        stem or lemmatize", other), 24);
+                unreachable!();
+            },
+            None => None,
+        };
+        let lexicon = canonicalize!(lconf.lexicon.clone()).map(|path| trylog!(
+                textfilter::Lexicon::from_file(&path), "Unable to read lexicon", 2));
+        result_file.segmenter = textfilter::Segmenter::for_language(&lang, lexicon);
+        result_file.characters = char_classes(&lang, &lconf);
         if let Some(wp_path) = canonicalize!(lconf.wikipedia) {
+            result_file.set_module("wikipedia");
             info!("extracting Wikipedia articles from {}",
                   wp_path.to_string_lossy());
             //extract_text(wikipedia::ArticleParser::new(
@@ -234,41 +462,631 @@ fn main() {
             //        &mut result_file);
             extract_text(trylog!(wikipedia::parser_from_file(&wp_path), "Could not open input file", 1),
                     Some(Box::new(wikipedia::Wikipedia)),
-                    &lconf.stopwords,
+                    stopwords.as_ref(),
+                    &mut result_file);
+        }
+        if let Some(ws_path) = canonicalize!(lconf.wikisource) {
+            result_file.set_module("wikisource");
+            info!("extracting Wikisource works from {}", ws_path.to_string_lossy());
+            let mut works = trylog!(wikisource::Works::new(&ws_path),
+                    "Could not read Wikisource dump", 1);
+            if let Some(ref templates) = lconf.wikisource_header_templates {
+                works.set_header_templates(templates.clone());
+            }
+            extract_text(works, Some(Box::new(wikisource::Wikisource)),
+                    stopwords.as_ref(),
                     &mut result_file);
         }
+        if let Some(wkt_path) = canonicalize!(lconf.wiktionary) {
+            result_file.set_module("wiktionary");
+            let section = lconf.wiktionary_section.clone()
+                .unwrap_or(lang.to_name().to_string());
+            info!("extracting Wiktionary glosses and examples from section {} of {}", section,
+                  wkt_path.to_string_lossy());
+            let mut entries = trylog!(wiktionary::Entries::new(&wkt_path, &section),
+                    "Could not open input file", 1);
+            if let Some(glosses_path) = canonicalize!(lconf.wiktionary_glosses) {
+                let glosses_file = trylog!(File::create(&glosses_path),
+                        "please make sure that the glosses file is writable", 22);
+                entries.set_gloss_output(Box::new(::std::io::BufWriter::new(glosses_file)));
+            }
+            extract_text(entries, None, stopwords.as_ref(), &mut result_file);
+        }
         if let Some(gb_path) = canonicalize!(lconf.gutenberg) {
+            result_file.set_module("gutenberg");
             info!("Extracting Gutenberg books from {}",
                   gb_path.display());
             extract_text(common::read_files(gb_path.into(), "txt".into()),
                 Some(Box::new(gutenberg::Gutenberg)),
-                &lconf.stopwords,
+                stopwords.as_ref(),
+                &mut result_file);
+        }
+        if let Some(epub_path) = canonicalize!(lconf.epub) {
+            result_file.set_module("epub");
+            info!("Extracting EPUB books from {}", epub_path.display());
+            let mut books = trylog!(epub::EpubBooks::new(&epub_path, lang.clone()),
+                    "Unable to read from given directory", 2);
+            if let Some(ref rights) = lconf.epub_rights {
+                books.set_required_rights(rights.clone());
+            }
+            extract_text(books, Some(Box::new(epub::Epub)),
+                stopwords.as_ref(),
                 &mut result_file);
         }
+        if let Some(html_path) = canonicalize!(lconf.html) {
+            result_file.set_module("html");
+            info!("Extracting web pages from {}", html_path.display());
+            let mut pages = trylog!(html::WebDocuments::new(&html_path, lang.clone()),
+                    "Unable to read from given directory", 2);
+            if let Some(min_words) = lconf.html_min_words {
+                pages.set_min_words(min_words);
+            }
+            extract_text(pages, None, stopwords.as_ref(), &mut result_file);
+        }
+        if let Some(documents_path) = canonicalize!(lconf.documents) {
+            result_file.set_module("documents");
+            for format in documents::FORMATS.iter() {
+                info!("Extracting {} documents from {}", format.extension(),
+                      documents_path.display());
+                extract_text(trylog!(documents::OfficeDocuments::new(&documents_path, *format),
+                        "Unable to read from given directory", 2),
+                    Some(Box::new(documents::OfficeDocument(*format))),
+                    stopwords.as_ref(),
+                    &mut result_file);
+            }
+        }
         if let Some(europeana_path) = canonicalize!(lconf.europeana) {
+            result_file.set_module("europeana");
             info!("Extracting news paper articles from {}",
                   europeana_path.to_string_lossy());
             let input_path = PathBuf::from(&europeana_path);
-            extract_text(europeana::Articles::new(&input_path), None,
-                &lconf.stopwords,
+            let mut articles = europeana::Articles::new(&input_path);
+            if let Some(threshold) = lconf.europeana_min_quality {
+                articles.set_quality_threshold(threshold);
+            }
+            extract_text(articles, None,
+                stopwords.as_ref(),
+                &mut result_file);
+        }
+        if let Some(alto_path) = canonicalize!(lconf.alto) {
+            result_file.set_module("alto");
+            info!("Extracting ALTO news paper articles from {}",
+                  alto_path.to_string_lossy());
+            let mut documents = trylog!(alto::AltoDocuments::new(&alto_path),
+                "Unable to read from given directory", 2);
+            if let Some(confidence) = lconf.alto_min_confidence {
+                documents.set_min_confidence(confidence);
+            }
+            extract_text(documents, None,
+                stopwords.as_ref(),
                 &mut result_file);
         }
         if let Some(cc_path) = canonicalize!(lconf.codecivil) {
+            result_file.set_module("codecivil");
             info!("Extracting the code civil from {}",
                   cc_path.to_string_lossy());
             extract_text(common::read_files(cc_path.into(), "md".into()),
                 Some(Box::new(codecivil::CodeCivil)),
-                &lconf.stopwords,
+                stopwords.as_ref(),
+                &mut result_file);
+        }
+        if let Some(tmx_path) = canonicalize!(lconf.tmx) {
+            result_file.set_module("tmx");
+            match parse_target_language(&lconf.tmx_align_with) {
+                Some(other) => {
+                    let pairs_file = pairs_path(&output_path, "tmx", &lang, &other);
+                    info!("extracting {}-{} pairs from translation memories in {} to {}",
+                          lang.to_639_3(), other.to_639_3(), tmx_path.to_string_lossy(),
+                          pairs_file.to_string_lossy());
+                    write_pairs(trylog!(tmx::TmxPairs::new(&tmx_path, lang.clone(),
+                                other.clone()), "Unable to read translation memories", 2),
+                        stopwords.as_ref(), &other, &result_file, &pairs_file);
+                },
+                None => {
+                    info!("extracting translation memories from {}",
+                          tmx_path.to_string_lossy());
+                    extract_text(trylog!(tmx::TmxFiles::new(&tmx_path, lang.clone()),
+                            "Unable to read translation memories", 2),
+                        None, stopwords.as_ref(),
+                        &mut result_file);
+                },
+            }
+        }
+        if let Some(moses_path) = canonicalize!(lconf.moses) {
+            result_file.set_module("moses");
+            let suffix = lconf.moses_suffix.clone().unwrap_or(language_suffix(&lang));
+            match parse_target_language(&lconf.moses_align_with) {
+                Some(other) => {
+                    let target_suffix = lconf.moses_target_suffix.clone()
+                        .unwrap_or(language_suffix(&other));
+                    let pairs_file = pairs_path(&output_path, "moses", &lang, &other);
+                    info!("extracting {}-{} pairs from aligned corpora in {} to {}",
+                          lang.to_639_3(), other.to_639_3(), moses_path.to_string_lossy(),
+                          pairs_file.to_string_lossy());
+                    write_pairs(trylog!(moses::MosesPairs::new(&moses_path, &suffix,
+                                &target_suffix), "Unable to read aligned corpora", 2),
+                        stopwords.as_ref(), &other, &result_file, &pairs_file);
+                },
+                None => {
+                    info!("extracting sentences from aligned corpora in {}",
+                          moses_path.to_string_lossy());
+                    extract_text(trylog!(moses::MosesFiles::new(&moses_path, &suffix),
+                            "Unable to read aligned corpora", 2),
+                        None, stopwords.as_ref(),
+                        &mut result_file);
+                },
+            }
+        }
+        if let Some(tatoeba_path) = canonicalize!(lconf.tatoeba) {
+            result_file.set_module("tatoeba");
+            match parse_target_language(&lconf.tatoeba_align_with) {
+                Some(other) => {
+                    let pairs_file = pairs_path(&output_path, "tatoeba", &lang, &other);
+                    info!("extracting {}-{} translations from {} to {}", lang.to_639_3(),
+                          other.to_639_3(), tatoeba_path.to_string_lossy(),
+                          pairs_file.to_string_lossy());
+                    write_pairs(trylog!(tatoeba::TatoebaPairs::new(&tatoeba_path, lang.clone(),
+                                other.clone()), "Unable to read Tatoeba export", 2),
+                        stopwords.as_ref(), &other, &result_file, &pairs_file);
+                },
+                None => {
+                    info!("extracting Tatoeba sentences from {}",
+                          tatoeba_path.to_string_lossy());
+                    extract_text(trylog!(tatoeba::TatoebaSentences::new(&tatoeba_path,
+                                lang.clone()), "Unable to read Tatoeba export", 2),
+                        None, stopwords.as_ref(),
+                        &mut result_file);
+                },
+            }
+        }
+        if let Some(conllu_path) = canonicalize!(lconf.conllu) {
+            result_file.set_module("conllu");
+            let name = lconf.conllu_tokens.clone().unwrap_or("surface".into());
+            let form = match conllu::TokenForm::from_name(&name) {
+                Some(form) => form,
+                None => {
+                    error_exit(&format!("Invalid token form for CoNLL-U treebanks: {}", name),
+                            24);
+                    unreachable!();
+                },
+            };
+            info!("extracting sentences from treebanks in {}", conllu_path.to_string_lossy());
+            extract_text(trylog!(conllu::ConlluFiles::new(&conllu_path, form),
+                    "Unable to read treebanks", 2),
+                None, stopwords.as_ref(),
+                &mut result_file);
+        }
+        if let Some(subtitles_path) = canonicalize!(lconf.subtitles) {
+            result_file.set_module("subtitles");
+            info!("extracting subtitles from {}", subtitles_path.to_string_lossy());
+            let mut subtitles = trylog!(subtitles::Subtitles::new(&subtitles_path),
+                    "Unable to read from given directory", 2);
+            if let Some(gap) = lconf.subtitles_scene_gap {
+                subtitles.set_max_gap(gap);
+            }
+            extract_text(subtitles, None, stopwords.as_ref(), &mut result_file);
+        }
+        if let Some(freedict_path) = canonicalize!(lconf.freedict) {
+            result_file.set_module("freedict");
+            info!("extracting examples from FreeDict dictionaries in {}",
+                  freedict_path.to_string_lossy());
+            let mut examples = trylog!(freedict::FreedictExamples::new(&freedict_path,
+                        lang.clone()), "Unable to read dictionaries", 2);
+            if let Some(headwords_path) = canonicalize!(lconf.freedict_headwords) {
+                let headwords_file = trylog!(File::create(&headwords_path),
+                        "please make sure that the headword file is writable", 22);
+                examples.set_headword_output(Box::new(::std::io::BufWriter::new(
+                            headwords_file)));
+            }
+            extract_text(examples, None, stopwords.as_ref(), &mut result_file);
+        }
+        if let Some(tei_path) = canonicalize!(lconf.tei) {
+            result_file.set_module("tei");
+            info!("extracting TEI documents from {}", tei_path.to_string_lossy());
+            extract_text(trylog!(tei::TeiFiles::new(&tei_path, lang.clone()),
+                    "Unable to read from given directory", 2),
+                None, stopwords.as_ref(),
                 &mut result_file);
         }
         if let Some(dgt_path) = canonicalize!(lconf.dgt) {
+            result_file.set_module("dgt");
             info!("extracting EU-DGT Translation Memories from {}",
                   dgt_path.to_string_lossy());
             extract_text(trylog!(dgt::DgtFiles::new(&dgt_path, lang.clone()), 
                 "Unable to read from given directory", 2),
-                None, &lconf.stopwords,
+                None, stopwords.as_ref(),
                 &mut result_file);
         }
+        if let Some(headwords_path) = canonicalize!(lconf.coverage_headwords) {
+            trylog!(result_file.output.flush(), "could not write to output file", 23);
+            let vocabulary = result_file.output.vocabulary.take().unwrap_or_default();
+            let vocabulary_path = report_path(&output_path, &lang, "vocab.tsv");
+            let mut vocabulary_file = io::BufWriter::new(trylog!(
+                    File::create(&vocabulary_path),
+                    "please make sure that the vocabulary file is writable", 22));
+            trylog!(vocabulary.write(&mut vocabulary_file),
+                    "could not write vocabulary", 23);
+            let headwords = trylog!(coverage::read_headwords(&headwords_path, Some(&lang)),
+                    "Unable to read headword list", 2);
+            let thresholds = lconf.coverage_thresholds.clone()
+                .unwrap_or(coverage::DEFAULT_THRESHOLDS.to_vec());
+            let report = coverage::CoverageReport::new(&headwords, &vocabulary, &thresholds);
+            let report_file = report_path(&output_path, &lang, "coverage.txt");
+            info!("writing coverage report for {} headwords to {}", report.headwords,
+                  report_file.to_string_lossy());
+            trylog!(File::create(&report_file).and_then(|mut f| write!(f, "{}", report)),
+                    "could not write coverage report", 23);
+        }
+    }
+    trylog!(result_file.output.flush(), "could not write to output file", 23);
+}
+
+/// Print a message to standard error and exit; the subcommands report to the terminal rather
+/// than the log.
+fn fail(msg: String, code: i32) -> ! {
+    eprintln!("{}", msg);
+    ::std::process::exit(code);
+}
+
+// parse the arguments of a subcommand, print the usage and exit on --help or if the number of
+// free arguments differs
+fn parse_arguments(options: &getopts::Options, args: &[String], free: usize, usage: &str)
+        -> getopts::Matches {
+    let matches = options.parse(args).unwrap_or_else(|e| fail(e.to_string(), 1));
+    if matches.opt_present("help") || matches.free.len() != free {
+        print!("{}", options.usage(usage));
+        ::std::process::exit(match matches.opt_present("help") { true => 0, false => 1 });
+    }
+    matches
+}
+
+// parse the value of an option, exit if it is invalid
+fn parse_option<T: FromStr>(matches: &getopts::Matches, name: &str) -> Option<T> {
+    matches.opt_str(name).map(|value| value.parse().unwrap_or_else(|_| fail(format!(
+                "Invalid value for --{}: {}", name, value), 1)))
+}
+
+/// Report the dictionary coverage of a vocabulary written before
+///
+/// Invoked as `crafted coverage <HEADWORDS> <VOCABULARY> [MIN_COUNT...]`, the report is printed
+/// to standard output.
+fn run_coverage(program: &str, args: &[String]) {
+    if args.len() < 2 || args[0] == "-h" || args[0] == "--help" {
+        println!("Usage: {} coverage <HEADWORDS> <VOCABULARY> [MIN_COUNT...]\n{}", program,
+                 textwrap::fill("Report how many of the headwords (a plain text list or a \
+                     FreeDict dictionary) occur in the vocabulary written by crafted, \
+                     at the given minimum counts.", 80));
+        ::std::process::exit(match args.len() < 2 { true => 1, false => 0 });
+    }
+    let headwords = coverage::read_headwords(&PathBuf::from(&args[0]), None)
+        .unwrap_or_else(|e| fail(format!("Unable to read headword list: {}", e), 2));
+    let vocabulary = File::open(&args[1]).map_err(From::from)
+        .and_then(|f| coverage::Vocabulary::read(BufReader::new(f)))
+        .unwrap_or_else(|e: input_source::TransformationError| fail(format!(
+                    "Unable to read vocabulary: {}", e), 2));
+    let mut thresholds = Vec::new();
+    for threshold in &args[2..] {
+        match threshold.parse::<u64>() {
+            Ok(t) => thresholds.push(t),
+            Err(_) => fail(format!("Invalid minimum count: {}", threshold), 1),
+        }
+    }
+    if thresholds.is_empty() {
+        thresholds = coverage::DEFAULT_THRESHOLDS.to_vec();
+    }
+    print!("{}", coverage::CoverageReport::new(&headwords, &vocabulary, &thresholds));
+}
+
+/// Corpus output file
+///
+/// The normalizer, segmenter and character classes of the current language are applied by
+/// `extract_text`, which streams the filtered words into `output`.
+struct CorpusWriter {
+    output: CorpusFile,
+    normalizer: Option<Box<textfilter::Normalizer>>,
+    segmenter: textfilter::Segmenter,
+    characters: textfilter::CharClasses,
+}
+
+impl CorpusWriter {
+    fn set_module(&mut self, module: &'static str) {
+        self.output.count_words();
+        self.output.module = module;
+    }
+}
+
+/// Buffered corpus file, which counts the words written
+///
+/// If a vocabulary is set, each line written is counted for the module set last, once it is
+/// complete.
+struct CorpusFile {
+    file: io::BufWriter<File>,
+    vocabulary: Option<coverage::Vocabulary>,
+    module: &'static str,
+    /// words of the current line, kept for the vocabulary
+    line: Vec<u8>,
+}
+
+impl CorpusFile {
+    fn new(file: File) -> Self {
+        CorpusFile { file: io::BufWriter::new(file), vocabulary: None, module: "",
+            line: Vec::new() }
+    }
+
+    // count the words written since the last complete line
+    fn count_words(&mut self) {
+        if let Some(ref mut vocabulary) = self.vocabulary {
+            vocabulary.add_text(self.module, &String::from_utf8_lossy(&self.line));
+        }
+        self.line.clear();
+    }
+}
+
+impl Write for CorpusFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.file.write(buf)?;
+        if self.vocabulary.is_some() {
+            self.line.extend_from_slice(&buf[..written]);
+            if buf[..written].contains(&b'\n') {
+                self.count_words();
+            }
+        }
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.count_words();
+        self.file.flush()
+    }
+}
+
+/// Train word vectors on a corpus written before
+///
+/// Invoked as `crafted train [OPTIONS] <CORPUS> <VECTORS>`; the vectors are written in the binary
+/// word2vec format if `--binary` is given or the file name ends on `.bin`, otherwise in the text
+/// format.
+fn run_train(program: &str, args: &[String]) {
+    let defaults = word2vec::Config::default();
+    let mut options = getopts::Options::new();
+    options.optflag("", "cbow", "use the CBOW instead of the skip-gram architecture");
+    options.optopt("", "size", &format!("dimensions of the vectors ({})", defaults.dimensions),
+            "N");
+    options.optopt("", "window", &format!("maximum context distance ({})", defaults.window),
+            "N");
+    options.optopt("", "negative", &format!("negative samples ({})", defaults.negative), "N");
+    options.optopt("", "sample", &format!("subsampling threshold ({})", defaults.sample), "F");
+    options.optopt("", "min-count", &format!("minimum word count ({})", defaults.min_count),
+            "N");
+    options.optopt("", "alpha", "initial learning rate (0.025 skip-gram, 0.05 CBOW)", "F");
+    options.optopt("", "iter", &format!("passes over the corpus ({})", defaults.iterations),
+            "N");
+    options.optopt("", "threads", &format!("training threads ({})", defaults.threads), "N");
+    options.optflag("", "binary", "write the binary vector format");
+    options.optflag("h", "help", "print this help");
+    let matches = parse_arguments(&options, args, 2, &format!(
+            "Usage: {} train [OPTIONS] <CORPUS> <VECTORS>", program));
+    let config = word2vec::Config {
+        architecture: match matches.opt_present("cbow") {
+            true => word2vec::Architecture::Cbow,
+            false => word2vec::Architecture::SkipGram,
+        },
+        dimensions: parse_option(&matches, "size").unwrap_or(defaults.dimensions),
+        window: parse_option(&matches, "window").unwrap_or(defaults.window),
+        negative: parse_option(&matches, "negative").unwrap_or(defaults.negative),
+        sample: parse_option(&matches, "sample").unwrap_or(defaults.sample),
+        min_count: parse_option(&matches, "min-count").unwrap_or(defaults.min_count),
+        alpha: parse_option(&matches, "alpha"),
+        iterations: parse_option(&matches, "iter").unwrap_or(defaults.iterations),
+        threads: parse_option(&matches, "threads").unwrap_or(defaults.threads),
+        ..defaults
+    };
+    if config.dimensions == 0 || config.window == 0 {
+        fail("--size and --window must be at least 1".into(), 1);
+    }
+    let corpus = PathBuf::from(&matches.free[0]);
+    let vectors_path = PathBuf::from(&matches.free[1]);
+    let model = word2vec::train(&corpus, &config)
+        .unwrap_or_else(|e| fail(format!("Training failed: {}", e), 2));
+    let binary = matches.opt_present("binary")
+        || vectors_path.extension().map(|e| e == "bin").unwrap_or(false);
+    let written = File::create(&vectors_path).and_then(|f| {
+        let mut output = io::BufWriter::new(f);
+        match binary {
+            true => model.write_binary(&mut output),
+            false => model.write_text(&mut output),
+        }
+    });
+    if let Err(e) = written {
+        fail(format!("Could not write vectors to {}: {}", vectors_path.display(), e), 23);
+    }
+}
+
+/// Write the most similar words for each word of a vector file
+///
+/// Invoked as `crafted thesaurus [OPTIONS] <VECTORS> <OUTPUT>`; the thesaurus is written as TEI
+/// entries if `--tei` is given or the file name ends on `.tei`, otherwise as tab-separated
+/// values.
+fn run_thesaurus(program: &str, args: &[String]) {
+    let defaults = thesaurus::ThesaurusConfig::default();
+    let index_defaults = thesaurus::IndexConfig::default();
+    let mut options = getopts::Options::new();
+    options.optopt("", "top", &format!("similar words per headword ({})", defaults.top), "N");
+    options.optopt("", "min-similarity", &format!("minimum cosine similarity ({})",
+            defaults.min_similarity), "F");
+    options.optopt("", "headwords", "only write entries for these words (word list or \
+            FreeDict .tei file)", "FILE");
+    options.optopt("", "ef", &format!("candidates considered per search ({})",
+            index_defaults.ef_search), "N");
+    options.optflag("", "tei", "write TEI entries instead of tab-separated values");
+    options.optflag("h", "help", "print this help");
+    let matches = parse_arguments(&options, args, 2, &format!(
+            "Usage: {} thesaurus [OPTIONS] <VECTORS> <OUTPUT>\n\
+            Vectors are read in the binary format if the file name ends on .bin.", program));
+    let allowlist = matches.opt_str("headwords").map(|path| coverage::read_headwords(
+            &PathBuf::from(&path), None).unwrap_or_else(|e| fail(format!(
+                    "Unable to read headword list: {}", e), 2)).iter()
+            .map(|headword| coverage::corpus_form(headword)).collect());
+    let config = thesaurus::ThesaurusConfig {
+        top: parse_option(&matches, "top").unwrap_or(defaults.top),
+        min_similarity: parse_option(&matches, "min-similarity").unwrap_or(defaults.min_similarity),
+        allowlist,
+    };
+    let index_config = thesaurus::IndexConfig {
+        ef_search: parse_option(&matches, "ef").unwrap_or(index_defaults.ef_search),
+        ..index_defaults
+    };
+    let vectors_path = PathBuf::from(&matches.free[0]);
+    let output_path = PathBuf::from(&matches.free[1]);
+    let model = word2vec::Model::from_file(&vectors_path)
+        .unwrap_or_else(|e| fail(format!("Unable to read vectors: {}", e), 2));
+    let index = thesaurus::Index::build(&model, index_config);
+    let entries = thesaurus::build_thesaurus(&model, &index, &config);
+    let tei = matches.opt_present("tei")
+        || output_path.extension().map(|e| e == "tei").unwrap_or(false);
+    let written = File::create(&output_path).and_then(|f| {
+        let mut output = io::BufWriter::new(f);
+        match tei {
+            true => thesaurus::write_tei(&entries, &mut output),
+            false => thesaurus::write_tsv(&entries, &mut output),
+        }
+    });
+    if let Err(e) = written {
+        fail(format!("Could not write thesaurus to {}: {}", output_path.display(), e), 23);
+    }
+}
+
+/// Align the vector spaces of two languages and propose translations
+///
+/// Invoked as `crafted align [OPTIONS] <SOURCE_VECTORS> <TARGET_VECTORS> <SEED> <OUTPUT>`; the
+/// seed pairs are read from a FreeDict dictionary (`.tei`) or a tab-separated word list. The
+/// translation candidates of all source words missing from the seed are written to the output,
+/// the precision on the held-out seed pairs is printed to standard output.
+fn run_align(program: &str, args: &[String]) {
+    let mut options = getopts::Options::new();
+    options.optopt("", "top", "translation candidates per word (5)", "N");
+    options.optopt("", "neighbourhood", &format!("neighbours considered for CSLS ({})",
+            alignment::DEFAULT_NEIGHBOURHOOD), "N");
+    options.optopt("", "test-fraction", "fraction of the seed words held out for the \
+            evaluation (0.2)", "F");
+    options.optopt("", "seed", "seed of the held-out split (1)", "N");
+    options.optopt("", "max-words", "propose translations for at most this many of the most \
+            frequent source words", "N");
+    options.optflag("h", "help", "print this help");
+    let matches = parse_arguments(&options, args, 4, &format!(
+            "Usage: {} align [OPTIONS] <SOURCE_VECTORS> <TARGET_VECTORS> <SEED> <OUTPUT>\n\
+            Vectors are read in the binary format if the file name ends on .bin.", program));
+    let top = parse_option(&matches, "top").unwrap_or(5);
+    let neighbourhood = parse_option(&matches, "neighbourhood")
+        .unwrap_or(alignment::DEFAULT_NEIGHBOURHOOD);
+    let test_fraction = parse_option(&matches, "test-fraction").unwrap_or(0.2);
+    let max_words: Option<usize> = parse_option(&matches, "max-words");
+    let read_vectors = |path: &String| word2vec::Model::from_file(&PathBuf::from(path))
+        .unwrap_or_else(|e| fail(format!("Unable to read vectors from {}: {}", path, e), 2));
+    let source = read_vectors(&matches.free[0]);
+    let target = read_vectors(&matches.free[1]);
+    let seed = alignment::read_seed_pairs(&PathBuf::from(&matches.free[2]))
+        .unwrap_or_else(|e| fail(format!("Unable to read seed pairs: {}", e), 2));
+    let known: HashSet<String> = seed.iter().map(|pair| pair.0.clone()).collect();
+    let (train, test) = alignment::split_seed(seed, test_fraction,
+            parse_option(&matches, "seed").unwrap_or(1));
+    let aligned = alignment::Alignment::new(&source, &target, &train, neighbourhood)
+        .unwrap_or_else(|e| fail(format!("Unable to align the vectors: {}", e), 2));
+    print!("{}", alignment::evaluate(&aligned, &test, &[1, 5, 10]));
+    let candidates: Vec<(String, Vec<(String, f32)>)> = aligned.source_words().iter()
+        .filter(|word| !known.contains(*word))
+        .take(max_words.unwrap_or(usize::MAX))
+        .map(|word| (word.clone(), aligned.translate(word, top)))
+        .collect();
+    let output_path = PathBuf::from(&matches.free[3]);
+    let written = File::create(&output_path).and_then(|f|
+            alignment::write_candidates(&candidates, &mut io::BufWriter::new(f)));
+    if let Err(e) = written {
+        fail(format!("Could not write translations to {}: {}", output_path.display(), e), 23);
+    }
+}
+
+/// Join multiword expressions of a corpus written before
+///
+/// Invoked as `crafted phrases [OPTIONS] <CORPUS> <OUTPUT>`; the corpus is read twice per pass,
+/// unless `--in-memory` is given.
+fn run_phrases(program: &str, args: &[String]) {
+    let defaults = phrases::PhraseConfig::default();
+    let mut options = getopts::Options::new();
+    options.optopt("", "threshold", &format!("minimum score of a phrase ({})",
+            defaults.threshold), "F");
+    options.optopt("", "min-count", &format!("minimum bigram count ({})", defaults.min_count),
+            "N");
+    options.optopt("", "passes", &format!("passes, each joining two tokens ({})",
+            defaults.passes), "N");
+    options.optflag("", "in-memory", "read the whole corpus into memory");
+    options.optflag("h", "help", "print this help");
+    let matches = parse_arguments(&options, args, 2, &format!(
+            "Usage: {} phrases [OPTIONS] <CORPUS> <OUTPUT>", program));
+    let config = phrases::PhraseConfig {
+        threshold: parse_option(&matches, "threshold").unwrap_or(defaults.threshold),
+        min_count: parse_option(&matches, "min-count").unwrap_or(defaults.min_count),
+        passes: parse_option(&matches, "passes").unwrap_or(defaults.passes),
+        ..defaults
+    };
+    let corpus = PathBuf::from(&matches.free[0]);
+    let output_path = PathBuf::from(&matches.free[1]);
+    if !matches.opt_present("in-memory") {
+        phrases::detect_in_file(&corpus, &output_path, &config)
+            .unwrap_or_else(|e| fail(format!("Phrase detection failed: {}", e), 2));
+        return;
+    }
+    let mut text = String::new();
+    if let Err(e) = File::open(&corpus).and_then(|mut f| f.read_to_string(&mut text)) {
+        fail(format!("Unable to read {}: {}", corpus.display(), e), 2);
+    }
+    let written = File::create(&output_path).and_then(|mut f|
+            f.write_all(phrases::detect_in_memory(&text, &config).as_bytes()));
+    if let Err(e) = written {
+        fail(format!("Could not write corpus to {}: {}", output_path.display(), e), 23);
+    }
+}
+
+/// Split the compounds of a corpus written before
+///
+/// Invoked as `crafted compounds [OPTIONS] <CORPUS> <OUTPUT>`; the corpus is read twice, first to
+/// count the words, then to split the compounds.
+fn run_compounds(program: &str, args: &[String]) {
+    let mut options = getopts::Options::new();
+    options.optflag("", "keep", "write the compound, followed by its parts");
+    options.optopt("", "min-part", "minimum length of a part (3)", "N");
+    options.optopt("", "min-count", "minimum count of a part (1)", "N");
+    options.optflag("h", "help", "print this help");
+    let matches = parse_arguments(&options, args, 2, &format!(
+            "Usage: {} compounds [OPTIONS] <CORPUS> <OUTPUT>", program));
+    let mut splitter = textfilter::CompoundSplitter::new(match matches.opt_present("keep") {
+        true => textfilter::CompoundMode::Keep,
+        false => textfilter::CompoundMode::Replace,
+    });
+    splitter.min_part_length = parse_option(&matches, "min-part")
+        .unwrap_or(splitter.min_part_length);
+    splitter.min_count = parse_option(&matches, "min-count").unwrap_or(splitter.min_count);
+    let corpus = PathBuf::from(&matches.free[0]);
+    let output_path = PathBuf::from(&matches.free[1]);
+    let read_failed = |e: io::Error| -> ! {
+        fail(format!("Unable to read {}: {}", corpus.display(), e), 2)
+    };
+    let lines = || BufReader::new(File::open(&corpus).unwrap_or_else(|e| read_failed(e))).lines();
+    for line in lines() {
+        splitter.add_text(&line.unwrap_or_else(|e| read_failed(e)));
+    }
+    let written = File::create(&output_path).and_then(|f| {
+        let mut output = io::BufWriter::new(f);
+        for line in lines() {
+            let line = line.unwrap_or_else(|e| read_failed(e));
+            let words: Vec<String> = line.split_whitespace()
+                .map(|word| textfilter::Normalizer::normalize(&splitter, word).into_owned())
+                .collect();
+            writeln!(output, "{}", words.join(" "))?;
+        }
+        output.flush()
+    });
+    if let Err(e) = written {
+        fail(format!("Could not write corpus to {}: {}", output_path.display(), e), 23);
     }
 }
 
@@ -279,8 +1097,8 @@ fn main() {
 /// portions, before removing punctuation and stop words.
 fn extract_text<Source: Iterator<Item=input_source::Result<Entity>>>(
         input_source: Source, unfmt: Option<Box<Unformatter>>,
-        stopwords: &Option<String>,
-        result_file: &mut File) {
+        stopwords: Option<&HashSet<String>>,
+        result_file: &mut CorpusWriter) {
     let mut entities_read = 0; // keep it external to for loop to retrieve later
     let mut errorneous_articles = 0;
 
@@ -312,12 +1130,14 @@ fn extract_text<Source: Iterator<Item=input_source::Result<Entity>>>(
         // strip white space, punctuation, non-character word-alike sequences, etc; keep only
         // single-space separated words (exception are line breaks for context conservation, see
         // appropriate module documentation)
-        let stripped_words = match stopwords {
-            &Some(ref words) => textfilter::text2words(entity.content, Some(words
-                    .split(",").map(|x| x.trim().into()).collect::<HashSet<String>>())),
-            &None => textfilter::text2words(entity.content, None),
+        let options = textfilter::FilterOptions {
+            stopwords,
+            normalizer: result_file.normalizer.as_deref(),
+            segmenter: Some(&result_file.segmenter),
+            characters: Some(&result_file.characters),
         };
-        if let Err(msg) = result_file.write_all(stripped_words.as_bytes()) {
+        if let Err(msg) = textfilter::text2words_into(&entity.content, &options,
+                &mut result_file.output) {
             error!("could not write to output file: {}", msg);
             error_exit("Exiting", 23);
         }
@@ -332,9 +1152,82 @@ fn extract_text<Source: Iterator<Item=input_source::Result<Entity>>>(
         entities_read, errorneous_articles);
 }
 
+/// Write aligned pairs to a tab-separated file
+///
+/// Both sides of a pair are filtered and written on one line, separated by a tab. The source side
+/// is filtered like the corpus text, the target side with the segmenter and character classes of
+/// its language, without stop words and normalizer. Pairs with an empty side after filtering are
+/// skipped.
+fn write_pairs<Source: Iterator<Item=input_source::Result<AlignedPair>>>(
+        pairs: Source, stopwords: Option<&HashSet<String>>, target: &Language,
+        result_file: &CorpusWriter, pairs_path: &PathBuf) {
+    let mut pairs_file = match File::create(pairs_path) {
+        Err(e) => {
+                error!("error while opening {} for writing: {}",
+                       pairs_path.to_string_lossy(), e);
+                error_exit("please make sure that the output file is writable", 22);
+                unreachable!();
+            },
+        Ok(f) => io::BufWriter::new(f)
+    };
+    let source_options = textfilter::FilterOptions {
+        stopwords,
+        normalizer: result_file.normalizer.as_deref(),
+        segmenter: Some(&result_file.segmenter),
+        characters: Some(&result_file.characters),
+    };
+    let target_segmenter = textfilter::Segmenter::for_language(target, None);
+    let target_characters = textfilter::CharClasses::for_language(target);
+    let target_options = textfilter::FilterOptions {
+        segmenter: Some(&target_segmenter),
+        characters: Some(&target_characters),
+        ..textfilter::FilterOptions::default()
+    };
+    // filter text, keep all words on one line
+    let filter = |text: &str, options: &textfilter::FilterOptions|
+        textfilter::text2words_with(text, options).trim_end().replace('\n', " ");
+
+    let mut pairs_read = 0;
+    let mut pairs_written = 0;
+    for pair in pairs {
+        pairs_read += 1;
+        let pair = match pair {
+            Ok(p) => p,
+            Err(e) => {
+                debug!("unable to retrieve pair {} from input source; Error: {}",
+                       pairs_read, e);
+                continue;
+            }
+        };
+        let source = filter(&pair.source, &source_options);
+        let target = filter(&pair.target, &target_options);
+        if source.is_empty() || target.is_empty() {
+            continue;
+        }
+        if let Err(msg) = writeln!(pairs_file, "{}\t{}", source, target) {
+            error!("could not write to output file: {}", msg);
+            error_exit("Exiting", 23);
+        }
+        pairs_written += 1;
+    }
+    if let Err(msg) = pairs_file.flush() {
+        error!("could not write to output file: {}", msg);
+        error_exit("Exiting", 23);
+    }
+
+    info!("{} pairs read, {} written", pairs_read, pairs_written);
+}
+
 /// Remove formatting using pandoc
 fn process_formatting<'a>(unfmt: &'a Unformatter, mut doc: Entity)
         -> input_source::Result<Entity> {
+    // binary documents are read by pandoc directly
+    if let Some(path) = unfmt.input_file(&doc)? {
+        let json_ast = textfilter::call_pandoc_with_file(unfmt.get_input_format(), &path,
+                doc)?;
+        return textfilter::stringify_text(json_ast);
+    }
+
     // remove formatting which pandoc cannot handle (corner cases of incomplete
     // Pandoc readers)
     if unfmt.is_preprocessing_required() {
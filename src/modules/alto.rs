@@ -0,0 +1,290 @@
+//! ALTO/METS news paper full texts
+//!
+//! Europeana Newspapers and many national libraries publish their digitised news papers as ALTO
+//! XML, one file per scanned page. ALTO describes the page layout: text blocks consist of text
+//! lines, which consist of words (`<String/>` elements). Each word carries the OCR word confidence
+//! (`WC`) which is used to drop unreliable words.
+//!
+//! Pages are usually accompanied by a METS file for the whole issue. Its logical structure map
+//! groups text blocks from different pages into articles. If a METS file is found, one entity is
+//! emitted per article, otherwise one entity per text block. All `*.xml` files below the given
+//! directory are considered.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fs::{self, File};
+use std::io::{BufReader, Read};
+use std::path::{Path, PathBuf};
+use xml::attribute::OwnedAttribute;
+use xml::reader::{EventReader, XmlEvent};
+
+use common;
+use input_source::{Entity, Metadata, PositionType, Result, TransformationError};
+use textfilter;
+
+/// Words with a lower OCR confidence are dropped, unless configured otherwise.
+pub static DEFAULT_MIN_CONFIDENCE: f32 = 0.5;
+
+/// A text block of an ALTO page.
+#[derive(Clone, Debug)]
+pub struct TextBlock {
+    /// value of the `ID` attribute, referenced from METS files
+    pub id: String,
+    /// words of all lines, separated by a single space
+    pub text: String,
+}
+
+// an article from the logical structure map of a METS file
+struct Article {
+    label: Option<String>,
+    /// pairs of file ID and text block ID
+    areas: Vec<(String, String)>,
+}
+
+fn get_attribute<'a>(attributes: &'a [OwnedAttribute], name: &str) -> Option<&'a str> {
+    attributes.iter().find(|a| a.name.local_name == name).map(|a| a.value.as_str())
+}
+
+/// Parse the text blocks from an ALTO document.
+///
+/// Words with a word confidence below `min_confidence` are dropped. Words hyphenated at the end
+/// of a line are joined, either using the `SUBS_CONTENT` attribute or by merging the parts around
+/// a `<HYP/>` element.
+pub fn parse_alto<R: Read>(input: R, min_confidence: f32) -> Result<Vec<TextBlock>> {
+    let mut blocks = Vec::new();
+    let mut current: Option<TextBlock> = None;
+    // a <HYP/> has been encountered, next word continues the previous one
+    let mut hyphen_pending = false;
+    // the substitution content of the first hyphenation part has been used already
+    let mut skip_second_part = false;
+    for event in EventReader::new(input) {
+        match event? {
+            XmlEvent::StartElement { name, attributes, .. } => match name.local_name.as_str() {
+                "TextBlock" => current = Some(TextBlock {
+                        id: get_attribute(&attributes, "ID").unwrap_or("").to_string(),
+                        text: String::new() }),
+                "HYP" => hyphen_pending = !skip_second_part,
+                "String" => if let Some(ref mut block) = current {
+                    let subs_type = get_attribute(&attributes, "SUBS_TYPE");
+                    let mut word = get_attribute(&attributes, "CONTENT").unwrap_or("");
+                    if subs_type == Some("HypPart2") && skip_second_part {
+                        skip_second_part = false;
+                        continue;
+                    }
+                    if subs_type == Some("HypPart1") {
+                        if let Some(full_word) = get_attribute(&attributes, "SUBS_CONTENT") {
+                            word = full_word;
+                            skip_second_part = true;
+                        }
+                    }
+                    let confidence = get_attribute(&attributes, "WC")
+                        .and_then(|wc| wc.parse::<f32>().ok()).unwrap_or(1.0);
+                    if confidence < min_confidence || word.is_empty() {
+                        hyphen_pending = false;
+                        continue;
+                    }
+                    if !hyphen_pending && !block.text.is_empty() {
+                        block.text.push(' ');
+                    }
+                    block.text.push_str(word);
+                    hyphen_pending = false;
+                },
+                _ => (),
+            },
+            XmlEvent::EndElement { name } => if name.local_name == "TextBlock" {
+                if let Some(block) = current.take() {
+                    if !block.text.is_empty() {
+                        blocks.push(block);
+                    }
+                }
+                hyphen_pending = false;
+                skip_second_part = false;
+            },
+            _ => (),
+        }
+    }
+    Ok(blocks)
+}
+
+// Parse a METS file and return the referenced files (file ID -> location) and the articles from
+// the logical structure map.
+fn parse_mets<R: Read>(input: R) -> Result<(HashMap<String, String>, Vec<Article>)> {
+    let mut files = HashMap::new();
+    let mut articles = Vec::new();
+    let mut current_file: Option<String> = None;
+    let mut in_logical_map = false;
+    // for each opened <div>, whether it started an article
+    let mut divs: Vec<bool> = Vec::new();
+    let mut current_article: Option<Article> = None;
+    for event in EventReader::new(input) {
+        match event? {
+            XmlEvent::StartElement { name, attributes, .. } => match name.local_name.as_str() {
+                "file" => current_file = get_attribute(&attributes, "ID").map(|x| x.to_string()),
+                "FLocat" => if let Some(ref id) = current_file {
+                    if let Some(href) = get_attribute(&attributes, "href") {
+                        files.insert(id.clone(), href.to_string());
+                    }
+                },
+                "structMap" => in_logical_map = get_attribute(&attributes, "TYPE")
+                    .map(|t| t.eq_ignore_ascii_case("LOGICAL")).unwrap_or(false),
+                "div" if in_logical_map => {
+                    let is_article = current_article.is_none() &&
+                        get_attribute(&attributes, "TYPE").map(|t|
+                            t.eq_ignore_ascii_case("ARTICLE")).unwrap_or(false);
+                    if is_article {
+                        current_article = Some(Article { areas: Vec::new(),
+                            label: get_attribute(&attributes, "LABEL").map(|l| l.to_string()) });
+                    }
+                    divs.push(is_article);
+                },
+                "area" => if let Some(ref mut article) = current_article {
+                    if let (Some(file), Some(block)) = (get_attribute(&attributes, "FILEID"),
+                            get_attribute(&attributes, "BEGIN")) {
+                        article.areas.push((file.to_string(), block.to_string()));
+                    }
+                },
+                _ => (),
+            },
+            XmlEvent::EndElement { name } => match name.local_name.as_str() {
+                "file" => current_file = None,
+                "structMap" => in_logical_map = false,
+                "div" if in_logical_map => if divs.pop() == Some(true) {
+                    if let Some(article) = current_article.take() {
+                        if !article.areas.is_empty() {
+                            articles.push(article);
+                        }
+                    }
+                },
+                _ => (),
+            },
+            _ => (),
+        }
+    }
+    Ok((files, articles))
+}
+
+// Resolve the location of a file referenced from a METS file, relative to the METS file.
+fn resolve_location(mets_path: &Path, href: &str) -> PathBuf {
+    let href = match href.starts_with("file://") {
+        true => &href[7..],
+        false => href,
+    };
+    let location = mets_path.parent().unwrap_or(Path::new(".")).join(href);
+    fs::canonicalize(&location).unwrap_or(location)
+}
+
+// Peek into the beginning of a file to find out whether it is a METS file.
+fn is_mets_file(path: &Path) -> Result<bool> {
+    let mut head = Vec::with_capacity(4096);
+    File::open(path)?.take(4096).read_to_end(&mut head)?;
+    let head = String::from_utf8_lossy(&head);
+    Ok(match (head.find("<mets"), head.find("<alto")) {
+        (Some(mets), Some(alto)) => mets < alto,
+        (Some(_), None) => true,
+        _ => false,
+    })
+}
+
+/// Iterator over articles or text blocks from a directory of ALTO and METS files.
+///
+/// All METS files are processed first, emitting one entity per article. ALTO files not
+/// referenced by any METS file are processed afterwards, emitting one entity per text block.
+pub struct AltoDocuments {
+    mets_files: VecDeque<PathBuf>,
+    alto_files: VecDeque<PathBuf>,
+    /// ALTO files already processed as part of a METS file
+    consumed: HashSet<PathBuf>,
+    /// entities parsed, but not yet emitted
+    pending: VecDeque<Entity>,
+    min_confidence: f32,
+}
+
+impl AltoDocuments {
+    pub fn new(directory: &Path) -> Result<AltoDocuments> {
+        let mut mets_files = VecDeque::new();
+        let mut alto_files = VecDeque::new();
+        for path in common::find_files(directory, "xml")? {
+            let path = fs::canonicalize(&path).unwrap_or(path);
+            match is_mets_file(&path)? {
+                true => mets_files.push_back(path),
+                false => alto_files.push_back(path),
+            }
+        }
+        Ok(AltoDocuments { mets_files, alto_files, consumed: HashSet::new(),
+            pending: VecDeque::new(), min_confidence: DEFAULT_MIN_CONFIDENCE })
+    }
+
+    /// Set the minimum word confidence (between 0 and 1) for a word to be kept.
+    pub fn set_min_confidence(&mut self, confidence: f32) {
+        self.min_confidence = confidence;
+    }
+
+    fn read_alto_file(&self, path: &Path) -> Result<Vec<TextBlock>> {
+        let file = File::open(path).map_err(|e| TransformationError::IoError(e,
+                PositionType::InDirectory(path.to_path_buf())))?;
+        parse_alto(BufReader::new(file), self.min_confidence).map_err(|mut e| {
+            e.inject_position(PositionType::InDirectory(path.to_path_buf())); e })
+    }
+
+    // parse a METS file and queue all its articles
+    fn queue_articles(&mut self, mets_path: PathBuf) -> Result<()> {
+        let (files, articles) = parse_mets(BufReader::new(File::open(&mets_path)?))
+            .map_err(|mut e| {
+                e.inject_position(PositionType::InDirectory(mets_path.clone())); e })?;
+        // text blocks of all referenced pages, by file ID
+        let mut pages: HashMap<String, HashMap<String, String>> = HashMap::new();
+        for article in articles {
+            let mut content = String::new();
+            for &(ref file_id, ref block_id) in article.areas.iter() {
+                if !pages.contains_key(file_id) {
+                    let location = match files.get(file_id) {
+                        Some(href) => resolve_location(&mets_path, href),
+                        None => {
+                            warn!("{}: skipping reference to unknown file ID {}",
+                                  mets_path.display(), file_id);
+                            continue;
+                        },
+                    };
+                    let blocks = self.read_alto_file(&location)?;
+                    self.consumed.insert(location);
+                    pages.insert(file_id.clone(), blocks.into_iter()
+                            .map(|b| (b.id, b.text)).collect());
+                }
+                if let Some(text) = pages.get(file_id).and_then(|p| p.get(block_id)) {
+                    if !content.is_empty() {
+                        content.push_str(&format!(" {} ", textfilter::RETURN_ESCAPE_SEQUENCE));
+                    }
+                    content.push_str(text);
+                }
+            }
+            if !content.is_empty() {
+                let mut entity = Entity::with_path(content, mets_path.clone());
+                entity.metadata = Metadata { title: article.label, ..Metadata::default() };
+                self.pending.push_back(entity);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Iterator for AltoDocuments {
+    type Item = Result<Entity>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(entity) = self.pending.pop_front() {
+                return Some(Ok(entity));
+            }
+            if let Some(mets_path) = self.mets_files.pop_front() {
+                trysome!(self.queue_articles(mets_path));
+                continue;
+            }
+            let alto_path = get!(self.alto_files.pop_front());
+            if self.consumed.contains(&alto_path) {
+                continue;
+            }
+            for block in trysome!(self.read_alto_file(&alto_path)) {
+                self.pending.push_back(Entity::with_path(block.text, alto_path.clone()));
+            }
+        }
+    }
+}
@@ -0,0 +1,183 @@
+//! CoNLL-U treebanks
+//!
+//! The Universal Dependencies treebanks are distributed in the CoNLL-U format: one token per line
+//! with ten tab-separated columns (ID, FORM, LEMMA, UPOS, ...), sentences separated by blank lines
+//! and comments starting with `#`. Tokenisation, lemmas and POS tags are manually verified.
+//!
+//! Each sentence is emitted as a context line. The tokens are either the surface forms, the
+//! lemmas or lemmas annotated with their universal POS tag (`Haus_NOUN`), see
+//! [`TokenForm`](enum.TokenForm.html).
+//!
+//! Multiword tokens (e.g. German "zum" = "zu dem") are given as an ID range (`3-4`) followed by
+//! the syntactic words. Surface forms are taken from the multiword token, lemmas from the
+//! syntactic words. Empty nodes (IDs like `8.1`) are skipped.
+
+use std::collections::VecDeque;
+use std::io::{BufRead, BufReader, Lines, Read};
+use std::path::{Path, PathBuf};
+
+use common;
+use input_source::{Entity, PositionType, Result, TransformationError};
+use textfilter;
+
+// maximum size of the text of an entity; larger files are split into several entities
+static MAX_BUFFER_SIZE: usize = 1048576; // 1M
+
+type LineReader = Lines<BufReader<Box<Read>>>;
+
+/// The representation of a token in the output.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TokenForm {
+    /// word form as found in the text
+    Surface,
+    /// lemma, or the surface form if no lemma is given
+    Lemma,
+    /// lemma and universal POS tag, joined by an underscore
+    LemmaPos,
+}
+
+impl TokenForm {
+    /// Parse the name used in the configuration: `surface`, `lemma` or `lemma_pos`.
+    pub fn from_name(name: &str) -> Option<TokenForm> {
+        match name.to_lowercase().as_str() {
+            "surface" | "form" => Some(TokenForm::Surface),
+            "lemma" => Some(TokenForm::Lemma),
+            "lemma_pos" => Some(TokenForm::LemmaPos),
+            _ => None,
+        }
+    }
+}
+
+// parse an ID column: a word index, a range (multiword token) or a decimal (empty node)
+enum TokenId {
+    Word(u32),
+    Range(u32, u32),
+    Empty,
+}
+
+fn parse_id(id: &str) -> Option<TokenId> {
+    if id.contains('.') {
+        return Some(TokenId::Empty);
+    }
+    let mut bounds = id.splitn(2, '-').map(|n| n.parse::<u32>());
+    match (bounds.next(), bounds.next()) {
+        (Some(Ok(start)), Some(Ok(end))) => Some(TokenId::Range(start, end)),
+        (Some(Ok(index)), None) => Some(TokenId::Word(index)),
+        _ => None,
+    }
+}
+
+/// Convert the token lines of a sentence to the requested tokens.
+///
+/// Comment lines are ignored, malformed lines are skipped.
+pub fn sentence_tokens<'a, I: IntoIterator<Item=&'a str>>(lines: I, form: TokenForm)
+        -> Vec<String> {
+    let mut tokens = Vec::new();
+    // last word index covered by the current multiword token
+    let mut multiword_end = 0;
+    for line in lines {
+        if line.starts_with('#') {
+            continue;
+        }
+        let columns: Vec<&str> = line.split('\t').collect();
+        if columns.len() < 4 {
+            continue;
+        }
+        let surface = columns[1];
+        match parse_id(columns[0]) {
+            Some(TokenId::Range(_, end)) => if form == TokenForm::Surface {
+                tokens.push(surface.to_string());
+                multiword_end = end;
+            },
+            Some(TokenId::Word(index)) => {
+                if index <= multiword_end {
+                    continue; // part of a multiword token, already emitted
+                }
+                let lemma = match columns[2] {
+                    "_" | "" => surface,
+                    lemma => lemma,
+                };
+                tokens.push(match form {
+                    TokenForm::Surface => surface.to_string(),
+                    TokenForm::Lemma => lemma.to_string(),
+                    TokenForm::LemmaPos => format!("{}_{}", lemma, columns[3]),
+                });
+            },
+            Some(TokenId::Empty) | None => (),
+        }
+    }
+    tokens
+}
+
+/// Iterator over the sentences of all CoNLL-U files below a directory
+///
+/// The sentences are grouped into entities of about 1 MB, each sentence forming its own context
+/// line.
+pub struct ConlluFiles {
+    files: VecDeque<PathBuf>,
+    form: TokenForm,
+    /// lines of the current file, its path and the current line number
+    current: Option<(LineReader, PathBuf, u64)>,
+}
+
+impl ConlluFiles {
+    /// Read all `.conllu` files below a directory (or the given file).
+    pub fn new(path: &Path, form: TokenForm) -> Result<ConlluFiles> {
+        let files = match path.is_dir() {
+            true => common::find_files(path, "conllu")?,
+            false => vec![path.to_path_buf()],
+        };
+        Ok(ConlluFiles { files: files.into_iter().collect(), form, current: None })
+    }
+}
+
+impl Iterator for ConlluFiles {
+    type Item = Result<Entity>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (mut lines, path, mut line_number) = match self.current.take() {
+                Some(current) => current,
+                None => {
+                    let path = get!(self.files.pop_front());
+                    let lines = trysome!(common::open_file(&path)
+                            .map(|f| BufReader::new(f).lines()));
+                    (lines, path, 0)
+                },
+            };
+            let first_line = line_number + 1;
+            let mut text = String::new();
+            let mut sentence: Vec<String> = Vec::new();
+            let mut end_of_file = false;
+            while text.len() < MAX_BUFFER_SIZE && !end_of_file {
+                let line = match lines.next() {
+                    Some(line) => {
+                        line_number += 1;
+                        trysome!(line.map_err(|e| TransformationError::IoError(e,
+                                PositionType::InFile(path.clone(), line_number, 0))))
+                    },
+                    None => {
+                        end_of_file = true;
+                        String::new()
+                    },
+                };
+                if !line.trim().is_empty() {
+                    sentence.push(line);
+                    continue;
+                }
+                let tokens = sentence_tokens(sentence.iter().map(|l| l.as_str()), self.form);
+                sentence.clear();
+                if !tokens.is_empty() {
+                    text.push_str(&tokens.join(" "));
+                    text.push_str(&format!(" {} ", textfilter::RETURN_ESCAPE_SEQUENCE));
+                }
+            }
+            if !end_of_file { // more to come from this file
+                self.current = Some((lines, path.clone(), line_number));
+            }
+            if !text.is_empty() {
+                return Some(Ok(Entity::with_exact_pos(text, path, first_line, 1)));
+            }
+        }
+    }
+}
@@ -0,0 +1,118 @@
+//! Office documents (OpenDocument, DOCX, RTF)
+//!
+//! Freely licensed texts are often at hand as word processor documents. Pandoc reads OpenDocument
+//! text (`.odt`), Office Open XML (`.docx`) and, since version 2.14, RTF documents. Since these
+//! are (mostly) binary formats, pandoc reads the files itself, see
+//! [`Unformatter::input_file`](../../input_source/trait.Unformatter.html#method.input_file).
+//!
+//! Each format is processed separately with its own [`OfficeDocument`](struct.OfficeDocument.html)
+//! unformatter; one entity is emitted per document.
+
+use pandoc;
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+
+use common;
+use input_source::{Entity, Metadata, PositionType, Result, TransformationError, Unformatter};
+
+/// A document format read by pandoc from files.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DocumentFormat {
+    Docx,
+    Odt,
+    Rtf,
+}
+
+/// All supported document formats.
+pub static FORMATS: [DocumentFormat; 3] = [DocumentFormat::Docx, DocumentFormat::Odt,
+    DocumentFormat::Rtf];
+
+impl DocumentFormat {
+    /// File extension of the format, without the dot.
+    pub fn extension(&self) -> &'static str {
+        match *self {
+            DocumentFormat::Docx => "docx",
+            DocumentFormat::Odt => "odt",
+            DocumentFormat::Rtf => "rtf",
+        }
+    }
+
+    fn pandoc_format(&self) -> pandoc::InputFormat {
+        match *self {
+            DocumentFormat::Docx => pandoc::InputFormat::Docx,
+            DocumentFormat::Odt => pandoc::InputFormat::Other("odt".into()),
+            DocumentFormat::Rtf => pandoc::InputFormat::Other("rtf".into()),
+        }
+    }
+}
+
+/// Pandoc configuration for office documents of one format
+///
+/// Pandoc reads the document from the file given as position of the entity; entities without a
+/// file are an error.
+pub struct OfficeDocument(pub DocumentFormat);
+
+impl Unformatter for OfficeDocument {
+    fn is_preprocessing_required(&self) -> bool {
+        false
+    }
+
+    fn get_input_format(&self) -> pandoc::InputFormat {
+        self.0.pandoc_format()
+    }
+
+    fn preprocess(&self, input: &Entity) -> Result<Entity> {
+        Ok(Entity { content: input.content.clone(), position: input.position.clone(),
+            metadata: input.metadata.clone() })
+    }
+
+    fn input_file(&self, input: &Entity) -> Result<Option<PathBuf>> {
+        match input.position {
+            PositionType::InDirectory(ref path) => Ok(Some(path.clone())),
+            ref position => Err(TransformationError::ErrorneousStructure(format!(
+                    "{} documents can only be read from a file", self.0.extension()),
+                    position.clone())),
+        }
+    }
+}
+
+// lock files and backups of word processors, e.g. "~$Brief.docx" or ".~lock.Brief.odt#"
+fn is_temporary_file(path: &Path) -> bool {
+    path.file_name().and_then(|n| n.to_str())
+        .map(|name| name.starts_with("~$") || name.starts_with(".~lock"))
+        .unwrap_or(false)
+}
+
+/// Find all documents of the given format below a directory.
+///
+/// Lock files of word processors are skipped.
+pub fn find_documents(directory: &Path, format: DocumentFormat) -> Result<Vec<PathBuf>> {
+    Ok(common::find_files(directory, format.extension())?.into_iter()
+        .filter(|path| !is_temporary_file(path)).collect())
+}
+
+/// Iterator over the documents of one format below a directory
+///
+/// The entities have no content, only the path of the document as position and the file name
+/// (without extension) as title. Use them with [`OfficeDocument`](struct.OfficeDocument.html).
+pub struct OfficeDocuments {
+    paths: VecDeque<PathBuf>,
+}
+
+impl OfficeDocuments {
+    pub fn new(directory: &Path, format: DocumentFormat) -> Result<OfficeDocuments> {
+        Ok(OfficeDocuments { paths: find_documents(directory, format)?.into_iter().collect() })
+    }
+}
+
+impl Iterator for OfficeDocuments {
+    type Item = Result<Entity>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let path = get!(self.paths.pop_front());
+        let title = path.file_stem().map(|s| s.to_string_lossy().into_owned());
+        let mut entity = Entity::with_path(String::new(), path);
+        entity.metadata = Metadata { title, ..Metadata::default() };
+        Some(Ok(entity))
+    }
+}
@@ -0,0 +1,305 @@
+//! EPUB ebooks
+//!
+//! An EPUB is a zip archive. `META-INF/container.xml` points to the package document (OPF), which
+//! lists the Dublin Core metadata of the book, all files (manifest) and the reading order of the
+//! content documents (spine). The content documents are XHTML files, which are converted by
+//! pandoc's HTML reader; see [`Epub`](struct.Epub.html).
+//!
+//! One entity is emitted per chapter, i.e. per content document of the spine. Books are filtered
+//! by their `dc:language` and, optionally, their `dc:rights` statement. Navigation documents,
+//! non-linear spine items and chapters marked as imprint, colophon or table of contents are
+//! skipped.
+
+use isolang::Language;
+use pandoc;
+use std::collections::{HashMap, VecDeque};
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::{Path, PathBuf};
+use xml::attribute::OwnedAttribute;
+use xml::reader::{EventReader, XmlEvent};
+use zip::read::ZipArchive;
+
+use common;
+use input_source::{Entity, Metadata, PositionType, Result, TransformationError, Unformatter};
+use super::tmx::language_matches;
+
+/// Values of `epub:type` marking chapters which do not belong to the text of the book.
+static SKIPPED_CHAPTER_TYPES: [&str; 6] = ["imprint", "colophon", "copyright-page",
+    "uncopyright", "toc", "landmarks"];
+
+/// Pandoc configuration for EPUB chapters
+///
+/// Footnotes and end notes (`<aside>` elements) are removed before conversion, since they would
+/// interrupt the text.
+pub struct Epub;
+
+impl Unformatter for Epub {
+    fn is_preprocessing_required(&self) -> bool {
+        true
+    }
+
+    fn get_input_format(&self) -> pandoc::InputFormat {
+        pandoc::InputFormat::Html
+    }
+
+    fn preprocess(&self, input: &Entity) -> Result<Entity> {
+        let mut content = String::with_capacity(input.content.len());
+        let mut rest = input.content.as_str();
+        while let Some(start) = rest.find("<aside") {
+            content.push_str(&rest[..start]);
+            rest = match rest[start..].find("</aside>") {
+                Some(end) => &rest[start + end + "</aside>".len()..],
+                None => "",
+            };
+        }
+        content.push_str(rest);
+        Ok(Entity { content, position: input.position.clone(),
+            metadata: input.metadata.clone() })
+    }
+}
+
+/// Metadata and reading order of a book, parsed from the package document.
+#[derive(Debug, Default)]
+pub struct Package {
+    pub title: Option<String>,
+    pub language: Option<String>,
+    pub rights: Option<String>,
+    pub date: Option<String>,
+    /// paths of the content documents within the archive, in reading order
+    pub chapters: Vec<String>,
+}
+
+fn get_attribute<'a>(attributes: &'a [OwnedAttribute], name: &str) -> Option<&'a str> {
+    attributes.iter().find(|a| a.name.local_name == name).map(|a| a.value.as_str())
+}
+
+// decode %XX escapes of a URL path
+fn percent_decode(href: &str) -> String {
+    let bytes = href.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let digits = ::std::str::from_utf8(&bytes[i + 1..i + 3]).unwrap_or("");
+            if let Ok(byte) = u8::from_str_radix(digits, 16) {
+                decoded.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Resolve a reference from a document at `base` (a path within the archive) to a path within the
+/// archive.
+///
+/// Fragments are removed and `..` segments are resolved.
+pub fn resolve_href(base: &str, href: &str) -> String {
+    let href = percent_decode(href.split('#').next().unwrap_or(""));
+    let mut segments: Vec<&str> = base.split('/').collect();
+    segments.pop(); // file name of the referencing document
+    for segment in href.split('/') {
+        match segment {
+            "" | "." => (),
+            ".." => { segments.pop(); },
+            s => segments.push(s),
+        }
+    }
+    segments.retain(|s| !s.is_empty());
+    segments.join("/")
+}
+
+/// Find the path of the package document in `META-INF/container.xml`.
+pub fn parse_container<R: Read>(input: R) -> Result<String> {
+    for event in EventReader::new(input) {
+        if let XmlEvent::StartElement { name, attributes, .. } = event? {
+            if name.local_name == "rootfile" {
+                if let Some(path) = get_attribute(&attributes, "full-path") {
+                    return Ok(path.to_string());
+                }
+            }
+        }
+    }
+    Err(TransformationError::ErrorneousStructure("no rootfile found in container.xml".into(),
+            PositionType::None))
+}
+
+/// Parse the package document, located at `opf_path` within the archive.
+pub fn parse_package<R: Read>(input: R, opf_path: &str) -> Result<Package> {
+    let mut package = Package::default();
+    // manifest: ID -> (href, properties)
+    let mut manifest: HashMap<String, (String, String)> = HashMap::new();
+    let mut spine = Vec::new();
+    let mut in_metadata = false;
+    let mut field: Option<String> = None;
+    let mut text = String::new();
+    for event in EventReader::new(input) {
+        match event? {
+            XmlEvent::StartElement { name, attributes, .. } => match name.local_name.as_str() {
+                "metadata" => in_metadata = true,
+                "item" => if let (Some(id), Some(href)) = (get_attribute(&attributes, "id"),
+                        get_attribute(&attributes, "href")) {
+                    manifest.insert(id.to_string(), (href.to_string(),
+                        get_attribute(&attributes, "properties").unwrap_or("").to_string()));
+                },
+                "itemref" => if get_attribute(&attributes, "linear") != Some("no") {
+                    if let Some(id) = get_attribute(&attributes, "idref") {
+                        spine.push(id.to_string());
+                    }
+                },
+                n @ "title" | n @ "language" | n @ "rights" | n @ "date" if in_metadata => {
+                    field = Some(n.to_string());
+                    text.clear();
+                },
+                _ => (),
+            },
+            XmlEvent::Characters(data) | XmlEvent::CData(data) => if field.is_some() {
+                text.push_str(&data);
+            },
+            XmlEvent::EndElement { name } => match name.local_name.as_str() {
+                "metadata" => in_metadata = false,
+                n if field.as_ref().map(|f| f == n).unwrap_or(false) => {
+                    let value = Some(text.trim().to_string());
+                    // only the first occurrence of each field is kept
+                    match n {
+                        "title" if package.title.is_none() => package.title = value,
+                        "language" if package.language.is_none() => package.language = value,
+                        "rights" if package.rights.is_none() => package.rights = value,
+                        "date" if package.date.is_none() => package.date = value,
+                        _ => (),
+                    }
+                    field = None;
+                },
+                _ => (),
+            },
+            _ => (),
+        }
+    }
+    for id in spine {
+        match manifest.get(&id) {
+            Some(&(ref href, ref properties)) => if !properties.split_whitespace()
+                    .any(|p| p == "nav") {
+                package.chapters.push(resolve_href(opf_path, href));
+            },
+            None => warn!("spine references unknown manifest item {}", id),
+        }
+    }
+    Ok(package)
+}
+
+// whether a chapter is marked as imprint, colophon, etc.
+fn is_skipped_chapter(content: &str) -> bool {
+    let mut head = content.len().min(4096);
+    while !content.is_char_boundary(head) {
+        head -= 1;
+    }
+    let head = &content[..head];
+    let mut rest = head;
+    while let Some(start) = rest.find("epub:type=\"") {
+        let value_start = start + "epub:type=\"".len();
+        let value_end = rest[value_start..].find('"').map(|e| value_start + e)
+            .unwrap_or(rest.len());
+        if rest[value_start..value_end].split_whitespace()
+                .any(|t| SKIPPED_CHAPTER_TYPES.contains(&t)) {
+            return true;
+        }
+        rest = &rest[value_end..];
+    }
+    false
+}
+
+fn read_entry(archive: &mut ZipArchive<File>, name: &str) -> Result<String> {
+    let entry = archive.by_name(name)?;
+    let mut content = String::new();
+    common::decoding_reader(entry)?.read_to_string(&mut content)?;
+    Ok(content)
+}
+
+/// Iterator over the chapters of all EPUB files below a directory
+pub struct EpubBooks {
+    books: VecDeque<PathBuf>,
+    language: Language,
+    /// if set, the rights statement of a book has to contain one of these (lower case) terms
+    required_rights: Option<Vec<String>>,
+    /// chapters of the current book
+    pending: VecDeque<Entity>,
+}
+
+impl EpubBooks {
+    /// Read all books with the given language from a directory (or the given file).
+    pub fn new(path: &Path, language: Language) -> Result<EpubBooks> {
+        let books = match path.is_dir() {
+            true => common::find_files(path, "epub")?,
+            false => vec![path.to_path_buf()],
+        };
+        Ok(EpubBooks { books: books.into_iter().collect(), language, required_rights: None,
+            pending: VecDeque::new() })
+    }
+
+    /// Only read books whose `dc:rights` statement contains one of the given terms (compared
+    /// case-insensitively), e.g. `public domain` or `CC0`. Books without a statement are skipped.
+    pub fn set_required_rights(&mut self, terms: Vec<String>) {
+        self.required_rights = Some(terms.into_iter().map(|t| t.to_lowercase()).collect());
+    }
+
+    fn is_accepted(&self, package: &Package) -> bool {
+        if let Some(ref language) = package.language {
+            if !language_matches(language, &self.language) {
+                return false;
+            }
+        }
+        match self.required_rights {
+            Some(ref terms) => package.rights.as_ref().map(|rights| {
+                    let rights = rights.to_lowercase();
+                    terms.iter().any(|t| rights.contains(t.as_str()))
+                }).unwrap_or(false),
+            None => true,
+        }
+    }
+
+    // read the package document and queue all chapters of an accepted book
+    fn queue_chapters(&mut self, path: &Path) -> Result<()> {
+        let mut archive = ZipArchive::new(File::open(path)?)?;
+        let opf_path = parse_container(BufReader::new(
+                archive.by_name("META-INF/container.xml")?))?;
+        let package = parse_package(BufReader::new(archive.by_name(&opf_path)?), &opf_path)?;
+        if !self.is_accepted(&package) {
+            debug!("{}: skipping book with language {:?} and rights {:?}", path.display(),
+                   package.language, package.rights);
+            return Ok(());
+        }
+        let metadata = Metadata { title: package.title.clone(), date: package.date.clone(),
+            language: package.language.clone(), provider: None };
+        for chapter in package.chapters.iter() {
+            let content = read_entry(&mut archive, chapter)?;
+            if is_skipped_chapter(&content) {
+                continue;
+            }
+            let mut entity = Entity::with_path(content, path.join(chapter));
+            entity.metadata = metadata.clone();
+            self.pending.push_back(entity);
+        }
+        Ok(())
+    }
+}
+
+impl Iterator for EpubBooks {
+    type Item = Result<Entity>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(chapter) = self.pending.pop_front() {
+                return Some(Ok(chapter));
+            }
+            let path = get!(self.books.pop_front());
+            if let Err(mut e) = self.queue_chapters(&path) {
+                e.inject_position(PositionType::InDirectory(path));
+                return Some(Err(e));
+            }
+        }
+    }
+}
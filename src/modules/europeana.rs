@@ -1,3 +1,9 @@
+//! Europeana news paper articles
+//!
+//! The European Library publishes digitised news papers as JSON documents, one per edition. The
+//! text has been obtained using OCR software and is hence of varying quality. Each article is
+//! therefore rated using a heuristic OCR quality score (see [`ocr_quality`](fn.ocr_quality.html))
+//! and dropped, if it falls below a configurable threshold. By default, all articles are kept.
 use json;
 use json::JsonValue;
 use std::path::{Path};
@@ -5,6 +11,9 @@ use std::path::{Path};
 use common;
 use input_source::*;
 
+/// Articles with a lower OCR quality score are dropped, unless configured otherwise; no article
+/// is dropped by default.
+pub static DEFAULT_QUALITY_THRESHOLD: f32 = 0.0;
 
 pub struct Europeana;
 
@@ -12,11 +21,19 @@ pub struct Europeana;
 /// Iterator, which parses the content out of a JSON file
 pub struct Articles {
     paths: Box<Iterator<Item=Result<Entity>>>,
+    /// minimum OCR quality score of an article
+    quality_threshold: f32,
 }
 
 impl Articles {
     pub fn new(top_level: &Path) -> Self {
-        Articles { paths: common::read_files(top_level.into(), "json".into()) }
+        Articles { paths: common::read_files(top_level.into(), "json".into()),
+            quality_threshold: DEFAULT_QUALITY_THRESHOLD }
+    }
+
+    /// Set the minimum OCR quality score (between 0 and 1) for an article to be emitted.
+    pub fn set_quality_threshold(&mut self, threshold: f32) {
+        self.quality_threshold = threshold;
     }
 }
 
@@ -26,29 +43,171 @@ fn mkerr(input: &str, pos: PositionType) -> Option<Result<Entity>> {
     Some(Err(TransformationError::ErrorneousStructure(input.to_string(), pos)))
 }
 
+// Extract a string from a JSON value; Europeana uses both plain strings and arrays of strings for
+// its metadata fields, the latter are joined by a comma.
+fn json_text(value: Option<&JsonValue>) -> Option<String> {
+    match value {
+        Some(&JsonValue::Array(ref values)) => {
+            let joined = values.iter().filter_map(|v| v.as_str())
+                .collect::<Vec<&str>>().join(", ");
+            match joined.is_empty() {
+                true => None,
+                false => Some(joined),
+            }
+        },
+        Some(v) => v.as_str().map(|s| s.trim()).and_then(|s| match s.is_empty() {
+            true => None,
+            false => Some(s.to_string()),
+        }),
+        None => None,
+    }
+}
+
 impl Iterator for Articles {
     type Item = Result<Entity>;
 
     // policy:
-    // propagate errors directly, but skip to next file if no content could be found
+    // propagate errors directly, but skip to next file if no content could be found or if the OCR
+    // quality is too low
     fn next(&mut self) -> Option<Self::Item> {
-        let edition_js = trysome!(get!(self.paths.next()));
-        let meta = trysome!(json::parse(&edition_js.content).map_err(|e|
-            TransformationError::JsonError(e, edition_js.position.clone())));
-        let mut output = String::new();
-        match meta {
-            JsonValue::Object(ref obj) => match obj.get("contentAsText") {
-                Option::Some(&JsonValue::Array(ref values)) => for text in values {
-                    output.push_str(&text.to_string());
+        loop {
+            let edition_js = trysome!(get!(self.paths.next()));
+            let meta = trysome!(json::parse(&edition_js.content).map_err(|e|
+                TransformationError::JsonError(e, edition_js.position.clone())));
+            let mut output = String::new();
+            let mut metadata = Metadata::default();
+            match meta {
+                JsonValue::Object(ref obj) => {
+                    match obj.get("contentAsText") {
+                        Option::Some(&JsonValue::Array(ref values)) => for text in values {
+                            output.push_str(&text.to_string());
+                        },
+                        _ => return mkerr("Expected a JSON array underneath \
+                               \"contextAsText\" key", edition_js.position),
+                    }
+                    metadata.title = json_text(obj.get("title"));
+                    metadata.date = json_text(obj.get("date"));
+                    metadata.language = json_text(obj.get("language"));
+                    metadata.provider = json_text(obj.get("provider"));
                 },
-                _ => return mkerr("Expected a JSON array underneath \
-                       \"contextAsText\" key", edition_js.position),
-            },
-            _ => return mkerr("expected JSON document with an Object at \
-                the top level".into(), edition_js.position),
+                _ => return mkerr("expected JSON document with an Object at \
+                    the top level".into(), edition_js.position),
+            };
+
+            let quality = ocr_quality(&output);
+            if quality < self.quality_threshold {
+                debug!("{}: OCR quality of {:.2} below threshold, skipping",
+                       edition_js.position, quality);
+                continue;
+            }
+            return Some(Ok(Entity { content: output, position: edition_js.position,
+                    metadata }));
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// OCR quality estimation
+
+// characters which commonly occur in proper text and are hence not counted as garbage
+#[inline]
+fn is_common_punctuation(c: char) -> bool {
+    match c {
+        '.' | ',' | ';' | ':' | '!' | '?' | '\'' | '"' | '(' | ')' | '-' | '–' | '„' | '“'
+            | '”' | '«' | '»' | '’' | '‘' => true,
+        _ => false,
+    }
+}
+
+// A token looks like a dictionary word, if it consists only of letters (with hyphens or
+// apostrophes in between) and has a regular capitalisation: lower case, capitalised or all upper
+// case. OCR errors typically result in mixed case, intermixed digits or long runs of the same
+// letter.
+fn is_dictionary_like(token: &str) -> bool {
+    let token = token.trim_matches(|c: char| !c.is_alphanumeric());
+    let length = token.chars().count();
+    if length == 0 || length > 25 {
+        return false;
+    }
+    if !token.chars().all(|c| c.is_alphabetic() || c == '-' || c == '\'' || c == '’') {
+        return false;
+    }
+    let mut same_char_run = 0;
+    let mut previous = None;
+    for c in token.chars() {
+        same_char_run = match previous == Some(c) {
+            true => same_char_run + 1,
+            false => 1,
         };
-        Some(Ok(Entity { content: output, position: edition_js.position }))
+        if same_char_run > 3 {
+            return false;
+        }
+        previous = Some(c);
     }
+    let rest_is_lower = token.chars().skip(1).filter(|c| c.is_alphabetic())
+        .all(|c| c.is_lowercase());
+    let all_upper = token.chars().filter(|c| c.is_alphabetic()).all(|c| c.is_uppercase());
+    rest_is_lower || all_upper
 }
 
+/// Estimate the OCR quality of a text.
+///
+/// The score is a value between 0 (garbage) and 1 (proper text). It is computed from three
+/// indicators:
+///
+/// -   the fraction of tokens which look like dictionary words (alphabetic, sensible
+///     capitalisation, no excessive repetition of characters),
+/// -   the share of characters found within runs of two or more non-alphanumeric characters which
+///     are not common punctuation, e.g. `»^~` or `||`,
+/// -   the average word length; very short or very long averages indicate either fragmented words
+///     or merged columns.
+pub fn ocr_quality(text: &str) -> f32 {
+    let mut tokens = 0;
+    let mut dictionary_like = 0;
+    let mut word_lengths = 0;
+    for token in text.split_whitespace() {
+        tokens += 1;
+        if is_dictionary_like(token) {
+            dictionary_like += 1;
+        }
+        word_lengths += token.chars().filter(|c| c.is_alphanumeric()).count();
+    }
+    if tokens == 0 {
+        return 0.0;
+    }
 
+    // count characters within garbage runs
+    let mut visible_chars = 0;
+    let mut garbage_chars = 0;
+    let mut current_run = 0;
+    for c in text.chars() {
+        if !c.is_whitespace() {
+            visible_chars += 1;
+        }
+        if c.is_whitespace() || c.is_alphanumeric() || is_common_punctuation(c) {
+            if current_run >= 2 {
+                garbage_chars += current_run;
+            }
+            current_run = 0;
+        } else {
+            current_run += 1;
+        }
+    }
+    if current_run >= 2 {
+        garbage_chars += current_run;
+    }
+
+    let dictionary_ratio = dictionary_like as f32 / tokens as f32;
+    // a few percent of garbage already indicate a severely broken text
+    let garbage_penalty = (5.0 * garbage_chars as f32 / visible_chars as f32).min(1.0);
+    // average word lengths between 3.5 and 8 are typical for European languages
+    let average_length = word_lengths as f32 / tokens as f32;
+    let length_factor = if average_length < 3.5 {
+        (1.0 - (3.5 - average_length) / 3.0).max(0.0)
+    } else if average_length > 8.0 {
+        (1.0 - (average_length - 8.0) / 6.0).max(0.0)
+    } else {
+        1.0
+    };
+    dictionary_ratio * (1.0 - garbage_penalty) * length_factor
+}
@@ -0,0 +1,327 @@
+//! FreeDict TEI dictionaries
+//!
+//! FreeDict dictionaries are TEI P5 documents named after their language pair, e.g.
+//! `deu-eng.tei`. Each `<entry>` has one or more headwords (`<form><orth>`), grammatical
+//! information (`<gramGrp>`) and senses (`<sense>`) with translations (`<cit type="trans">`),
+//! definitions (`<def>`) and example quotes (`<cit type="example">`):
+//!
+//! ```xml
+//! <entry>
+//!   <form><orth>Haus</orth></form>
+//!   <gramGrp><pos>n</pos></gramGrp>
+//!   <sense>
+//!     <cit type="trans"><quote>house</quote></cit>
+//!     <cit type="example"><quote>Das Haus ist alt.</quote>
+//!       <cit type="trans"><quote>The house is old.</quote></cit></cit>
+//!   </sense>
+//! </entry>
+//! ```
+//!
+//! Only dictionaries whose source language is the configured language are read. The example
+//! quotes form a small corpus of their own, the headwords can be written to a word list to be
+//! used as a vocabulary allowlist or for a coverage report.
+
+use isolang::Language;
+use std::collections::{BTreeSet, VecDeque};
+use std::io::{BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use xml::reader::{EventReader, Events, XmlEvent};
+
+use common;
+use input_source::{Entity, Metadata, PositionType, Result, TransformationError};
+use super::tmx::language_matches;
+use textfilter;
+
+/// A sense of a dictionary entry.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Sense {
+    /// translations into the target language
+    pub translations: Vec<String>,
+    /// definitions, usually in the source language
+    pub definitions: Vec<String>,
+    /// example quotes in the source language
+    pub examples: Vec<String>,
+}
+
+/// An entry of a FreeDict dictionary.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DictEntry {
+    pub headwords: Vec<String>,
+    /// part of speech, as given in the dictionary (e.g. `n`, `v`)
+    pub part_of_speech: Option<String>,
+    pub senses: Vec<Sense>,
+}
+
+impl DictEntry {
+    /// All example quotes of all senses.
+    pub fn examples(&self) -> Vec<&str> {
+        self.senses.iter().flat_map(|s| s.examples.iter().map(|e| e.as_str())).collect()
+    }
+}
+
+/// Source and target language code of a dictionary, taken from its file name (`deu-eng.tei`).
+pub fn dictionary_languages(path: &Path) -> Option<(String, String)> {
+    let stem = get!(path.file_stem()).to_string_lossy().into_owned();
+    let mut codes = stem.splitn(2, '-');
+    match (codes.next(), codes.next()) {
+        (Some(source), Some(target)) if !source.is_empty() && !target.is_empty() =>
+            Some((source.to_string(), target.to_string())),
+        _ => None,
+    }
+}
+
+/// Whether the dictionary at the given path translates from the given language.
+///
+/// Dictionaries whose file name does not contain a language pair are assumed to match.
+pub fn is_source_language(path: &Path, language: &Language) -> bool {
+    dictionary_languages(path).map(|(source, _)| language_matches(&source, language))
+        .unwrap_or(true)
+}
+
+// an open element: its name and, for <cit>, its type
+struct Element {
+    name: String,
+    cit_type: Option<String>,
+}
+
+/// Streaming reader of the entries of a FreeDict TEI dictionary.
+pub struct EntryReader<R: Read> {
+    events: Events<R>,
+    finished: bool,
+}
+
+impl<R: Read> EntryReader<R> {
+    pub fn new(input: R) -> EntryReader<R> {
+        EntryReader { events: EventReader::new(input).into_iter(), finished: false }
+    }
+
+    // read the content of an entry, after its start tag has been consumed
+    fn read_entry(&mut self) -> Result<DictEntry> {
+        let mut entry = DictEntry::default();
+        let mut stack: Vec<Element> = Vec::new();
+        // text of the <orth>, <pos>, <def> or <quote> element being read and its depth
+        let mut text: Option<(String, usize)> = None;
+        loop {
+            let event = match self.events.next() {
+                Some(event) => event?,
+                None => return Err(TransformationError::ErrorneousStructure(
+                        "unexpected end of document within <entry>".into(),
+                        PositionType::None)),
+            };
+            match event {
+                XmlEvent::StartElement { name, attributes, .. } => {
+                    let name = name.local_name;
+                    let cit_type = attributes.iter().find(|a| a.name.local_name == "type")
+                        .map(|a| a.value.clone());
+                    if name == "sense" {
+                        entry.senses.push(Sense::default());
+                    }
+                    match name.as_str() {
+                        "orth" | "pos" | "def" | "quote" if text.is_none() =>
+                            text = Some((String::new(), stack.len())),
+                        _ => (),
+                    }
+                    stack.push(Element { name, cit_type });
+                },
+                XmlEvent::Characters(content) | XmlEvent::CData(content) =>
+                    if let Some((ref mut text, _)) = text {
+                        text.push_str(&content);
+                    },
+                XmlEvent::Whitespace(_) => if let Some((ref mut text, _)) = text {
+                    text.push(' ');
+                },
+                XmlEvent::EndElement { .. } => {
+                    let element = match stack.pop() {
+                        Some(element) => element,
+                        None => break, // </entry>
+                    };
+                    let finished = match text {
+                        Some((_, depth)) => depth == stack.len(),
+                        None => false,
+                    };
+                    if finished {
+                        let content = text.take().unwrap().0.split_whitespace()
+                            .collect::<Vec<_>>().join(" ");
+                        if !content.is_empty() {
+                            add_to_entry(&mut entry, &stack, &element.name, content);
+                        }
+                    }
+                },
+                _ => (),
+            }
+        }
+        entry.senses.retain(|s| *s != Sense::default());
+        Ok(entry)
+    }
+}
+
+// file a text element according to where it occurs within the entry
+fn add_to_entry(entry: &mut DictEntry, ancestors: &[Element], name: &str, content: String) {
+    let in_sense = ancestors.iter().any(|e| e.name == "sense");
+    let citations: Vec<&str> = ancestors.iter().filter(|e| e.name == "cit")
+        .map(|e| e.cit_type.as_ref().map(|t| t.as_str()).unwrap_or("")).collect();
+    let in_related = ancestors.iter().any(|e| e.name == "re");
+    if in_related {
+        return;
+    }
+    if !in_sense && citations.is_empty() && (name == "orth" || name == "pos") {
+        match name {
+            "orth" => entry.headwords.push(content),
+            _ => if entry.part_of_speech.is_none() {
+                entry.part_of_speech = Some(content);
+            },
+        }
+        return;
+    }
+    // some dictionaries put translations directly into the entry
+    if entry.senses.is_empty() {
+        entry.senses.push(Sense::default());
+    }
+    let sense = entry.senses.last_mut().unwrap();
+    // quotes within nested citations are translations of examples and the like
+    match (name, citations.len(), citations.first()) {
+        ("def", 0, _) => sense.definitions.push(content),
+        ("quote", 1, Some(&"trans")) => sense.translations.push(content),
+        ("quote", 1, Some(&"example")) => sense.examples.push(content),
+        _ => (),
+    }
+}
+
+impl<R: Read> Iterator for EntryReader<R> {
+    type Item = Result<DictEntry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+        loop {
+            match self.events.next() {
+                Some(Ok(XmlEvent::StartElement { ref name, .. }))
+                        if name.local_name == "entry" => {
+                    let entry = self.read_entry();
+                    if entry.is_err() {
+                        self.finished = true;
+                    }
+                    return Some(entry);
+                },
+                Some(Ok(XmlEvent::EndDocument)) | None => {
+                    self.finished = true;
+                    return None;
+                },
+                Some(Err(e)) => {
+                    self.finished = true;
+                    return Some(Err(From::from(e)));
+                },
+                Some(Ok(_)) => (),
+            }
+        }
+    }
+}
+
+/// Open a FreeDict dictionary for reading.
+pub fn read_dictionary(path: &Path) -> Result<EntryReader<Box<Read>>> {
+    let reader = common::decoding_reader(BufReader::new(common::open_file(path)?))?;
+    Ok(EntryReader::new(Box::new(BufReader::new(reader))))
+}
+
+/// Find the dictionaries (`.tei` files) below a directory (or the given file).
+pub fn find_dictionaries(path: &Path) -> Result<Vec<PathBuf>> {
+    match path.is_dir() {
+        true => common::find_files(path, "tei"),
+        false => Ok(vec![path.to_path_buf()]),
+    }
+}
+
+/// Collect the headwords of the given dictionaries.
+///
+/// Dictionaries with another source language are skipped.
+pub fn read_headwords(path: &Path, language: &Language) -> Result<BTreeSet<String>> {
+    let mut headwords = BTreeSet::new();
+    for dictionary in find_dictionaries(path)? {
+        if !is_source_language(&dictionary, language) {
+            continue;
+        }
+        for entry in read_dictionary(&dictionary)? {
+            headwords.extend(entry?.headwords);
+        }
+    }
+    Ok(headwords)
+}
+
+/// Iterator over the example quotes of FreeDict dictionaries
+///
+/// One entity is emitted per dictionary, each example forming a context line. The headwords can
+/// be written to a word list (one per line) while reading, see
+/// [`set_headword_output`](#method.set_headword_output).
+pub struct FreedictExamples {
+    dictionaries: VecDeque<PathBuf>,
+    headword_output: Option<Box<Write>>,
+    headwords: BTreeSet<String>,
+}
+
+impl FreedictExamples {
+    /// Read all dictionaries below a directory (or the given file) with the given source
+    /// language.
+    pub fn new(path: &Path, language: Language) -> Result<FreedictExamples> {
+        let dictionaries = find_dictionaries(path)?.into_iter()
+            .filter(|d| is_source_language(d, &language)).collect();
+        Ok(FreedictExamples { dictionaries, headword_output: None,
+            headwords: BTreeSet::new() })
+    }
+
+    /// Write the headwords of all dictionaries to the given output, one per line, sorted and
+    /// without duplicates. The list is written when the last dictionary has been read.
+    pub fn set_headword_output(&mut self, output: Box<Write>) {
+        self.headword_output = Some(output);
+    }
+
+    fn read_examples(&mut self, path: &Path) -> Result<String> {
+        let separator = format!(" {} ", textfilter::RETURN_ESCAPE_SEQUENCE);
+        let mut examples = Vec::new();
+        for entry in read_dictionary(path)? {
+            let entry = entry?;
+            examples.extend(entry.examples().iter().map(|e| e.to_string()));
+            if self.headword_output.is_some() {
+                self.headwords.extend(entry.headwords);
+            }
+        }
+        Ok(examples.join(&separator))
+    }
+
+    fn write_headwords(&mut self) -> Result<()> {
+        if let Some(mut output) = self.headword_output.take() {
+            for headword in &self.headwords {
+                writeln!(output, "{}", headword)?;
+            }
+            output.flush()?;
+        }
+        Ok(())
+    }
+}
+
+impl Iterator for FreedictExamples {
+    type Item = Result<Entity>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let path = match self.dictionaries.pop_front() {
+                Some(path) => path,
+                None => {
+                    trysome!(self.write_headwords());
+                    return None;
+                },
+            };
+            let examples = trysome!(self.read_examples(&path).map_err(
+                    |mut e: TransformationError| {
+                        e.inject_position(PositionType::InDirectory(path.clone())); e }));
+            if examples.is_empty() {
+                continue;
+            }
+            let title = path.file_stem().map(|s| s.to_string_lossy().into_owned());
+            let mut entity = Entity::with_path(examples, path);
+            entity.metadata = Metadata { title, ..Metadata::default() };
+            return Some(Ok(entity));
+        }
+    }
+}
+
@@ -46,7 +46,7 @@ impl Unformatter for Gutenberg {
 
         // some books contain arbitrari hyphens, which often fill the gaps between two words:
         Ok(Entity { content: content[start..end].replace("--", " "),
-            position: input.position.clone() })
+            position: input.position.clone(), metadata: input.metadata.clone() })
     }
 }
 
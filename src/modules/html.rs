@@ -0,0 +1,544 @@
+//! Web pages and web archives
+//!
+//! This module reads HTML files (`.html`, `.htm`) and WARC archives (`.warc`, `.warc.gz`), e.g. a
+//! locally stored Common Crawl segment. Of a WARC archive, only `response` records with an HTML
+//! payload are considered.
+//!
+//! Web pages contain a lot of boilerplate: navigation, footers, cookie banners, link lists. The
+//! main content is found with a text density heuristic, similar to the one of boilerpipe: the page
+//! is split into text blocks at block-level elements and each block is classified by its number
+//! of words and its link density (the share of words within links). Long blocks with few links
+//! are kept, short blocks only if they are surrounded by content. Elements which are
+//! boilerplate by definition (`<nav>`, `<footer>`, `<script>`, ...) or by their class or ID (e.g.
+//! `cookie-banner`) are skipped entirely.
+//!
+//! The language of a page is taken from `<html lang>` or, if absent, from the `Content-Language`
+//! of the HTTP response or a `<meta http-equiv>` element. Pages declaring another language are
+//! skipped, pages without a declaration are kept.
+
+use flate2::read::MultiGzDecoder;
+use htmlstream::{self, HTMLTagState};
+use isolang::Language;
+use std::collections::{HashMap, VecDeque};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+
+use common::{self, SingleByteReader};
+use input_source::{Entity, Metadata, PositionType, Result, TransformationError};
+use super::tmx::language_matches;
+use textfilter;
+
+/// Blocks with at least this many words are considered content (if their link density is low).
+pub static DEFAULT_MIN_WORDS: usize = 15;
+
+/// Blocks with a higher share of words within links are boilerplate.
+static MAX_LINK_DENSITY: f32 = 0.33;
+
+/// Short blocks need at least this many words to be kept next to content blocks.
+static MIN_WORDS_NEXT_TO_CONTENT: usize = 5;
+
+/// Elements whose content is never part of the main text.
+static SKIPPED_ELEMENTS: [&str; 13] = ["script", "style", "noscript", "nav", "header", "footer",
+    "aside", "form", "button", "select", "template", "iframe", "svg"];
+
+/// Words of class names or IDs (separated by hyphens or underscores) marking boilerplate
+/// elements.
+static BOILERPLATE_MARKERS: [&str; 12] = ["cookie", "consent", "gdpr", "banner", "newsletter",
+    "breadcrumb", "navbar", "menu", "sidebar", "share", "social", "footer"];
+
+/// Elements holding the whole page or its main content, never skipped by their class or ID.
+static CONTAINER_ELEMENTS: [&str; 4] = ["html", "body", "main", "article"];
+
+/// Elements without content and closing tag.
+static VOID_ELEMENTS: [&str; 14] = ["area", "base", "br", "col", "embed", "hr", "img", "input",
+    "link", "meta", "param", "source", "track", "wbr"];
+
+/// Elements starting a new text block.
+static BLOCK_ELEMENTS: [&str; 21] = ["p", "div", "li", "ul", "ol", "h1", "h2", "h3", "h4", "h5",
+    "h6", "td", "th", "tr", "table", "section", "article", "main", "blockquote", "pre", "dd"];
+
+/// A web page, reduced to its main content.
+#[derive(Debug, Default)]
+pub struct Document {
+    /// text blocks of the main content, separated by context line breaks
+    pub text: String,
+    pub title: Option<String>,
+    /// value of `<html lang>`
+    pub language: Option<String>,
+    /// value of `<meta http-equiv="Content-Language">`
+    pub meta_language: Option<String>,
+}
+
+// a block of text between two block-level elements
+#[derive(Default)]
+struct Block {
+    text: String,
+    words: usize,
+    link_words: usize,
+    is_heading: bool,
+}
+
+impl Block {
+    fn link_density(&self) -> f32 {
+        match self.words {
+            0 => 0.0,
+            w => self.link_words as f32 / w as f32,
+        }
+    }
+}
+
+/// Decode HTML character references like `&amp;`, `&#228;` or `&#xE4;`.
+///
+/// Only the most common named references are known, others are left untouched.
+pub fn decode_entities(text: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        output.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let end = match rest.find(';') {
+            Some(end) if end <= 10 => end,
+            _ => {
+                output.push('&');
+                rest = &rest[1..];
+                continue;
+            },
+        };
+        let entity = &rest[1..end];
+        let decoded = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some(' '),
+            _ if entity.starts_with("#x") || entity.starts_with("#X") =>
+                u32::from_str_radix(&entity[2..], 16).ok().and_then(::std::char::from_u32),
+            _ if entity.starts_with('#') =>
+                entity[1..].parse::<u32>().ok().and_then(::std::char::from_u32),
+            _ => None,
+        };
+        match decoded {
+            Some(c) => {
+                output.push(c);
+                rest = &rest[end + 1..];
+            },
+            None => {
+                output.push('&');
+                rest = &rest[1..];
+            },
+        }
+    }
+    output.push_str(rest);
+    output
+}
+
+fn attribute(attributes: &str, name: &str) -> Option<String> {
+    htmlstream::attr_iter(&attributes.to_string()).map(|(_, a)| a)
+        .find(|a| a.name.to_lowercase() == name).map(|a| a.value)
+}
+
+fn is_boilerplate_element(name: &str, attributes: &str) -> bool {
+    if SKIPPED_ELEMENTS.contains(&name) {
+        return true;
+    }
+    if CONTAINER_ELEMENTS.contains(&name) {
+        return false;
+    }
+    let identifiers = format!("{} {}", attribute(attributes, "class").unwrap_or_default(),
+            attribute(attributes, "id").unwrap_or_default()).to_lowercase();
+    identifiers.split(|c: char| c.is_whitespace() || c == '-' || c == '_')
+        .any(|word| BOILERPLATE_MARKERS.contains(&word))
+}
+
+/// Extract the main content of a web page.
+///
+/// Blocks with at least `min_words` words and a low link density are considered content, see
+/// the module documentation.
+pub fn extract_main_content(html: &str, min_words: usize) -> Document {
+    let mut document = Document::default();
+    let mut blocks: Vec<Block> = Vec::new();
+    let mut current = Block::default();
+    // name of the skipped element and the nesting depth of elements with the same name
+    let mut skipping: Option<(String, usize)> = None;
+    let mut link_depth = 0;
+    let mut in_title = false;
+    let mut title = String::new();
+
+    for (_, tag) in htmlstream::tag_iter(html) {
+        let name = tag.name.to_lowercase();
+        if in_title {
+            if let HTMLTagState::Text = tag.state {
+                title.push_str(&tag.html);
+                continue;
+            }
+            in_title = false;
+        }
+        match name.as_str() {
+            "title" if title.is_empty() => if let HTMLTagState::Opening = tag.state {
+                in_title = true;
+                continue;
+            },
+            "html" if is_opening(&tag.state) => document.language =
+                attribute(&tag.attributes, "lang")
+                    .or_else(|| attribute(&tag.attributes, "xml:lang")),
+            "meta" => if attribute(&tag.attributes, "http-equiv")
+                    .map(|h| h.eq_ignore_ascii_case("content-language")).unwrap_or(false) {
+                document.meta_language = attribute(&tag.attributes, "content");
+            },
+            _ => (),
+        }
+
+        if let Some((skipped, depth)) = skipping.take() {
+            let depth = match tag.state {
+                HTMLTagState::Opening if name == skipped => depth + 1,
+                HTMLTagState::Closing if name == skipped => depth - 1,
+                _ => depth,
+            };
+            if depth > 0 {
+                skipping = Some((skipped, depth));
+            }
+            continue;
+        }
+
+        match tag.state {
+            HTMLTagState::Text => {
+                let text = decode_entities(&tag.html);
+                let words = text.split_whitespace().collect::<Vec<_>>();
+                current.words += words.len();
+                if link_depth > 0 {
+                    current.link_words += words.len();
+                }
+                if !words.is_empty() {
+                    if !current.text.is_empty() {
+                        current.text.push(' ');
+                    }
+                    current.text.push_str(&words.join(" "));
+                }
+            },
+            // void elements have no closing tag which would end the skipping
+            HTMLTagState::Opening if !VOID_ELEMENTS.contains(&name.as_str())
+                    && is_boilerplate_element(&name, &tag.attributes) =>
+                skipping = Some((name, 1)),
+            ref state => {
+                if name == "a" {
+                    match *state {
+                        HTMLTagState::Opening => link_depth += 1,
+                        HTMLTagState::Closing if link_depth > 0 => link_depth -= 1,
+                        _ => (),
+                    }
+                }
+                if BLOCK_ELEMENTS.contains(&name.as_str()) {
+                    let finished = ::std::mem::replace(&mut current, Block::default());
+                    if finished.words > 0 {
+                        blocks.push(finished);
+                    }
+                    current.is_heading = is_opening(state) && name.len() == 2
+                        && name.starts_with('h');
+                }
+            },
+        }
+    }
+    if current.words > 0 {
+        blocks.push(current);
+    }
+
+    let title = decode_entities(title.trim());
+    if !title.is_empty() {
+        document.title = Some(title);
+    }
+    document.text = classify_blocks(&blocks, min_words);
+    document
+}
+
+fn is_opening(state: &HTMLTagState) -> bool {
+    match *state {
+        HTMLTagState::Opening | HTMLTagState::SelfClosing => true,
+        _ => false,
+    }
+}
+
+// select the content blocks and join them
+fn classify_blocks(blocks: &[Block], min_words: usize) -> String {
+    let is_content = |b: &Block| b.words >= min_words && b.link_density() <= MAX_LINK_DENSITY;
+    let content: Vec<bool> = blocks.iter().map(|b| is_content(b)).collect();
+    let separator = format!(" {} ", textfilter::RETURN_ESCAPE_SEQUENCE);
+    let mut kept = Vec::new();
+    for (index, block) in blocks.iter().enumerate() {
+        let previous = index > 0 && content[index - 1];
+        let next = index + 1 < blocks.len() && content[index + 1];
+        let keep = content[index]
+            // headings introducing content
+            || (block.is_heading && next)
+            // short paragraphs within the content
+            || (block.words >= MIN_WORDS_NEXT_TO_CONTENT && previous && next
+                && block.link_density() <= MAX_LINK_DENSITY);
+        if keep {
+            kept.push(block.text.trim());
+        }
+    }
+    kept.join(&separator)
+}
+
+// characters of ISO 8859-15 which differ from ISO 8859-1, by their ISO 8859-1 counterpart
+static ISO_8859_15: [(char, char); 8] = [('¤', '€'), ('¦', 'Š'), ('¨', 'š'), ('´', 'Ž'),
+    ('¸', 'ž'), ('¼', 'Œ'), ('½', 'œ'), ('¾', 'Ÿ')];
+
+/// Decode the bytes of a web page to a String.
+///
+/// The character set is taken from the `Content-Type` header, if given, or from a `<meta>`
+/// element within the first kilobyte. ISO 8859-1, ISO 8859-15 and Windows-1252 are converted,
+/// everything else is decoded as UTF-8 with invalid sequences being replaced.
+pub fn decode_page(body: &[u8], content_type: Option<&str>) -> String {
+    let head = String::from_utf8_lossy(&body[..body.len().min(1024)]).to_lowercase();
+    let charset = content_type.map(|c| c.to_lowercase()).and_then(|c| c.find("charset=")
+            .map(|i| c[i + 8..].to_string()))
+        .or_else(|| head.find("charset=").map(|i| head[i + 8..].to_string()))
+        .map(|c| c.trim_matches(|c: char| c == '"' || c == '\'').chars()
+            .take_while(|c| c.is_alphanumeric() || *c == '-' || *c == '_').collect::<String>());
+    let charset = charset.as_ref().map(|c| c.as_str());
+    let windows_1252 = match charset {
+        Some("iso-8859-1") | Some("latin1") | Some("iso-8859-15") | Some("latin9") =>
+            Some(false),
+        Some("windows-1252") | Some("cp1252") => Some(true),
+        _ => None,
+    };
+    match windows_1252 {
+        Some(windows_1252) => {
+            let mut text = String::with_capacity(body.len());
+            if SingleByteReader::new(body, windows_1252).read_to_string(&mut text).is_err() {
+                return String::from_utf8_lossy(body).into_owned();
+            }
+            match charset {
+                Some("iso-8859-15") | Some("latin9") => text.chars().map(|c|
+                        match ISO_8859_15.iter().find(|&&(latin1, _)| latin1 == c) {
+                    Some(&(_, latin9)) => latin9,
+                    None => c,
+                }).collect(),
+                _ => text,
+            }
+        },
+        None => String::from_utf8_lossy(body).into_owned(),
+    }
+}
+
+/// A record from a WARC archive.
+pub struct WarcRecord {
+    /// header fields, with lower-case names
+    pub headers: HashMap<String, String>,
+    pub payload: Vec<u8>,
+}
+
+// parse "Name: value" lines until an empty line; returns None at the end of the input
+fn read_headers<R: BufRead>(input: &mut R, headers: &mut HashMap<String, String>)
+        -> Result<bool> {
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        if input.read_until(b'\n', &mut line)? == 0 {
+            return Ok(false);
+        }
+        let text = String::from_utf8_lossy(&line);
+        let text = text.trim_end();
+        if text.is_empty() {
+            return Ok(true);
+        }
+        if let Some(colon) = text.find(':') {
+            headers.insert(text[..colon].trim().to_lowercase(), text[colon + 1..].trim()
+                    .to_string());
+        }
+    }
+}
+
+/// Reader for the records of a WARC archive
+pub struct WarcReader<R: BufRead> {
+    input: R,
+}
+
+impl<R: BufRead> WarcReader<R> {
+    pub fn new(input: R) -> WarcReader<R> {
+        WarcReader { input }
+    }
+
+    /// Read the next record, `None` at the end of the archive.
+    pub fn next_record(&mut self) -> Result<Option<WarcRecord>> {
+        // skip blank lines between records and find the version line
+        let mut line = Vec::new();
+        loop {
+            line.clear();
+            if self.input.read_until(b'\n', &mut line)? == 0 {
+                return Ok(None);
+            }
+            if line.starts_with(b"WARC/") {
+                break;
+            }
+            if !line.iter().all(|b| (*b as char).is_whitespace()) {
+                return Err(TransformationError::ErrorneousStructure(
+                        "expected WARC record header".into(), PositionType::None));
+            }
+        }
+        let mut headers = HashMap::new();
+        if !read_headers(&mut self.input, &mut headers)? {
+            return Ok(None);
+        }
+        let length = headers.get("content-length").and_then(|l| l.parse::<u64>().ok())
+            .ok_or(TransformationError::ErrorneousStructure(
+                    "WARC record without Content-Length".into(), PositionType::None))?;
+        // the buffer grows while reading, the length might be corrupt
+        let mut payload = Vec::new();
+        (&mut self.input).take(length).read_to_end(&mut payload)?;
+        if payload.len() as u64 != length {
+            return Err(TransformationError::ErrorneousStructure(format!(
+                    "WARC record truncated after {} of {} bytes", payload.len(), length),
+                    PositionType::None));
+        }
+        Ok(Some(WarcRecord { headers, payload }))
+    }
+}
+
+/// Split an HTTP response into its (lower-case) headers and body.
+pub fn parse_http_response(response: &[u8]) -> Result<(HashMap<String, String>, &[u8])> {
+    let mut reader = response;
+    let mut status = Vec::new();
+    reader.read_until(b'\n', &mut status)?;
+    if !status.starts_with(b"HTTP/") {
+        return Err(TransformationError::ErrorneousStructure("not an HTTP response".into(),
+                PositionType::None));
+    }
+    let mut headers = HashMap::new();
+    read_headers(&mut reader, &mut headers)?;
+    Ok((headers, reader))
+}
+
+/// Iterator over the main content of web pages from HTML files and WARC archives
+pub struct WebDocuments {
+    files: VecDeque<PathBuf>,
+    /// currently opened archive
+    archive: Option<(WarcReader<BufReader<Box<Read>>>, PathBuf)>,
+    language: Language,
+    min_words: usize,
+}
+
+fn has_extension(path: &Path, extensions: &[&str]) -> bool {
+    path.file_name().and_then(|n| n.to_str()).map(|name| {
+        let name = name.to_lowercase();
+        extensions.iter().any(|e| name.ends_with(e))
+    }).unwrap_or(false)
+}
+
+impl WebDocuments {
+    /// Read all HTML files and WARC archives below a directory (or the given file).
+    pub fn new(path: &Path, language: Language) -> Result<WebDocuments> {
+        let files = match path.is_dir() {
+            true => {
+                let mut files = Vec::new();
+                for extension in &["html", "htm", "warc", "gz"] {
+                    files.extend(common::find_files(path, extension)?.into_iter()
+                        .filter(|f| has_extension(f, &[".html", ".htm", ".warc",
+                                ".warc.gz"])));
+                }
+                files.sort();
+                files
+            },
+            false => vec![path.to_path_buf()],
+        };
+        Ok(WebDocuments { files: files.into_iter().collect(), archive: None, language,
+            min_words: DEFAULT_MIN_WORDS })
+    }
+
+    /// Set the minimum number of words of a text block to be considered content.
+    pub fn set_min_words(&mut self, min_words: usize) {
+        self.min_words = min_words;
+    }
+
+    // extract the content of a page; None if it is in another language or has no content
+    fn make_entity(&self, html: &str, header_language: Option<&String>, position: PositionType)
+            -> Option<Entity> {
+        let document = extract_main_content(html, self.min_words);
+        let declared = document.language.clone()
+            .or_else(|| header_language.cloned())
+            .or_else(|| document.meta_language.clone());
+        if let Some(ref declared) = declared {
+            if !declared.split(',').any(|tag| language_matches(tag.trim(), &self.language)) {
+                debug!("{}: skipping page in language {}", position, declared);
+                return None;
+            }
+        }
+        if document.text.is_empty() {
+            return None;
+        }
+        Some(Entity { content: document.text, position, metadata: Metadata {
+            title: document.title, language: declared, ..Metadata::default() } })
+    }
+
+    fn open_archive(path: &Path) -> Result<WarcReader<BufReader<Box<Read>>>> {
+        let file = File::open(path)?;
+        let reader: Box<Read> = match has_extension(path, &[".gz"]) {
+            // Common Crawl archives consist of one gzip member per record
+            true => Box::new(MultiGzDecoder::new(file)?),
+            false => Box::new(file),
+        };
+        Ok(WarcReader::new(BufReader::new(reader)))
+    }
+}
+
+impl Iterator for WebDocuments {
+    type Item = Result<Entity>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some((mut archive, path)) = self.archive.take() {
+                let position = PositionType::InDirectory(path.clone());
+                let record = match archive.next_record() {
+                    Ok(Some(record)) => record,
+                    Ok(None) => continue,
+                    Err(mut e) => {
+                        e.inject_position(position);
+                        return Some(Err(e));
+                    },
+                };
+                self.archive = Some((archive, path));
+                if record.headers.get("warc-type").map(|t| t != "response").unwrap_or(true) {
+                    continue;
+                }
+                let (headers, body) = match parse_http_response(&record.payload) {
+                    Ok(response) => response,
+                    Err(_) => continue, // not an HTTP response
+                };
+                let content_type = headers.get("content-type").map(|c| c.as_str());
+                if !content_type.map(|c| c.contains("html")).unwrap_or(false) {
+                    continue;
+                }
+                let html = decode_page(body, content_type);
+                if let Some(entity) = self.make_entity(&html, headers.get("content-language"),
+                        position) {
+                    return Some(Ok(entity));
+                }
+                continue;
+            }
+
+            let path = get!(self.files.pop_front());
+            if has_extension(&path, &[".warc", ".warc.gz"]) {
+                match WebDocuments::open_archive(&path) {
+                    Ok(archive) => self.archive = Some((archive, path)),
+                    Err(mut e) => {
+                        e.inject_position(PositionType::InDirectory(path));
+                        return Some(Err(e));
+                    },
+                }
+                continue;
+            }
+            let mut body = Vec::new();
+            if let Err(e) = File::open(&path).and_then(|mut f| f.read_to_end(&mut body)) {
+                return Some(Err(TransformationError::IoError(e,
+                        PositionType::InDirectory(path))));
+            }
+            let html = decode_page(&body, None);
+            if let Some(entity) = self.make_entity(&html, None,
+                    PositionType::InDirectory(path)) {
+                return Some(Ok(entity));
+            }
+        }
+    }
+}
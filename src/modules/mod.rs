@@ -3,8 +3,21 @@
 //! `UnFormatter` trait, which defines preprocessing functionality for problematic input data which
 //! leads to pandoc crashes.
 
+pub mod alto;
 pub mod codecivil;
+pub mod conllu;
 pub mod dgt;
+pub mod documents;
+pub mod epub;
 pub mod europeana;
+pub mod freedict;
 pub mod gutenberg;
+pub mod html;
+pub mod moses;
+pub mod subtitles;
+pub mod tatoeba;
+pub mod tei;
+pub mod tmx;
 pub mod wikipedia;
+pub mod wikisource;
+pub mod wiktionary;
@@ -0,0 +1,207 @@
+//! Line-aligned parallel corpora (Moses format)
+//!
+//! Many free parallel corpora, e.g. the OPUS downloads or the Europarl v7 pairs, are distributed
+//! as two plain text files with one sentence per line, where line *n* of one file is the
+//! translation of line *n* of the other. The files only differ in their suffix, which usually is
+//! the ISO 639-1 code of the language, e.g. `Europarl.de-en.de` and `Europarl.de-en.en`. The files
+//! may be compressed with gzip or bzip2 (`Europarl.de-en.de.gz`).
+//!
+//! This module either emits the sentences of one side as monolingual text, one sentence per
+//! context line, or aligned pairs. For the latter, the line counts of both files are verified
+//! before reading.
+
+use std::collections::VecDeque;
+use std::fs;
+use std::io::{BufRead, BufReader, Lines, Read};
+use std::path::{Path, PathBuf};
+
+use common;
+use input_source::{AlignedPair, Entity, PositionType, Result, TransformationError};
+use textfilter;
+
+// maximum size of the text of an entity; larger files are split into several entities
+static MAX_BUFFER_SIZE: usize = 1048576; // 1M
+
+type LineReader = Lines<BufReader<Box<Read>>>;
+
+// split off a compression suffix
+fn strip_compression(file_name: &str) -> (&str, &str) {
+    for compression in &[".gz", ".bz2"] {
+        if file_name.ends_with(compression) {
+            return file_name.split_at(file_name.len() - compression.len());
+        }
+    }
+    (file_name, "")
+}
+
+/// Find all corpus files with the given language suffix (without the dot) in a directory.
+///
+/// Compressed files (`.gz`, `.bz2`) are included. If a file is given, it is returned.
+pub fn corpus_files(path: &Path, suffix: &str) -> Result<Vec<PathBuf>> {
+    if !path.is_dir() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+    let suffix = format!(".{}", suffix);
+    let mut files = Vec::new();
+    for entry in fs::read_dir(path)? {
+        let entry_path = entry?.path();
+        let matches = entry_path.file_name().and_then(|n| n.to_str()).map(|name|
+                strip_compression(name).0.ends_with(&suffix)).unwrap_or(false);
+        if matches && entry_path.is_file() {
+            files.push(entry_path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Derive the path of the aligned counterpart of a corpus file by exchanging the suffix.
+///
+/// Returns `None` if the file name doesn't end on the given suffix.
+pub fn counterpart(path: &Path, suffix: &str, other_suffix: &str) -> Option<PathBuf> {
+    let file_name = get!(path.file_name().and_then(|n| n.to_str()));
+    let (name, compression) = strip_compression(file_name);
+    let suffix = format!(".{}", suffix);
+    if !name.ends_with(&suffix) {
+        return None;
+    }
+    Some(path.with_file_name(format!("{}.{}{}", &name[..name.len() - suffix.len()],
+            other_suffix, compression)))
+}
+
+fn open_lines(path: &Path) -> Result<LineReader> {
+    Ok(BufReader::new(common::open_file(path)?).lines())
+}
+
+fn count_lines(path: &Path) -> Result<u64> {
+    let mut count = 0;
+    for line in open_lines(path)? {
+        line?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+/// Iterator over the sentences of one side of a Moses corpus
+///
+/// The sentences are grouped into entities of about 1 MB, each sentence forming its own context
+/// line.
+pub struct MosesFiles {
+    files: VecDeque<PathBuf>,
+    /// lines of the current file, its path and the current line number
+    current: Option<(LineReader, PathBuf, u64)>,
+}
+
+impl MosesFiles {
+    /// Read all files with the given language suffix from a directory (or the given file).
+    pub fn new(path: &Path, suffix: &str) -> Result<MosesFiles> {
+        Ok(MosesFiles { files: corpus_files(path, suffix)?.into_iter().collect(),
+            current: None })
+    }
+}
+
+impl Iterator for MosesFiles {
+    type Item = Result<Entity>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (mut lines, path, mut line_number) = match self.current.take() {
+                Some(current) => current,
+                None => {
+                    let path = get!(self.files.pop_front());
+                    (trysome!(open_lines(&path)), path, 0)
+                },
+            };
+            let first_line = line_number + 1;
+            let mut text = String::new();
+            while text.len() < MAX_BUFFER_SIZE {
+                match lines.next() {
+                    Some(line) => {
+                        line_number += 1;
+                        text.push_str(&trysome!(line.map_err(|e| TransformationError::IoError(e,
+                                PositionType::InFile(path.clone(), line_number, 0)))));
+                        text.push_str(&format!(" {} ", textfilter::RETURN_ESCAPE_SEQUENCE));
+                    },
+                    None => break,
+                }
+            }
+            if text.len() >= MAX_BUFFER_SIZE { // more to come from this file
+                self.current = Some((lines, path.clone(), line_number));
+            }
+            if !text.is_empty() {
+                return Some(Ok(Entity::with_exact_pos(text, path, first_line, 1)));
+            }
+        }
+    }
+}
+
+/// Iterator over aligned pairs from a Moses corpus
+///
+/// For each file with the source suffix, the file with the target suffix is read alongside. Pairs
+/// with an empty side are skipped. A corpus whose files differ in the number of lines is skipped
+/// with an error, since the alignment cannot be trusted.
+pub struct MosesPairs {
+    /// pairs of corpus files
+    files: VecDeque<(PathBuf, PathBuf)>,
+    /// lines of both files of the current pair, path of the source file and line number
+    current: Option<(LineReader, LineReader, PathBuf, u64)>,
+}
+
+impl MosesPairs {
+    pub fn new(path: &Path, source_suffix: &str, target_suffix: &str) -> Result<MosesPairs> {
+        let mut files = VecDeque::new();
+        for source in corpus_files(path, source_suffix)? {
+            match counterpart(&source, source_suffix, target_suffix) {
+                Some(ref target) if target.exists() => files.push_back((source, target.clone())),
+                _ => warn!("no aligned file with suffix .{} found for {}", target_suffix,
+                           source.to_string_lossy()),
+            }
+        }
+        Ok(MosesPairs { files, current: None })
+    }
+
+    // open the next pair of files, after verifying the line counts
+    fn open_next(&mut self) -> Option<Result<()>> {
+        let (source, target) = get!(self.files.pop_front());
+        let source_lines = trysome!(count_lines(&source));
+        let target_lines = trysome!(count_lines(&target));
+        if source_lines != target_lines {
+            return Some(Err(TransformationError::ErrorneousStructure(format!(
+                    "line counts differ: {} has {} lines, {} has {} lines",
+                    source.to_string_lossy(), source_lines, target.to_string_lossy(),
+                    target_lines), PositionType::InDirectory(source))));
+        }
+        self.current = Some((trysome!(open_lines(&source)), trysome!(open_lines(&target)),
+                source, 0));
+        Some(Ok(()))
+    }
+}
+
+impl Iterator for MosesPairs {
+    type Item = Result<AlignedPair>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.current.is_none() {
+                trysome!(get!(self.open_next()));
+            }
+            let (mut source_lines, mut target_lines, path, mut line_number) =
+                self.current.take().unwrap(); // set above
+            match (source_lines.next(), target_lines.next()) {
+                (Some(source), Some(target)) => {
+                    line_number += 1;
+                    let position = PositionType::InFile(path.clone(), line_number, 0);
+                    self.current = Some((source_lines, target_lines, path, line_number));
+                    let source = trysome!(source.map_err(|e|
+                            TransformationError::IoError(e, position.clone())));
+                    let target = trysome!(target.map_err(|e|
+                            TransformationError::IoError(e, position.clone())));
+                    if !source.trim().is_empty() && !target.trim().is_empty() {
+                        return Some(Ok(AlignedPair { source, target, position }));
+                    }
+                },
+                _ => (), // end of corpus, continue with the next one
+            }
+        }
+    }
+}
@@ -0,0 +1,283 @@
+//! Subtitles (SRT, WebVTT and OpenSubtitles XML)
+//!
+//! Subtitles are a source of spoken language, which is rare in encyclopedic or legal texts. This
+//! module reads SubRip (`.srt`) and WebVTT (`.vtt`) files as well as the XML format of the
+//! OpenSubtitles corpus from OPUS (`.xml`, `.xml.gz`), in which the subtitles are already split
+//! into (tokenised) sentences with time stamps.
+//!
+//! Timing information and styling tags are removed. Cues which split a sentence are merged. The
+//! cues are grouped into dialogue scenes: whenever the silence between two cues exceeds a
+//! threshold (see [`DEFAULT_SCENE_GAP`](static.DEFAULT_SCENE_GAP.html)), a new scene, i.e. a new
+//! context line, starts. One entity is emitted per file.
+
+use std::collections::VecDeque;
+use std::io::{BufReader, Read};
+use std::path::{Path, PathBuf};
+use xml::reader::{EventReader, XmlEvent};
+
+use common::{self, SingleByteReader};
+use input_source::{Entity, Metadata, PositionType, Result, TransformationError};
+use textfilter;
+
+/// Silence between two cues (in seconds) which starts a new scene, unless configured otherwise.
+pub static DEFAULT_SCENE_GAP: f64 = 5.0;
+
+/// A subtitle cue: text displayed within a time span.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Cue {
+    /// start in seconds
+    pub start: f64,
+    /// end in seconds
+    pub end: f64,
+    pub text: String,
+}
+
+/// Parse a time stamp like `01:02:03,456` (SRT), `01:02:03.456` or `02:03.456` (WebVTT) into
+/// seconds.
+pub fn parse_timestamp(timestamp: &str) -> Option<f64> {
+    let timestamp = timestamp.trim().replace(',', ".");
+    let mut seconds = 0.0;
+    for part in timestamp.split(':') {
+        seconds = seconds * 60.0 + get!(part.trim().parse::<f64>().ok());
+    }
+    Some(seconds)
+}
+
+/// Remove styling tags (`<i>`, `<font ...>`, `<v Speaker>`, `<00:01.000>`), SSA override codes
+/// (`{\an8}`), descriptions of sounds in square brackets and music notes from the text of a cue.
+///
+/// An opening `<`, `{` or `[` without its closing counterpart in the cue is kept, e.g. in "I <3
+/// you".
+pub fn strip_tags(text: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        let after = &rest[c.len_utf8()..];
+        let closing = match c {
+            '<' => Some('>'),
+            '{' => Some('}'),
+            '[' => Some(']'),
+            _ => None,
+        };
+        rest = match closing.and_then(|closing| after.find(closing)) {
+            Some(end) => &after[end + 1..],
+            None => {
+                if c != '♪' && c != '♫' {
+                    output.push(c);
+                }
+                after
+            },
+        };
+    }
+    output.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Parse the cues of a SubRip or WebVTT file.
+///
+/// Blocks without a timing line (the WebVTT header, `NOTE` and `STYLE` blocks) are skipped.
+pub fn parse_cues(content: &str) -> Vec<Cue> {
+    let mut cues = Vec::new();
+    let content = content.replace("\r\n", "\n");
+    for block in content.split("\n\n") {
+        let mut lines = block.lines().skip_while(|l| !l.contains("-->"));
+        let timing = match lines.next() {
+            Some(timing) => timing,
+            None => continue,
+        };
+        let mut times = timing.splitn(2, "-->");
+        let start = times.next().and_then(parse_timestamp);
+        // WebVTT cue settings may follow the end time
+        let end = times.next().and_then(|t| t.split_whitespace().next())
+            .and_then(parse_timestamp);
+        if let (Some(start), Some(end)) = (start, end) {
+            let text = strip_tags(&lines.collect::<Vec<_>>().join(" "));
+            if !text.is_empty() {
+                cues.push(Cue { start, end, text });
+            }
+        }
+    }
+    cues
+}
+
+/// Parse the sentences of an OpenSubtitles XML document as cues.
+///
+/// Sentences without own time stamps inherit the time of the previous sentence.
+pub fn parse_opensubtitles<R: Read>(input: R) -> Result<Vec<Cue>> {
+    let mut cues = Vec::new();
+    let mut current: Option<Cue> = None;
+    let mut last_time = 0.0;
+    for event in EventReader::new(input) {
+        match event? {
+            XmlEvent::StartElement { name, attributes, .. } => match name.local_name.as_str() {
+                "s" => current = Some(Cue { start: last_time, end: last_time,
+                        text: String::new() }),
+                "time" => {
+                    let id = attributes.iter().find(|a| a.name.local_name == "id")
+                        .map(|a| a.value.clone()).unwrap_or_default();
+                    let time = attributes.iter().find(|a| a.name.local_name == "value")
+                        .and_then(|a| parse_timestamp(&a.value));
+                    if let Some(time) = time {
+                        last_time = time;
+                        if let Some(ref mut cue) = current {
+                            match id.ends_with('S') && cue.text.is_empty() {
+                                true => { cue.start = time; cue.end = time; },
+                                false => cue.end = time,
+                            }
+                        }
+                    }
+                },
+                _ => (),
+            },
+            XmlEvent::Characters(text) => if let Some(ref mut cue) = current {
+                if !cue.text.is_empty() {
+                    cue.text.push(' ');
+                }
+                cue.text.push_str(text.trim());
+            },
+            XmlEvent::EndElement { name } => if name.local_name == "s" {
+                if let Some(mut cue) = current.take() {
+                    cue.text = strip_tags(&cue.text);
+                    if !cue.text.is_empty() {
+                        cues.push(cue);
+                    }
+                }
+            },
+            _ => (),
+        }
+    }
+    Ok(cues)
+}
+
+// whether a cue ends a sentence
+fn ends_sentence(text: &str) -> bool {
+    text.trim_end_matches(|c| c == '"' || c == '\'' || c == '»' || c == '«' || c == '”')
+        .ends_with(|c| c == '.' || c == '!' || c == '?' || c == '…')
+}
+
+/// Group cues into scenes of sentences.
+///
+/// A new scene starts whenever the time between the end of a cue and the start of the next one
+/// exceeds `max_gap` seconds. Within a scene, cues which do not end on sentence punctuation are
+/// merged with the following cue.
+pub fn group_scenes(cues: &[Cue], max_gap: f64) -> Vec<Vec<String>> {
+    let mut scenes = Vec::new();
+    let mut scene: Vec<String> = Vec::new();
+    let mut sentence = String::new();
+    let mut previous_end: Option<f64> = None;
+    for cue in cues {
+        if previous_end.map(|end| cue.start - end > max_gap).unwrap_or(false) {
+            if !sentence.is_empty() {
+                scene.push(::std::mem::replace(&mut sentence, String::new()));
+            }
+            if !scene.is_empty() {
+                scenes.push(::std::mem::replace(&mut scene, Vec::new()));
+            }
+        }
+        if !sentence.is_empty() {
+            sentence.push(' ');
+        }
+        sentence.push_str(&cue.text);
+        if ends_sentence(&cue.text) {
+            scene.push(::std::mem::replace(&mut sentence, String::new()));
+        }
+        previous_end = Some(cue.end);
+    }
+    if !sentence.is_empty() {
+        scene.push(sentence);
+    }
+    if !scene.is_empty() {
+        scenes.push(scene);
+    }
+    scenes
+}
+
+// read a text file, falling back to Windows-1252 if it is not valid UTF-8
+fn read_text(path: &Path) -> Result<String> {
+    let mut bytes = Vec::new();
+    common::open_file(path)?.read_to_end(&mut bytes)?;
+    if bytes.starts_with(b"\xEF\xBB\xBF") {
+        bytes.drain(..3);
+    }
+    match String::from_utf8(bytes) {
+        Ok(text) => Ok(text),
+        Err(e) => {
+            let mut text = String::new();
+            SingleByteReader::new(&e.into_bytes()[..], true).read_to_string(&mut text)?;
+            Ok(text)
+        },
+    }
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name().map(|n| n.to_string_lossy().to_lowercase()).unwrap_or_default()
+}
+
+fn is_xml_file(path: &Path) -> bool {
+    let name = file_name(path);
+    name.ends_with(".xml") || name.ends_with(".xml.gz")
+}
+
+/// Iterator over the scenes of subtitle files
+///
+/// Each entity contains the scenes of one file, each scene forming a context line.
+pub struct Subtitles {
+    files: VecDeque<PathBuf>,
+    max_gap: f64,
+}
+
+impl Subtitles {
+    /// Read all subtitle files (`.srt`, `.vtt`, `.xml`, `.xml.gz`) below a directory (or the
+    /// given file).
+    pub fn new(path: &Path) -> Result<Subtitles> {
+        let files = match path.is_dir() {
+            true => {
+                let mut files = Vec::new();
+                for extension in &["srt", "vtt", "xml", "gz"] {
+                    files.extend(common::find_files(path, extension)?.into_iter().filter(|f| {
+                        let name = file_name(f);
+                        !name.ends_with(".gz") || name.ends_with(".xml.gz")
+                    }));
+                }
+                files.sort();
+                files
+            },
+            false => vec![path.to_path_buf()],
+        };
+        Ok(Subtitles { files: files.into_iter().collect(), max_gap: DEFAULT_SCENE_GAP })
+    }
+
+    /// Set the silence between two cues (in seconds) after which a new scene starts.
+    pub fn set_max_gap(&mut self, seconds: f64) {
+        self.max_gap = seconds;
+    }
+
+    fn read_cues(path: &Path) -> Result<Vec<Cue>> {
+        match is_xml_file(path) {
+            true => parse_opensubtitles(BufReader::new(common::open_file(path)?)),
+            false => Ok(parse_cues(&read_text(path)?)),
+        }
+    }
+}
+
+impl Iterator for Subtitles {
+    type Item = Result<Entity>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let path = get!(self.files.pop_front());
+            let cues = trysome!(Subtitles::read_cues(&path).map_err(
+                    |mut e: TransformationError| {
+                        e.inject_position(PositionType::InDirectory(path.clone())); e }));
+            let separator = format!(" {} ", textfilter::RETURN_ESCAPE_SEQUENCE);
+            let content = group_scenes(&cues, self.max_gap).iter()
+                .map(|scene| scene.join(" ")).collect::<Vec<_>>().join(&separator);
+            if content.is_empty() {
+                continue;
+            }
+            let title = path.file_stem().map(|s| s.to_string_lossy().into_owned());
+            let mut entity = Entity::with_path(content, path);
+            entity.metadata = Metadata { title, ..Metadata::default() };
+            return Some(Ok(entity));
+        }
+    }
+}
@@ -0,0 +1,196 @@
+//! Tatoeba sentences
+//!
+//! Tatoeba is a collection of CC-BY licensed example sentences and their translations. The export
+//! consists of `sentences.csv` with the tab-separated columns sentence ID, ISO 639-3 language code
+//! and text, and `links.csv` with pairs of sentence IDs which are translations of each other. Both
+//! files may be compressed with gzip or bzip2.
+//!
+//! This module either emits the sentences of one language, each sentence on its own context line,
+//! or aligned pairs of translations.
+
+use isolang::Language;
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Lines, Read};
+use std::path::{Path, PathBuf};
+
+use common;
+use input_source::{AlignedPair, Entity, PositionType, Result, TransformationError};
+use textfilter;
+
+// maximum size of the text of an entity; more sentences are split into several entities
+static MAX_BUFFER_SIZE: usize = 1048576; // 1M
+
+type LineReader = Lines<BufReader<Box<Read>>>;
+
+/// A sentence from `sentences.csv`.
+#[derive(Debug, PartialEq)]
+pub struct Sentence {
+    pub id: u64,
+    /// ISO 639-3 code, `\N` if unknown
+    pub language: String,
+    pub text: String,
+}
+
+/// Parse a line of `sentences.csv`.
+///
+/// Returns `None` for malformed lines.
+pub fn parse_sentence(line: &str) -> Option<Sentence> {
+    let mut columns = line.splitn(3, '\t');
+    let id = get!(columns.next().and_then(|id| id.trim().parse().ok()));
+    let language = get!(columns.next()).to_string();
+    let text = get!(columns.next()).trim().to_string();
+    Some(Sentence { id, language, text })
+}
+
+/// Parse a line of `links.csv` into the IDs of a sentence and its translation.
+pub fn parse_link(line: &str) -> Option<(u64, u64)> {
+    let mut columns = line.split('\t').map(|id| id.trim().parse::<u64>());
+    match (columns.next(), columns.next()) {
+        (Some(Ok(sentence)), Some(Ok(translation))) => Some((sentence, translation)),
+        _ => None,
+    }
+}
+
+// Find a file of the export: either the given file or, within a directory, the file with the
+// given name, optionally compressed.
+fn find_export_file(path: &Path, name: &str) -> Result<PathBuf> {
+    if !path.is_dir() {
+        return Ok(path.to_path_buf());
+    }
+    for candidate in &[name.to_string(), format!("{}.bz2", name), format!("{}.gz", name)] {
+        let file = path.join(candidate);
+        if file.exists() {
+            return Ok(file);
+        }
+    }
+    Err(TransformationError::ErrorneousStructure(format!("no {} found", name),
+            PositionType::InDirectory(path.to_path_buf())))
+}
+
+fn open_lines(path: &Path) -> Result<LineReader> {
+    Ok(BufReader::new(common::open_file(path)?).lines())
+}
+
+// read all sentences of the given language into a map from ID to text
+fn read_sentences(path: &Path, code: &str) -> Result<HashMap<u64, String>> {
+    let mut sentences = HashMap::new();
+    let mut line_number = 0;
+    for line in open_lines(path)? {
+        line_number += 1;
+        let line = line.map_err(|e| TransformationError::IoError(e,
+                PositionType::InFile(path.to_path_buf(), line_number, 0)))?;
+        if let Some(sentence) = parse_sentence(&line) {
+            if sentence.language == code && !sentence.text.is_empty() {
+                sentences.insert(sentence.id, sentence.text);
+            }
+        }
+    }
+    Ok(sentences)
+}
+
+/// Iterator over the sentences of one language
+///
+/// The sentences are grouped into entities of about 1 MB, each sentence forming its own context
+/// line.
+pub struct TatoebaSentences {
+    lines: LineReader,
+    path: PathBuf,
+    /// ISO 639-3 code of the language to extract
+    code: String,
+    line_number: u64,
+    finished: bool,
+}
+
+impl TatoebaSentences {
+    /// Read the sentences of the given language from `sentences.csv`; `path` is either the file
+    /// itself or the directory containing it.
+    pub fn new(path: &Path, lang: Language) -> Result<TatoebaSentences> {
+        let path = find_export_file(path, "sentences.csv")?;
+        Ok(TatoebaSentences { lines: open_lines(&path)?, path,
+            code: lang.to_639_3().to_string(), line_number: 0, finished: false })
+    }
+}
+
+impl Iterator for TatoebaSentences {
+    type Item = Result<Entity>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+        let first_line = self.line_number + 1;
+        let mut text = String::new();
+        while text.len() < MAX_BUFFER_SIZE {
+            let line = match self.lines.next() {
+                Some(line) => line,
+                None => {
+                    self.finished = true;
+                    break;
+                },
+            };
+            self.line_number += 1;
+            let line = trysome!(line.map_err(|e| TransformationError::IoError(e,
+                    PositionType::InFile(self.path.clone(), self.line_number, 0))));
+            if let Some(sentence) = parse_sentence(&line) {
+                if sentence.language == self.code && !sentence.text.is_empty() {
+                    text.push_str(&sentence.text);
+                    text.push_str(&format!(" {} ", textfilter::RETURN_ESCAPE_SEQUENCE));
+                }
+            }
+        }
+        match text.is_empty() {
+            true => None,
+            false => Some(Ok(Entity::with_exact_pos(text, self.path.clone(), first_line, 1))),
+        }
+    }
+}
+
+/// Iterator over pairs of translations
+///
+/// The sentences of both languages are held in memory, `links.csv` is streamed.
+pub struct TatoebaPairs {
+    source: HashMap<u64, String>,
+    target: HashMap<u64, String>,
+    links: LineReader,
+    links_path: PathBuf,
+    line_number: u64,
+}
+
+impl TatoebaPairs {
+    /// Read pairs from the export in the given directory (or the directory of the given
+    /// `sentences.csv`).
+    pub fn new(path: &Path, source: Language, target: Language) -> Result<TatoebaPairs> {
+        let sentences_path = find_export_file(path, "sentences.csv")?;
+        let links_path = match path.is_dir() {
+            true => find_export_file(path, "links.csv")?,
+            false => find_export_file(path.parent().unwrap_or(Path::new(".")), "links.csv")?,
+        };
+        Ok(TatoebaPairs {
+            source: read_sentences(&sentences_path, source.to_639_3())?,
+            target: read_sentences(&sentences_path, target.to_639_3())?,
+            links: open_lines(&links_path)?, links_path, line_number: 0 })
+    }
+}
+
+impl Iterator for TatoebaPairs {
+    type Item = Result<AlignedPair>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let line = get!(self.links.next());
+            self.line_number += 1;
+            let position = PositionType::InFile(self.links_path.clone(), self.line_number, 0);
+            let line = trysome!(line.map_err(|e| TransformationError::IoError(e,
+                    position.clone())));
+            let (source_id, target_id) = match parse_link(&line) {
+                Some(link) => link,
+                None => continue,
+            };
+            if let (Some(source), Some(target)) = (self.source.get(&source_id),
+                    self.target.get(&target_id)) {
+                return Some(Ok(AlignedPair { source: source.clone(), target: target.clone(),
+                    position }));
+            }
+        }
+    }
+}
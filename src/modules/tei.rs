@@ -0,0 +1,214 @@
+//! TEI P5 documents
+//!
+//! Many digital humanities corpora, e.g. the Deutsches Textarchiv, are published in the TEI P5
+//! XML format. Only the text within `<text><body>` is extracted; the `<teiHeader>` (apart from
+//! the title), notes (`<note>`) and forme work like running heads and page numbers (`<fw>`) are
+//! skipped. Of editorial choices (`<choice>`), the corrected, regularised and expanded readings
+//! are kept.
+//!
+//! Paragraphs (`<p>`), verse lines (`<l>`), line groups (`<lg>`), divisions (`<div>`) and
+//! headings (`<head>`) end a context line. Line breaks within hyphenated words (`<lb
+//! break="no"/>`) are joined. Passages marked with an `xml:lang` of another language are skipped.
+//! One entity is emitted per document.
+
+use isolang::Language;
+use std::collections::VecDeque;
+use std::io::{BufReader, Read};
+use std::path::{Path, PathBuf};
+use xml::attribute::OwnedAttribute;
+use xml::reader::{EventReader, XmlEvent};
+
+use common;
+use input_source::{Entity, Metadata, PositionType, Result};
+use super::tmx::language_matches;
+use textfilter;
+
+/// Elements whose content is never extracted.
+static SKIPPED_ELEMENTS: [&str; 3] = ["teiHeader", "note", "fw"];
+
+/// Readings within `<choice>` which are replaced by their alternative.
+static SKIPPED_READINGS: [&str; 3] = ["sic", "orig", "abbr"];
+
+/// Elements ending a context line.
+static BOUNDARY_ELEMENTS: [&str; 5] = ["p", "l", "lg", "div", "head"];
+
+// state of an open element
+struct Frame {
+    name: String,
+    /// content is within <body>
+    in_body: bool,
+    /// content is skipped
+    skipped: bool,
+    /// content is in the configured language (or of unknown language)
+    language_matches: bool,
+}
+
+fn get_xml_lang(attributes: &[OwnedAttribute]) -> Option<&str> {
+    attributes.iter().find(|a| a.name.local_name == "lang"
+            && a.name.prefix.as_ref().map(|p| p == "xml").unwrap_or(false))
+        .map(|a| a.value.as_str())
+}
+
+// end the current context line, unless nothing has been written since the last one
+fn push_boundary(output: &mut String) {
+    let trimmed = output.trim_end().len();
+    output.truncate(trimmed);
+    if !output.is_empty() && !output.ends_with(textfilter::RETURN_ESCAPE_SEQUENCE) {
+        output.push(' ');
+        output.push(textfilter::RETURN_ESCAPE_SEQUENCE);
+    }
+    if !output.is_empty() {
+        output.push(' ');
+    }
+}
+
+/// A TEI document, reduced to its text.
+#[derive(Debug, Default)]
+pub struct TeiDocument {
+    /// first `<title>` of the header
+    pub title: Option<String>,
+    /// text of the body, context lines separated by the `RETURN_ESCAPE_SEQUENCE`
+    pub text: String,
+}
+
+/// Extract the text of the body of a TEI document, skipping passages in other languages.
+pub fn parse_tei<R: Read>(input: R, language: &Language) -> Result<TeiDocument> {
+    let mut document = TeiDocument::default();
+    let mut stack: Vec<Frame> = Vec::new();
+    let mut in_title = false;
+    let mut title = String::new();
+    // a line break within a word was seen, the whitespace up to the next text is skipped
+    let mut joined = false;
+    for event in EventReader::new(input) {
+        match event? {
+            XmlEvent::StartElement { name, attributes, .. } => {
+                let name = name.local_name;
+                let (in_body, skipped, parent_language, parent) = match stack.last() {
+                    Some(frame) => (frame.in_body, frame.skipped, frame.language_matches,
+                            frame.name.as_str()),
+                    None => (false, false, true, ""),
+                };
+                let skipped = skipped || SKIPPED_ELEMENTS.contains(&name.as_str())
+                    || (parent == "choice" && SKIPPED_READINGS.contains(&name.as_str()));
+                let language_matches = match get_xml_lang(&attributes) {
+                    Some(tag) => language_matches(tag, language),
+                    None => parent_language,
+                };
+                if name == "title" && title.is_empty() && !in_body {
+                    in_title = true;
+                }
+                if in_body && !skipped && language_matches {
+                    if BOUNDARY_ELEMENTS.contains(&name.as_str()) {
+                        push_boundary(&mut document.text);
+                        joined = false;
+                    } else if name == "lb" || name == "pb" || name == "cb" {
+                        joined = attributes.iter().any(|a| a.name.local_name == "break"
+                                && a.value == "no");
+                        if !joined && !document.text.ends_with(' ') {
+                            document.text.push(' ');
+                        }
+                    }
+                }
+                stack.push(Frame { in_body: in_body || name == "body", skipped,
+                    language_matches, name });
+            },
+            XmlEvent::EndElement { .. } => {
+                if let Some(frame) = stack.pop() {
+                    if frame.name == "title" {
+                        in_title = false;
+                    }
+                    if frame.in_body && !frame.skipped && frame.language_matches
+                            && BOUNDARY_ELEMENTS.contains(&frame.name.as_str()) {
+                        push_boundary(&mut document.text);
+                    }
+                }
+            },
+            XmlEvent::Characters(text) | XmlEvent::CData(text) => {
+                if in_title {
+                    title.push_str(&text);
+                }
+                let emit = stack.last().map(|f| f.in_body && !f.skipped && f.language_matches)
+                    .unwrap_or(false);
+                if emit {
+                    let text = match joined {
+                        true => text.trim_start(),
+                        false => text.as_str(),
+                    };
+                    let words = text.split_whitespace().collect::<Vec<_>>().join(" ");
+                    joined = joined && words.is_empty();
+                    if text.starts_with(char::is_whitespace) && !document.text.is_empty()
+                            && !document.text.ends_with(' ') {
+                        document.text.push(' ');
+                    }
+                    document.text.push_str(&words);
+                    if text.ends_with(char::is_whitespace) && !words.is_empty() {
+                        document.text.push(' ');
+                    }
+                }
+            },
+            XmlEvent::Whitespace(_) => if stack.last().map(|f| f.in_body).unwrap_or(false)
+                    && !joined && !document.text.is_empty() && !document.text.ends_with(' ') {
+                document.text.push(' ');
+            },
+            _ => (),
+        }
+    }
+    // remove trailing context break
+    let trimmed = document.text.trim_end_matches(|c: char| c.is_whitespace()
+            || c == textfilter::RETURN_ESCAPE_SEQUENCE).len();
+    document.text.truncate(trimmed);
+    let title = title.split_whitespace().collect::<Vec<_>>().join(" ");
+    if !title.is_empty() {
+        document.title = Some(title);
+    }
+    Ok(document)
+}
+
+/// Iterator over TEI documents below a directory
+///
+/// All files ending on `.xml` or `.tei` are read, one entity is emitted per document.
+pub struct TeiFiles {
+    files: VecDeque<PathBuf>,
+    language: Language,
+}
+
+impl TeiFiles {
+    pub fn new(path: &Path, language: Language) -> Result<TeiFiles> {
+        let files = match path.is_dir() {
+            true => {
+                let mut files = common::find_files(path, "xml")?;
+                files.extend(common::find_files(path, "tei")?);
+                files.sort();
+                files
+            },
+            false => vec![path.to_path_buf()],
+        };
+        Ok(TeiFiles { files: files.into_iter().collect(), language })
+    }
+}
+
+impl Iterator for TeiFiles {
+    type Item = Result<Entity>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let path = get!(self.files.pop_front());
+            let parsed = common::open_file(&path)
+                .and_then(|f| Ok(common::decoding_reader(BufReader::new(f))?))
+                .and_then(|r| parse_tei(BufReader::new(r), &self.language));
+            let document = match parsed {
+                Ok(document) => document,
+                Err(mut e) => {
+                    e.inject_position(PositionType::InDirectory(path));
+                    return Some(Err(e));
+                },
+            };
+            if document.text.is_empty() {
+                continue;
+            }
+            let mut entity = Entity::with_path(document.text, path);
+            entity.metadata = Metadata { title: document.title, ..Metadata::default() };
+            return Some(Ok(entity));
+        }
+    }
+}
@@ -0,0 +1,391 @@
+//! Translation memories (TMX) and term bases (TBX)
+//!
+//! Many parallel corpora are distributed as TMX 1.4 files, e.g. the DGT translation memories,
+//! the ECB and EMEA corpora or the whole OPUS collection. A TMX file consists of translation
+//! units (`<tu>`), each containing one variant (`<tuv>`) per language with the actual text in a
+//! `<seg>` element.
+//!
+//! This module reads TMX files from plain `*.tmx` files, gzip-compressed `*.tmx.gz` files or zip
+//! archives containing `*.tmx` files, in any of the encodings supported by
+//! [`common::decoding_reader`](../../common/fn.decoding_reader.html). Language tags are matched on
+//! their primary subtag, so that `xml:lang="de-DE"` and `lang="DE"` both match German. Either the
+//! text of one language is extracted ([`TmxFiles`](struct.TmxFiles.html)) or aligned pairs of two
+//! languages ([`TmxPairs`](struct.TmxPairs.html)).
+//!
+//! TBX term bases can be read as a plain term list using
+//! [`read_tbx_terms`](fn.read_tbx_terms.html), e.g. as headword list for the coverage report.
+
+use isolang::Language;
+use std::collections::{HashSet, VecDeque};
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::mem;
+use std::path::{Path, PathBuf};
+use xml::attribute::OwnedAttribute;
+use xml::reader::{EventReader, XmlEvent};
+use zip::read::ZipArchive;
+
+use common;
+use input_source::{AlignedPair, Entity, PositionType, Result, TransformationError};
+use textfilter;
+
+// maximum size of the text of an entity; larger files are split into several entities
+static MAX_BUFFER_SIZE: usize = 1048576; // 1M
+
+/// The text of a translation unit variant in a given language.
+#[derive(Clone, Debug)]
+pub struct Segment {
+    /// language tag, as found in the document
+    pub language: String,
+    pub text: String,
+}
+
+/// A translation unit, one segment per language.
+pub type TranslationUnit = Vec<Segment>;
+
+/// Test whether a language tag (e.g. `de-DE`, `DE` or `deu`) denotes the given language.
+///
+/// Only the primary subtag is compared, case-insensitively, against the ISO 639-1 or ISO 639-3
+/// code of the language.
+pub fn language_matches(tag: &str, language: &Language) -> bool {
+    let primary = tag.split(|c| c == '-' || c == '_').next().unwrap_or("").to_lowercase();
+    match primary.len() {
+        2 => language.to_639_1().map(|code| code == primary).unwrap_or(false),
+        3 => language.to_639_3() == primary,
+        _ => false,
+    }
+}
+
+fn get_language(attributes: &[OwnedAttribute]) -> Option<String> {
+    attributes.iter().find(|a| a.name.local_name == "lang").map(|a| a.value.clone())
+}
+
+// Read translation units one by one from a TMX document.
+struct TuReader<R: Read> {
+    events: EventReader<R>,
+}
+
+impl<R: Read> TuReader<R> {
+    fn new(input: R) -> TuReader<R> {
+        TuReader { events: EventReader::new(input) }
+    }
+
+    // return the next translation unit or None, if the end of the document has been reached
+    fn next_unit(&mut self) -> Result<Option<TranslationUnit>> {
+        let mut unit = TranslationUnit::new();
+        let mut language = None;
+        let mut text = String::new();
+        let mut in_segment = false;
+        // depth of inline elements with native code (e.g. <bpt/>), their content is skipped
+        let mut native_code_depth = 0;
+        loop {
+            match self.events.next()? {
+                XmlEvent::StartElement { name, attributes, .. } => match name.local_name.as_str() {
+                    "tu" => unit.clear(),
+                    "tuv" => language = get_language(&attributes),
+                    "seg" => {
+                        in_segment = true;
+                        text.clear();
+                    },
+                    "bpt" | "ept" | "it" | "ph" | "ut" if in_segment => native_code_depth += 1,
+                    _ => (),
+                },
+                XmlEvent::EndElement { name } => match name.local_name.as_str() {
+                    "tu" => return Ok(Some(unit)),
+                    "tuv" => language = None,
+                    "seg" => {
+                        in_segment = false;
+                        if let Some(ref language) = language {
+                            unit.push(Segment { language: language.clone(),
+                                text: mem::replace(&mut text, String::new()) });
+                        }
+                    },
+                    "bpt" | "ept" | "it" | "ph" | "ut" if in_segment => native_code_depth -= 1,
+                    _ => (),
+                },
+                XmlEvent::Characters(data) | XmlEvent::CData(data) | XmlEvent::Whitespace(data) =>
+                    if in_segment && native_code_depth == 0 {
+                        text.push_str(&data);
+                },
+                XmlEvent::EndDocument => return Ok(None),
+                _ => (),
+            }
+        }
+    }
+}
+
+/// Read all translation units from a TMX document.
+///
+/// The input has to be UTF-8, see [`common::decoding_reader`](../../common/fn.decoding_reader.html)
+/// for other encodings.
+pub fn read_translation_units<R: Read>(input: R) -> Result<Vec<TranslationUnit>> {
+    let mut reader = TuReader::new(input);
+    let mut units = Vec::new();
+    while let Some(unit) = reader.next_unit()? {
+        units.push(unit);
+    }
+    Ok(units)
+}
+
+// Open a plain or gzip-compressed file and decode it to UTF-8.
+fn open_file(path: &Path) -> Result<BufReader<Box<Read>>> {
+    Ok(BufReader::new(common::decoding_reader(common::open_file(path)?)?))
+}
+
+// Collect all TMX files (plain, gzipped and zip archives) from a directory. If a file is given,
+// it is returned.
+fn collect_tmx_files(path: &Path) -> Result<Vec<PathBuf>> {
+    if !path.is_dir() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+    let mut files = common::find_files(path, "tmx")?;
+    files.extend(common::find_files(path, "zip")?);
+    files.extend(common::find_files(path, "gz")?.into_iter().filter(|p|
+            p.to_string_lossy().to_lowercase().ends_with(".tmx.gz")));
+    files.sort();
+    Ok(files)
+}
+
+// List the `*.tmx` entries of a zip archive.
+fn tmx_entries(path: &Path) -> Result<Vec<usize>> {
+    let mut archive = ZipArchive::new(File::open(path)?)?;
+    let mut entries = Vec::new();
+    for index in 0..archive.len() {
+        if archive.by_index(index)?.name().to_lowercase().ends_with(".tmx") {
+            entries.push(index);
+        }
+    }
+    Ok(entries)
+}
+
+// Open an entry of a zip archive and decode it to UTF-8; the entry is decompressed on the fly.
+fn open_zip_entry(path: &Path, index: usize) -> Result<BufReader<Box<Read>>> {
+    Ok(BufReader::new(common::decoding_reader(common::ZipEntryReader::new(path, index))?))
+}
+
+// Emit translation units from a list of files; zip archives are opened and all *.tmx files
+// within are read. Files and zip entries are parsed while reading, so that only the current
+// translation unit is held in memory.
+struct UnitSource {
+    files: VecDeque<PathBuf>,
+    /// reader of the current file or zip entry, with the path of the file or zip archive
+    current: Option<(TuReader<BufReader<Box<Read>>>, PathBuf)>,
+    /// `*.tmx` entries of the current zip archive, not read yet
+    entries: VecDeque<(PathBuf, usize)>,
+}
+
+impl UnitSource {
+    fn new(files: Vec<PathBuf>) -> UnitSource {
+        UnitSource { files: files.into_iter().collect(), current: None, entries: VecDeque::new() }
+    }
+
+    fn next_unit(&mut self) -> Option<Result<(TranslationUnit, PathBuf)>> {
+        loop {
+            if let Some((mut reader, path)) = self.current.take() {
+                match reader.next_unit() {
+                    Ok(Some(unit)) => {
+                        self.current = Some((reader, path.clone()));
+                        return Some(Ok((unit, path)));
+                    },
+                    Ok(None) => (), // end of file
+                    Err(mut e) => {
+                        e.inject_position(PositionType::InDirectory(path));
+                        return Some(Err(e));
+                    },
+                }
+            }
+
+            let opened = match self.entries.pop_front() {
+                Some((path, index)) => open_zip_entry(&path, index).map(|reader|
+                        self.current = Some((TuReader::new(reader), path.clone()))).err()
+                        .map(|e| (e, path)),
+                None => {
+                    let path = get!(self.files.pop_front());
+                    let is_zip = path.extension().map(|e| e == "zip").unwrap_or(false);
+                    let result = match is_zip {
+                        true => tmx_entries(&path).map(|entries| self.entries.extend(
+                                entries.into_iter().map(|index| (path.clone(), index)))),
+                        false => open_file(&path).map(|reader|
+                                self.current = Some((TuReader::new(reader), path.clone()))),
+                    };
+                    result.err().map(|e| (e, path))
+                },
+            };
+            if let Some((mut e, path)) = opened {
+                e.inject_position(PositionType::InDirectory(path));
+                return Some(Err(e));
+            }
+        }
+    }
+}
+
+/// Iterator over the text of one language from TMX files
+///
+/// The segments of the configured language are emitted line by line, at most about 1 MB per
+/// entity (see [`set_max_entity_size`](#method.set_max_entity_size)). Translation units are never
+/// split and entities do not span multiple files.
+pub struct TmxFiles {
+    units: UnitSource,
+    language: Language,
+    /// size of the text after which an entity is emitted
+    max_entity_size: usize,
+    /// unit read from the next file, while the entity for the previous file was emitted
+    lookahead: Option<(TranslationUnit, PathBuf)>,
+    /// error to be returned after the current chunk
+    pending_error: Option<TransformationError>,
+}
+
+impl TmxFiles {
+    /// Read from a TMX file or from all TMX files below the given directory.
+    pub fn new(path: &Path, language: Language) -> Result<TmxFiles> {
+        Ok(TmxFiles::from_paths(collect_tmx_files(path)?, language))
+    }
+
+    /// Read from the given `*.tmx`, `*.tmx.gz` or `*.zip` files.
+    pub fn from_paths(paths: Vec<PathBuf>, language: Language) -> TmxFiles {
+        TmxFiles { units: UnitSource::new(paths), language, max_entity_size: MAX_BUFFER_SIZE,
+            lookahead: None, pending_error: None }
+    }
+
+    /// Set the size (in bytes) of the text after which an entity is emitted; the translation unit
+    /// reaching it is still included.
+    pub fn set_max_entity_size(&mut self, size: usize) {
+        self.max_entity_size = size;
+    }
+}
+
+impl Iterator for TmxFiles {
+    type Item = Result<Entity>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(e) = self.pending_error.take() {
+            return Some(Err(e));
+        }
+        let mut text = String::new();
+        let mut chunk_path: Option<PathBuf> = None;
+        loop {
+            let (unit, path) = match self.lookahead.take() {
+                Some(unit) => unit,
+                None => match self.units.next_unit() {
+                    Some(Ok(unit)) => unit,
+                    Some(Err(e)) => match text.is_empty() {
+                        true => return Some(Err(e)),
+                        false => {
+                            self.pending_error = Some(e);
+                            break;
+                        },
+                    },
+                    None => break,
+                },
+            };
+            // do not mix text from different files in one entity
+            if !text.is_empty() && chunk_path.as_ref() != Some(&path) {
+                self.lookahead = Some((unit, path));
+                break;
+            }
+            chunk_path = Some(path);
+            let language = &self.language;
+            if let Some(segment) = unit.into_iter().find(|s|
+                    language_matches(&s.language, language)) {
+                text.push_str(&segment.text);
+                text.push_str(&format!(" {} ", textfilter::RETURN_ESCAPE_SEQUENCE));
+            }
+            if text.len() >= self.max_entity_size {
+                break;
+            }
+        }
+        match (text.is_empty(), chunk_path) {
+            (false, Some(path)) => Some(Ok(Entity::with_path(text, path))),
+            _ => None,
+        }
+    }
+}
+
+/// Iterator over aligned pairs from TMX files
+///
+/// Translation units which lack one of the two languages are skipped.
+pub struct TmxPairs {
+    units: UnitSource,
+    source: Language,
+    target: Language,
+}
+
+impl TmxPairs {
+    /// Read from a TMX file or from all TMX files below the given directory.
+    pub fn new(path: &Path, source: Language, target: Language) -> Result<TmxPairs> {
+        Ok(TmxPairs { units: UnitSource::new(collect_tmx_files(path)?), source, target })
+    }
+}
+
+impl Iterator for TmxPairs {
+    type Item = Result<AlignedPair>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (unit, path) = trysome!(get!(self.units.next_unit()));
+            let mut source = None;
+            let mut target = None;
+            for segment in unit {
+                if source.is_none() && language_matches(&segment.language, &self.source) {
+                    source = Some(segment.text);
+                } else if target.is_none() &&
+                        language_matches(&segment.language, &self.target) {
+                    target = Some(segment.text);
+                }
+            }
+            if let (Some(source), Some(target)) = (source, target) {
+                return Some(Ok(AlignedPair { source, target,
+                    position: PositionType::InDirectory(path) }));
+            }
+        }
+    }
+}
+
+/// Read all terms of the given language, or of all languages, from a TBX term base.
+///
+/// The terms are returned in document order, without duplicates. Both TBX 2 (`<langSet>`) and
+/// TBX 3 (`<langSec>`) are supported; the file may be gzip-compressed.
+pub fn read_tbx_terms(path: &Path, language: Option<&Language>) -> Result<Vec<String>> {
+    let reader = open_file(path)?;
+    parse_tbx_terms(reader, language).map_err(|mut e| {
+        e.inject_position(PositionType::InDirectory(path.to_path_buf())); e })
+}
+
+/// Parse all terms of the given language from a TBX document, see
+/// [`read_tbx_terms`](fn.read_tbx_terms.html).
+pub fn parse_tbx_terms<R: Read>(input: R, language: Option<&Language>)
+        -> Result<Vec<String>> {
+    let mut terms = Vec::new();
+    let mut seen = HashSet::new();
+    let mut in_language = false;
+    let mut term: Option<String> = None;
+    for event in EventReader::new(input) {
+        match event? {
+            XmlEvent::StartElement { name, attributes, .. } => match name.local_name.as_str() {
+                "langSet" | "langSec" => in_language = match language {
+                    Some(language) => get_language(&attributes)
+                        .map(|l| language_matches(&l, language)).unwrap_or(false),
+                    None => true,
+                },
+                "term" if in_language => term = Some(String::new()),
+                _ => (),
+            },
+            XmlEvent::EndElement { name } => match name.local_name.as_str() {
+                "langSet" | "langSec" => in_language = false,
+                "term" => if let Some(found) = term.take() {
+                    let found = found.trim().to_string();
+                    if !found.is_empty() && seen.insert(found.clone()) {
+                        terms.push(found);
+                    }
+                },
+                _ => (),
+            },
+            XmlEvent::Characters(data) | XmlEvent::CData(data) | XmlEvent::Whitespace(data) =>
+                if let Some(ref mut term) = term {
+                    term.push_str(&data);
+            },
+            _ => (),
+        }
+    }
+    Ok(terms)
+}
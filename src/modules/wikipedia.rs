@@ -30,76 +30,154 @@ impl<'a> input_source::Unformatter for Wikipedia {
         let preproc = MediawikiPreprocessor::new(&input.content);
         Ok(Entity {
             content: preproc.preprocess()?,
-            position: input.position.clone() })
+            position: input.position.clone(),
+            metadata: input.metadata.clone() })
     }
 }
 
-// minimum buffer allocated for article
+/// A page from a MediaWiki XML dump.
+pub struct Page {
+    pub title: String,
+    /// namespace number, 0 for articles
+    pub namespace: i64,
+    /// MediaWiki markup of the latest revision
+    pub text: String,
+    pub position: PositionType,
+}
+
+impl Page {
+    /// Whether the page only redirects to another page.
+    pub fn is_redirect(&self) -> bool {
+        self.text.starts_with("#REDIRECT")
+    }
+}
 
+// the page element currently being read
+enum PageField {
+    Title,
+    Namespace,
+    Text,
+    /// namespace declaration in the site information
+    SiteNamespace,
+}
 
+/// Iterator over the articles of a MediaWiki XML dump
+///
+/// Redirects are skipped. The title of the page is stored in the metadata of the emitted entity.
+/// Use [`next_page`](#method.next_page) to access all pages with their namespace.
 pub struct ArticleParser<B: Read> {
     data_path: Option<PathBuf>,
-    event_reader: EventReader<B>
+    event_reader: EventReader<B>,
+    /// set after the end of the document or an error, the XML parser would repeat the last event
+    finished: bool,
+    /// namespaces from the site information, by number and (localised) name
+    namespaces: Vec<(i64, String)>,
 }
 
 impl<B: Read> ArticleParser<B> {
     pub fn new(input_reader: B) -> ArticleParser<B> {
         let er = EventReader::new(input_reader);
-        ArticleParser { event_reader: er, data_path: None }
+        ArticleParser { event_reader: er, data_path: None, finished: false,
+            namespaces: Vec::new() }
     }
-}
 
-impl<B: Read> Iterator for ArticleParser<B> {
-    type Item = Result<Entity>;
+    /// Namespaces declared in the site information of the dump, by number and localised name.
+    ///
+    /// The site information precedes the pages, so the namespaces are known once the first page
+    /// has been read.
+    pub fn namespaces(&self) -> &[(i64, String)] {
+        &self.namespaces
+    }
 
-    fn next(&mut self) -> Option<Result<Entity>> {
-        let mut is_text_element = false;
+    /// Return the next page of the dump, regardless of its namespace and including redirects.
+    pub fn next_page(&mut self) -> Option<Result<Page>> {
+        if self.finished {
+            return None;
+        }
+        let mut field = None;
+        let mut title = String::new();
+        let mut namespace = String::new();
         let mut text = String::new();
-        while let Ok(element) = self.event_reader.next() {
+        loop {
+            let element = match self.event_reader.next() {
+                Ok(e) => e,
+                Err(e) => {
+                    self.finished = true;
+                    return Some(Err(From::from(e)));
+                }
+            };
             match element {
-                XmlEvent::StartElement { name, .. } => {
-                    if name.local_name == "text" {
-                        is_text_element = true;
-                    }
+                XmlEvent::StartElement { name, attributes, .. } => match name.local_name.as_str() {
+                    "namespace" => if let Some(key) = attributes.iter()
+                            .find(|a| a.name.local_name == "key")
+                            .and_then(|a| a.value.parse().ok()) {
+                        self.namespaces.push((key, String::new()));
+                        field = Some(PageField::SiteNamespace);
+                    },
+                    "page" => {
+                        title.clear();
+                        namespace.clear();
+                        text.clear();
+                    },
+                    "title" => field = Some(PageField::Title),
+                    "ns" => field = Some(PageField::Namespace),
+                    "text" => field = Some(PageField::Text),
+                    _ => (),
                 },
-                XmlEvent::EndElement { name } => {
-                    if name.local_name == "text" && is_text_element {
-                        // check whether article text is only a redirect
-                        match text.starts_with("#REDIRECT") {
-                            true => {
-                                text.clear();
-                                continue;
-                            }, // ignore redirects
-                            false => break,
-                        } 
-                    }
+                XmlEvent::EndElement { name } => match name.local_name.as_str() {
+                    "page" => {
+                        let pos = self.event_reader.position();
+                        return Some(Ok(Page { title, text,
+                            namespace: namespace.trim().parse().unwrap_or(0),
+                            position: PositionType::InFile(self.data_path.clone()
+                                .unwrap_or(PathBuf::new()), pos.row + 1, pos.column + 1) }));
+                    },
+                    "title" | "ns" | "text" | "namespace" => field = None,
+                    _ => (),
                 },
-                XmlEvent::Characters(content) => {
-                    if is_text_element {
-                        text.push_str(&content);
-                    }
+                XmlEvent::Characters(content) | XmlEvent::Whitespace(content) =>
+                    match field {
+                        Some(PageField::Title) => title.push_str(&content),
+                        Some(PageField::Namespace) => namespace.push_str(&content),
+                        Some(PageField::Text) => text.push_str(&content),
+                        Some(PageField::SiteNamespace) => if let Some(namespace) =
+                                self.namespaces.last_mut() {
+                            namespace.1.push_str(&content);
+                        },
+                        None => (),
                 },
-                XmlEvent::Whitespace(space) => {
-                    if is_text_element {
-                        text.push_str(&space);
-                    }
-            },
-            _ => ()
+                XmlEvent::EndDocument => {
+                    self.finished = true;
+                    return None;
+                },
+                _ => ()
             };
-        };
-        if !text.is_empty() {
-            let pos = self.event_reader.position();
-            Some(Ok(Entity::with_exact_pos(text, self.data_path.clone()
-                .unwrap_or(PathBuf::new()), pos.row + 1, pos.column + 1)))
-        } else {
-            None
+        }
+    }
+}
+
+impl<B: Read> Iterator for ArticleParser<B> {
+    type Item = Result<Entity>;
+
+    fn next(&mut self) -> Option<Result<Entity>> {
+        loop {
+            let page = trysome!(get!(self.next_page()));
+            // ignore redirects and empty pages
+            if page.text.is_empty() || page.is_redirect() {
+                continue;
+            }
+            let mut entity = Entity::new(page.text, page.position);
+            entity.metadata.title = Some(page.title);
+            return Some(Ok(entity));
         }
     }
 }
 
 pub fn parser_from_file(filename: &Path) -> Result<ArticleParser<BzDecoder<File>>> {
     let compressed = File::open(filename.to_str().unwrap())?;
-    Ok(ArticleParser::new(BzDecoder::new(compressed)))
+    let mut parser = ArticleParser::new(BzDecoder::new(compressed));
+    parser.data_path = Some(filename.to_path_buf());
+    Ok(parser)
 }
 
 
@@ -0,0 +1,403 @@
+//! Wikisource dumps
+//!
+//! Wikisource hosts free literary texts in many languages. Its dumps use the same MediaWiki XML
+//! format as Wikipedia, but the texts of scanned works are not stored on the pages of the works
+//! themselves. Each scanned page is proofread on its own page in the Page namespace, e.g.
+//! `Page:Faust.djvu/12`, and the work (or chapter) page transcludes a range of pages using
+//! `<pages index="Faust.djvu" from=12 to=20 />`.
+//!
+//! This module resolves these transclusions within the dump, so that one entity is emitted per
+//! work or chapter page of the main namespace. The running headers and footers of the scanned
+//! pages (found in `<noinclude>` sections) as well as header templates of the work pages are
+//! removed. Section-wise transclusion (`fromsection`, `tosection`) is not supported, whole pages
+//! are included instead.
+//!
+//! Resolving transclusions requires two passes over the dump: first, the texts of the scanned pages
+//! are written to a temporary file, of which only the offsets are kept in memory, then the works
+//! are emitted, reading the transcluded pages back from the file.
+
+use bzip2::read::BzDecoder;
+use pandoc;
+use std::cmp;
+use std::collections::HashMap;
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use input_source::{Entity, PositionType, Result, TransformationError, Unformatter};
+use super::wikipedia::{self, ArticleParser, MediawikiPreprocessor, Page};
+
+/// Templates at the beginning of a work, giving title, author and other metadata.
+pub static DEFAULT_HEADER_TEMPLATES: [&str; 8] = ["header", "header2", "textdaten",
+    "textquelle", "en-tête", "titre", "intestazione", "encabezado"];
+
+/// Localised names of the Page namespace, which holds the scanned pages; its number differs
+/// between the Wikisource editions.
+pub static PAGE_NAMESPACES: [&str; 16] = ["Page", "Seite", "Pagina", "Página", "Pàgina",
+    "Strona", "Stránka", "Side", "Sida", "Sivu", "Oldal", "Страница", "Сторінка", "Σελίδα",
+    "Lehekülg", "Puslapis"];
+
+/// Pandoc configuration for Wikisource works
+///
+/// Works are MediaWiki documents and are preprocessed like Wikipedia articles.
+pub struct Wikisource;
+
+impl Unformatter for Wikisource {
+    fn is_preprocessing_required(&self) -> bool {
+        true
+    }
+
+    fn get_input_format(&self) -> pandoc::InputFormat {
+        pandoc::InputFormat::MediaWiki
+    }
+
+    fn preprocess(&self, input: &Entity) -> Result<Entity> {
+        let preproc = MediawikiPreprocessor::new(&input.content);
+        Ok(Entity {
+            content: preproc.preprocess()?,
+            position: input.position.clone(),
+            metadata: input.metadata.clone() })
+    }
+}
+
+/// A `<pages/>` transclusion tag.
+#[derive(Debug, PartialEq)]
+pub struct Transclusion {
+    /// name of the index, usually the name of the scanned file
+    pub index: String,
+    /// page numbers to include, in this order
+    pub pages: Vec<u32>,
+}
+
+// parse the attributes of a tag, values may be quoted or not
+fn parse_attributes(tag: &str) -> HashMap<String, String> {
+    let mut attributes = HashMap::new();
+    let mut rest = tag;
+    while let Some(equals) = rest.find('=') {
+        let name = rest[..equals].trim().rsplit(char::is_whitespace).next().unwrap_or("")
+            .to_lowercase();
+        let after = rest[equals + 1..].trim_start();
+        let (value, remainder) = match after.chars().next() {
+            Some(quote) if quote == '"' || quote == '\'' => match after[1..].find(quote) {
+                Some(end) => (&after[1..end + 1], &after[end + 2..]),
+                None => (&after[1..], ""),
+            },
+            _ => {
+                let end = after.find(|c: char| c.is_whitespace() || c == '/' || c == '>')
+                    .unwrap_or(after.len());
+                (&after[..end], &after[end..])
+            },
+        };
+        attributes.insert(name, value.to_string());
+        rest = remainder;
+    }
+    attributes
+}
+
+// parse page lists like "1-3,7,9-10", ranges end at the last page if known
+fn parse_page_list(list: &str, last_page: Option<u32>) -> Vec<u32> {
+    let mut pages = Vec::new();
+    for part in list.split(',') {
+        let mut bounds = part.splitn(2, '-').map(|n| n.trim().parse::<u32>());
+        match (bounds.next(), bounds.next()) {
+            (Some(Ok(from)), Some(Ok(to))) => pages.extend(from..cmp::min(to,
+                    last_page.unwrap_or(to)).saturating_add(1)),
+            (Some(Ok(page)), None) => pages.push(page),
+            _ => (),
+        }
+    }
+    pages
+}
+
+/// Find all `<pages/>` tags in a page and return their byte ranges and parsed content.
+///
+/// `last_page` gives the last page of an index, if known. It is used if no explicit range is given
+/// (neither `from`/`to` nor `include`) and ranges exceeding it are cut off, so that a bogus range
+/// like `to=999999999` does not exhaust the memory.
+pub fn find_transclusions<F: Fn(&str) -> Option<u32>>(text: &str, last_page: F)
+        -> Vec<(usize, usize, Transclusion)> {
+    let mut found = Vec::new();
+    let mut offset = 0;
+    while let Some(start) = text[offset..].find("<pages").map(|s| s + offset) {
+        let tag_end = match text[start..].find('>') {
+            Some(end) => start + end + 1,
+            None => break,
+        };
+        let mut end = tag_end;
+        // non-self-closing tag, skip closing tag
+        if !text[..tag_end].ends_with("/>") && text[tag_end..].starts_with("</pages>") {
+            end += "</pages>".len();
+        }
+        offset = end;
+        let attributes = parse_attributes(&text[start + "<pages".len()..tag_end - 1]);
+        let index = match attributes.get("index") {
+            Some(index) => index.clone(),
+            None => continue,
+        };
+        let last = last_page(&index);
+        let mut pages = match attributes.get("include") {
+            Some(list) => parse_page_list(list, last),
+            None => {
+                let from = attributes.get("from").and_then(|n| n.trim().parse().ok())
+                    .unwrap_or(1);
+                let to = attributes.get("to").and_then(|n| n.trim().parse().ok())
+                    .map(|to| cmp::min(to, last.unwrap_or(to)))
+                    .unwrap_or_else(|| last.unwrap_or(0));
+                (from..to.saturating_add(1)).collect()
+            },
+        };
+        if let Some(list) = attributes.get("exclude") {
+            let excluded = parse_page_list(list, last);
+            pages.retain(|p| !excluded.contains(p));
+        }
+        found.push((start, end, Transclusion { index, pages }));
+    }
+    found
+}
+
+// remove everything between the given opening and closing tag, including the tags
+fn remove_sections(text: &str, start_tag: &str, end_tag: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find(start_tag) {
+        output.push_str(&rest[..start]);
+        rest = match rest[start..].find(end_tag) {
+            Some(end) => &rest[start + end + end_tag.len()..],
+            None => "",
+        };
+    }
+    output.push_str(rest);
+    output
+}
+
+// remove all tags with the given name, e.g. <section begin="x" />, but keep the content
+fn remove_tags(text: &str, name: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+    let mut rest = text;
+    let start_tag = format!("<{}", name);
+    while let Some(start) = rest.find(&start_tag) {
+        output.push_str(&rest[..start]);
+        rest = match rest[start..].find('>') {
+            Some(end) => &rest[start + end + 1..],
+            None => "",
+        };
+    }
+    output.push_str(rest);
+    output
+}
+
+/// Remove all templates with the given names (case-insensitive) from MediaWiki markup.
+///
+/// Nested templates within the removed templates are removed as well.
+pub fn strip_templates(text: &str, names: &[String]) -> String {
+    let mut output = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        output.push_str(&rest[..start]);
+        let template = &rest[start + 2..];
+        let name_end = template.find(|c| c == '|' || c == '}').unwrap_or(template.len());
+        let name = template[..name_end].trim().to_lowercase();
+        if !names.iter().any(|n| *n == name) {
+            output.push_str("{{");
+            rest = template;
+            continue;
+        }
+        // find the matching closing braces
+        let mut depth = 1;
+        let mut position = 0;
+        while depth > 0 && position < template.len() {
+            if template[position..].starts_with("{{") {
+                depth += 1;
+                position += 2;
+            } else if template[position..].starts_with("}}") {
+                depth -= 1;
+                position += 2;
+            } else {
+                position += template[position..].chars().next().map(|c| c.len_utf8())
+                    .unwrap_or(1);
+            }
+        }
+        rest = &template[position..];
+    }
+    output.push_str(rest);
+    output
+}
+
+/// Remove the running headers and footers (`<noinclude>` sections) and section markers from the
+/// text of a scanned page.
+pub fn clean_page_text(text: &str) -> String {
+    let text = remove_sections(text, "<noinclude>", "</noinclude>");
+    remove_tags(&text, "section").trim().to_string()
+}
+
+// split a title like "Page:Faust.djvu/12" into index and page number
+fn split_page_title(title: &str) -> Option<(&str, u32)> {
+    let colon = get!(title.find(':'));
+    let slash = get!(title.rfind('/'));
+    if slash < colon {
+        return None;
+    }
+    let number = get!(title[slash + 1..].parse().ok());
+    Some((&title[colon + 1..slash], number))
+}
+
+// Whether a page is a scanned page. The number of the Page namespace is looked up in the
+// namespaces of the dump; if they are not declared, the title prefix is compared instead.
+fn is_scanned_page(page: &Page, namespaces: &[(i64, String)]) -> bool {
+    let is_page_namespace = |name: &str| PAGE_NAMESPACES.contains(&name);
+    match namespaces.iter().find(|namespace| is_page_namespace(&namespace.1)) {
+        Some(&(number, _)) => page.namespace == number,
+        None => page.namespace != 0 && page.title.find(':')
+            .map(|colon| is_page_namespace(&page.title[..colon])).unwrap_or(false),
+    }
+}
+
+fn open_dump(path: &Path) -> Result<ArticleParser<BzDecoder<File>>> {
+    wikipedia::parser_from_file(path)
+}
+
+/// numbers the temporary page files of this process
+static PAGE_FILES: AtomicUsize = AtomicUsize::new(0);
+
+/// Texts of the scanned pages, kept in a temporary file
+///
+/// Only the offset and length of each text are held in memory, by index and page number. The
+/// file is removed when the store is dropped.
+struct PageStore {
+    file: File,
+    path: PathBuf,
+    /// byte ranges of the page texts, by index and page number
+    offsets: HashMap<String, HashMap<u32, (u64, usize)>>,
+    /// end of the file
+    length: u64,
+}
+
+impl PageStore {
+    fn new() -> Result<PageStore> {
+        let path = env::temp_dir().join(format!("craft_wikisource_{}_{}.pages", process::id(),
+                PAGE_FILES.fetch_add(1, Ordering::SeqCst)));
+        let file = OpenOptions::new().read(true).write(true).create(true).truncate(true)
+            .open(&path).map_err(|e| TransformationError::IoError(e,
+                    PositionType::InDirectory(path.clone())))?;
+        Ok(PageStore { file, path, offsets: HashMap::new(), length: 0 })
+    }
+
+    fn insert(&mut self, index: &str, number: u32, text: &str) -> Result<()> {
+        (&self.file).write_all(text.as_bytes()).map_err(|e| self.error(e))?;
+        self.offsets.entry(index.to_string()).or_insert_with(HashMap::new)
+            .insert(number, (self.length, text.len()));
+        self.length += text.len() as u64;
+        Ok(())
+    }
+
+    // the highest page number of an index, if any of its pages is known
+    fn last_page(&self, index: &str) -> Option<u32> {
+        self.offsets.get(index).and_then(|pages| pages.keys().max().cloned())
+    }
+
+    fn get(&self, index: &str, number: u32) -> Result<Option<String>> {
+        let (offset, length) = match self.offsets.get(index).and_then(|p| p.get(&number)) {
+            Some(&range) => range,
+            None => return Ok(None),
+        };
+        let mut file = &self.file;
+        let mut text = vec![0; length];
+        file.seek(SeekFrom::Start(offset)).and_then(|_| file.read_exact(&mut text))
+            .map_err(|e| self.error(e))?;
+        // written from a string above
+        Ok(Some(String::from_utf8(text).unwrap()))
+    }
+
+    fn error(&self, e: ::std::io::Error) -> TransformationError {
+        TransformationError::IoError(e, PositionType::InDirectory(self.path.clone()))
+    }
+}
+
+impl Drop for PageStore {
+    fn drop(&mut self) {
+        if let Err(e) = fs::remove_file(&self.path) {
+            warn!("could not remove {}: {}", self.path.to_string_lossy(), e);
+        }
+    }
+}
+
+/// Iterator over the works and chapters of a Wikisource dump
+pub struct Works {
+    parser: ArticleParser<BzDecoder<File>>,
+    /// texts of the scanned pages
+    pages: PageStore,
+    header_templates: Vec<String>,
+}
+
+impl Works {
+    /// Read the given bzip2-compressed dump.
+    ///
+    /// This already performs the first pass over the dump, see the module documentation.
+    pub fn new(path: &Path) -> Result<Works> {
+        let mut pages = PageStore::new()?;
+        let mut parser = open_dump(path)?;
+        while let Some(page) = parser.next_page() {
+            let page = page?;
+            if !is_scanned_page(&page, parser.namespaces()) {
+                continue;
+            }
+            if let Some((index, number)) = split_page_title(&page.title) {
+                pages.insert(index, number, &clean_page_text(&page.text))?;
+            }
+        }
+        info!("{} indexes of scanned works found", pages.offsets.len());
+
+        Ok(Works { parser: open_dump(path)?, pages,
+            header_templates: DEFAULT_HEADER_TEMPLATES.iter().map(|t| t.to_string())
+                .collect() })
+    }
+
+    /// Set the names of the header templates to be removed from works.
+    pub fn set_header_templates(&mut self, templates: Vec<String>) {
+        self.header_templates = templates.into_iter().map(|t| t.to_lowercase()).collect();
+    }
+
+    /// Replace all `<pages/>` tags by the text of the referenced pages.
+    pub fn resolve_transclusions(&self, text: &str) -> Result<String> {
+        // pages of unknown indexes cannot be included anyway
+        let last_page = |index: &str| Some(self.pages.last_page(index).unwrap_or(0));
+        let mut output = String::with_capacity(text.len());
+        let mut copied_until = 0;
+        for (start, end, transclusion) in find_transclusions(text, last_page) {
+            output.push_str(&text[copied_until..start]);
+            for number in transclusion.pages {
+                if let Some(page_text) = self.pages.get(&transclusion.index, number)? {
+                    output.push_str(&page_text);
+                    output.push('\n');
+                }
+            }
+            copied_until = end;
+        }
+        output.push_str(&text[copied_until..]);
+        Ok(output)
+    }
+}
+
+impl Iterator for Works {
+    type Item = Result<Entity>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let page = trysome!(get!(self.parser.next_page()));
+            if page.namespace != 0 || page.is_redirect() {
+                continue;
+            }
+            let text = strip_templates(&trysome!(self.resolve_transclusions(&page.text)),
+                    &self.header_templates);
+            if text.trim().is_empty() {
+                continue;
+            }
+            let mut entity = Entity::new(text, page.position);
+            entity.metadata.title = Some(page.title);
+            return Some(Ok(entity));
+        }
+    }
+}
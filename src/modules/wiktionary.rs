@@ -0,0 +1,348 @@
+//! Wiktionary glosses and usage examples
+//!
+//! Wiktionary dumps use the MediaWiki XML format and are read with the
+//! [`ArticleParser`](../wikipedia/struct.ArticleParser.html). Each page describes a headword in
+//! several languages, one level-2 section per language. Only the section of the configured
+//! language is parsed; its glosses (definitions) and usage examples are emitted as context lines,
+//! one entity per headword.
+//!
+//! Two layouts are understood:
+//!
+//! -   the English Wiktionary layout with a `==English==` section, part-of-speech headings like
+//!     `===Noun===`, glosses as `#` list items and examples as `#:` or `#*:` list items,
+//! -   the German Wiktionary layout with a `== Haus ({{Sprache|Deutsch}}) ==` section, headings
+//!     like `=== {{Wortart|Substantiv|Deutsch}} ===` and glosses and examples as `:[1]` items
+//!     following the `{{Bedeutungen}}` and `{{Beispiele}}` templates.
+//!
+//! Optionally, the glosses are written to a tab-separated file with the columns headword, part of
+//! speech and gloss.
+
+use bzip2::read::BzDecoder;
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+
+use input_source::{Entity, Result, TransformationError};
+use super::wikipedia::{self, ArticleParser};
+use textfilter;
+
+/// Headings of sections which do not contain a part of speech.
+static NON_POS_HEADINGS: [&str; 26] = ["etymology", "pronunciation", "alternative forms",
+    "references", "translations", "synonyms", "antonyms", "derived terms", "related terms",
+    "see also", "anagrams", "usage notes", "descendants", "further reading", "conjugation",
+    "declension", "inflection", "hyponyms", "hypernyms", "quotations", "external links",
+    "coordinate terms", "compounds", "collocations", "abbreviations", "statistics"];
+
+/// Templates whose second positional parameter is the text to keep, e.g. `{{ux|en|an example}}`
+/// or `{{l|en|word}}`.
+static TEXT_TEMPLATES: [&str; 8] = ["ux", "usex", "uxi", "quote", "l", "m", "link", "mention"];
+
+/// Named parameters of these templates, e.g. the translation of an example (`t=`); other
+/// parameters containing `=` are taken as positional text.
+static NAMED_PARAMETERS: [&str; 16] = ["t", "tr", "ts", "lit", "gloss", "pos", "g", "id", "sc",
+    "q", "qq", "ref", "inline", "nocat", "sort", "footer"];
+
+/// A gloss (definition) of a headword.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Gloss {
+    /// part of speech as given in the heading, empty if unknown
+    pub part_of_speech: String,
+    pub text: String,
+}
+
+/// Glosses and usage examples of a headword in one language.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Entry {
+    pub glosses: Vec<Gloss>,
+    pub examples: Vec<String>,
+}
+
+// de-style block, introduced by a template on its own line
+#[derive(PartialEq)]
+enum Block {
+    Meanings,
+    Examples,
+    Other,
+}
+
+// parse a heading like "=== Noun ===" into its level and title
+fn parse_heading(line: &str) -> Option<(usize, &str)> {
+    if !line.starts_with('=') || !line.ends_with('=') || line.len() < 3 {
+        return None;
+    }
+    let level = line.chars().take_while(|c| *c == '=').count();
+    let title = line.trim_matches('=').trim();
+    match title.is_empty() {
+        true => None,
+        false => Some((level, title)),
+    }
+}
+
+fn is_language_heading(title: &str, section: &str) -> bool {
+    title.eq_ignore_ascii_case(section) || title.contains(&format!("|{}}}}}", section))
+}
+
+// extract the part of speech from a heading, if it names one
+fn part_of_speech(title: &str) -> Option<String> {
+    let title = match title.starts_with("{{") {
+        true => { // {{Wortart|Substantiv|Deutsch}}
+            let end = get!(title.find("}}"));
+            get!(title[2..end].split('|').nth(1)).trim()
+        },
+        false => title.trim_end_matches(|c: char| c.is_digit(10) || c.is_whitespace()),
+    };
+    let lower = title.to_lowercase();
+    match title.is_empty() || NON_POS_HEADINGS.contains(&lower.as_str()) {
+        true => None,
+        false => Some(title.to_string()),
+    }
+}
+
+// expand a template (without braces) to the text it contributes to a sentence
+fn expand_template(template: &str) -> String {
+    let mut parameters = template.split('|').filter(|p| match p.find('=') {
+        Some(equals) => !NAMED_PARAMETERS.contains(&p[..equals].trim()),
+        None => true,
+    });
+    let name = parameters.next().unwrap_or("").trim().to_lowercase();
+    if TEXT_TEMPLATES.contains(&name.as_str()) {
+        parameters.nth(1).unwrap_or("").to_string()
+    } else if name == "gloss" {
+        format!("({})", parameters.next().unwrap_or(""))
+    } else {
+        String::new()
+    }
+}
+
+// remove elements like <ref>...</ref> including their content
+fn remove_element(text: &str, name: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+    let mut rest = text;
+    let start_tag = format!("<{}", name);
+    let end_tag = format!("</{}>", name);
+    while let Some(start) = rest.find(&start_tag) {
+        output.push_str(&rest[..start]);
+        let tag_end = match rest[start..].find('>') {
+            Some(end) => start + end + 1,
+            None => { rest = ""; break; },
+        };
+        rest = match rest[start..tag_end].ends_with("/>") {
+            true => &rest[tag_end..],
+            false => match rest[tag_end..].find(&end_tag) {
+                Some(end) => &rest[tag_end + end + end_tag.len()..],
+                None => "",
+            },
+        };
+    }
+    output.push_str(rest);
+    output
+}
+
+// the innermost template being read or the text outside of templates
+fn innermost<'a>(templates: &'a mut [String], text: &'a mut String) -> &'a mut String {
+    match templates.last_mut() {
+        Some(template) => template,
+        None => text,
+    }
+}
+
+// Expand templates from the innermost to the outermost in one pass; each template is collected
+// on a stack until its closing braces. Stray closing braces and unclosed templates are kept.
+fn expand_templates(markup: &str) -> String {
+    let mut text = String::with_capacity(markup.len());
+    let mut templates: Vec<String> = Vec::new();
+    let bytes = markup.as_bytes();
+    let mut copied_until = 0;
+    let mut position = 0;
+    while position + 1 < bytes.len() {
+        let braces = &bytes[position..position + 2];
+        if braces != b"{{" && braces != b"}}" {
+            position += 1;
+            continue;
+        }
+        innermost(&mut templates, &mut text).push_str(&markup[copied_until..position]);
+        if braces == b"{{" {
+            templates.push(String::new());
+        } else {
+            match templates.pop() {
+                Some(template) => innermost(&mut templates, &mut text)
+                    .push_str(&expand_template(&template)),
+                None => text.push_str("}}"),
+            }
+        }
+        position += 2;
+        copied_until = position;
+    }
+    innermost(&mut templates, &mut text).push_str(&markup[copied_until..]);
+    while let Some(template) = templates.pop() {
+        let outer = innermost(&mut templates, &mut text);
+        outer.push_str("{{");
+        outer.push_str(&template);
+    }
+    text
+}
+
+/// Reduce MediaWiki markup to plain text.
+///
+/// Templates are expanded to their text for a few well-known templates (usage examples, links,
+/// glosses) and removed otherwise. Links are replaced by their label, references, HTML tags and
+/// bold or italic markup are removed.
+pub fn clean_markup(text: &str) -> String {
+    let text = remove_element(text, "ref");
+    // replace links first, their labels would be split into template parameters otherwise
+    let mut output = String::with_capacity(text.len());
+    let mut rest = text.as_str();
+    while let Some(start) = rest.find("[[") {
+        output.push_str(&rest[..start]);
+        let end = match rest[start..].find("]]") {
+            Some(end) => start + end,
+            None => break,
+        };
+        let link = &rest[start + 2..end];
+        output.push_str(link.rsplit('|').next().unwrap_or(""));
+        rest = &rest[end + 2..];
+    }
+    output.push_str(rest);
+    let output = expand_templates(&output);
+    // strip remaining HTML tags
+    let mut text = String::with_capacity(output.len());
+    let mut in_tag = false;
+    for c in output.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            _ if !in_tag => text.push(c),
+            _ => (),
+        }
+    }
+    text.replace("'''", "").replace("''", "").split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+// remove the list marker of a de-style item, e.g. ":[1, 2] "
+fn strip_item_marker(line: &str) -> &str {
+    let line = line.trim_start_matches(':').trim_start();
+    match line.starts_with('[') {
+        true => match line.find(']') {
+            Some(end) => line[end + 1..].trim_start(),
+            None => line,
+        },
+        false => line,
+    }
+}
+
+/// Parse the glosses and usage examples from the given language section of a page.
+///
+/// `section` is the name of the language as used in the section headings, e.g. `English` on the
+/// English Wiktionary or `Deutsch` on the German one.
+pub fn parse_entry(markup: &str, section: &str) -> Entry {
+    let mut entry = Entry::default();
+    let mut in_section = false;
+    let mut pos = String::new();
+    let mut block = Block::Other;
+    for line in markup.lines() {
+        let line = line.trim();
+        if let Some((level, title)) = parse_heading(line) {
+            if level <= 2 {
+                in_section = is_language_heading(title, section);
+                pos.clear();
+            } else if let Some(part_of_speech) = part_of_speech(title) {
+                pos = part_of_speech;
+            }
+            block = Block::Other;
+            continue;
+        }
+        if !in_section {
+            continue;
+        }
+        match line {
+            "{{Bedeutungen}}" => block = Block::Meanings,
+            "{{Beispiele}}" => block = Block::Examples,
+            _ if line.starts_with("{{") && line.ends_with("}}") => block = Block::Other,
+            _ if line.starts_with('#') => {
+                let marker_end = line.find(|c| c != '#' && c != ':' && c != '*')
+                    .unwrap_or(line.len());
+                let marker = &line[..marker_end];
+                // #* introduces the source of a quotation
+                if marker.ends_with('*') {
+                    continue;
+                }
+                let text = clean_markup(&line[marker_end..]);
+                if text.is_empty() {
+                    continue;
+                }
+                match marker.contains(':') {
+                    true => entry.examples.push(text),
+                    false => entry.glosses.push(Gloss { part_of_speech: pos.clone(), text }),
+                }
+            },
+            _ if line.starts_with(':') && block != Block::Other => {
+                let text = clean_markup(strip_item_marker(line));
+                if text.is_empty() {
+                    continue;
+                }
+                match block {
+                    Block::Meanings => entry.glosses.push(Gloss {
+                            part_of_speech: pos.clone(), text }),
+                    _ => entry.examples.push(text),
+                }
+            },
+            _ => (),
+        }
+    }
+    entry
+}
+
+/// Iterator over the Wiktionary entries of a dump
+///
+/// Each entity contains the glosses and examples of one headword, each on its own context line.
+/// The headword is stored as title in the metadata.
+pub struct Entries {
+    parser: ArticleParser<BzDecoder<File>>,
+    section: String,
+    /// optional output for the headword, part of speech and gloss table
+    gloss_output: Option<Box<Write>>,
+}
+
+impl Entries {
+    /// Read the given bzip2-compressed dump, parsing the sections with the given language name.
+    pub fn new(path: &Path, section: &str) -> Result<Entries> {
+        Ok(Entries { parser: wikipedia::parser_from_file(path)?, section: section.to_string(),
+            gloss_output: None })
+    }
+
+    /// Write all glosses as tab-separated values (headword, part of speech, gloss) to the given
+    /// output.
+    pub fn set_gloss_output(&mut self, output: Box<Write>) {
+        self.gloss_output = Some(output);
+    }
+}
+
+impl Iterator for Entries {
+    type Item = Result<Entity>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let page = trysome!(get!(self.parser.next_page()));
+            if page.namespace != 0 || page.is_redirect() {
+                continue;
+            }
+            let entry = parse_entry(&page.text, &self.section);
+            if let Some(ref mut output) = self.gloss_output {
+                for gloss in entry.glosses.iter() {
+                    trysome!(writeln!(output, "{}\t{}\t{}", page.title, gloss.part_of_speech,
+                            gloss.text).map_err(|e| TransformationError::IoError(e,
+                            page.position.clone())));
+                }
+            }
+            let separator = format!(" {} ", textfilter::RETURN_ESCAPE_SEQUENCE);
+            let content = entry.glosses.into_iter().map(|g| g.text)
+                .chain(entry.examples.into_iter()).collect::<Vec<_>>().join(&separator);
+            if content.is_empty() {
+                continue;
+            }
+            let mut entity = Entity::new(content, page.position);
+            entity.metadata.title = Some(page.title);
+            return Some(Ok(entity));
+        }
+    }
+}
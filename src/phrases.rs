@@ -0,0 +1,253 @@
+//! Phrase detection
+//!
+//! Multiword expressions like "New York" or "Europäische Union" are better represented by a
+//! single vector. Following Mikolov et al. (2013) and gensim, a bigram `a b` becomes a phrase if
+//!
+//! ```text
+//! (count(a b) - min_count) / (count(a) * count(b)) * |vocabulary| > threshold
+//! ```
+//!
+//! where `|vocabulary|` is the number of distinct words and bigrams counted. The words of a
+//! phrase are joined with `_`. As each pass only joins two tokens, longer phrases ("New_York
+//! Times") are found by further passes over the output of the previous one.
+//!
+//! The input is text as returned by `text2words`: one context per line, words separated by
+//! single spaces. Phrases never span lines. Texts can be processed in memory or, for corpora
+//! larger than the main memory, as files, which are read twice per pass (counting, then
+//! joining).
+
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use input_source::Result;
+
+/// Character joining the words of a phrase.
+pub static DELIMITER: char = '_';
+
+/// Parameters of the phrase detection; the defaults are those of gensim.
+#[derive(Clone, Debug)]
+pub struct PhraseConfig {
+    /// minimum score of a phrase
+    pub threshold: f64,
+    /// bigrams occurring at most this often are never phrases
+    pub min_count: u64,
+    /// number of passes (at least one); each pass can join two tokens
+    pub passes: usize,
+    /// rare words and bigrams are pruned if more than this many are counted
+    pub max_vocabulary: usize,
+}
+
+impl Default for PhraseConfig {
+    fn default() -> PhraseConfig {
+        PhraseConfig { threshold: 10.0, min_count: 5, passes: 2, max_vocabulary: 40_000_000 }
+    }
+}
+
+// key of a bigram in the count tables; words never contain spaces
+fn bigram_key(buffer: &mut String, first: &str, second: &str) {
+    buffer.clear();
+    buffer.push_str(first);
+    buffer.push(' ');
+    buffer.push_str(second);
+}
+
+/// Word and bigram counts of a text
+#[derive(Debug, Default)]
+pub struct Counts {
+    words: HashMap<String, u64>,
+    bigrams: HashMap<String, u64>,
+    max_vocabulary: usize,
+    /// entries below this count are removed when pruning
+    min_reduce: u64,
+}
+
+impl Counts {
+    pub fn new(max_vocabulary: usize) -> Counts {
+        Counts { max_vocabulary, min_reduce: 1, ..Counts::default() }
+    }
+
+    /// Count the words and bigrams of a line.
+    pub fn add_line(&mut self, line: &str) {
+        let mut key = String::new();
+        let mut previous: Option<&str> = None;
+        for word in line.split_whitespace() {
+            *self.words.entry(word.to_string()).or_insert(0) += 1;
+            if let Some(previous) = previous {
+                bigram_key(&mut key, previous, word);
+                match self.bigrams.get_mut(&key) {
+                    Some(count) => *count += 1,
+                    None => {
+                        self.bigrams.insert(key.clone(), 1);
+                    },
+                }
+            }
+            previous = Some(word);
+        }
+        if self.words.len() + self.bigrams.len() > self.max_vocabulary {
+            self.prune();
+        }
+    }
+
+    // remove the rarest entries, as gensim does
+    fn prune(&mut self) {
+        let min_reduce = self.min_reduce;
+        self.words.retain(|_, count| *count >= min_reduce);
+        self.bigrams.retain(|_, count| *count >= min_reduce);
+        self.min_reduce += 1;
+    }
+
+    /// Score all bigrams and keep those above the threshold.
+    pub fn phrases(&self, config: &PhraseConfig) -> Phrases {
+        let vocabulary = (self.words.len() + self.bigrams.len()) as f64;
+        let mut phrases = HashMap::new();
+        for (bigram, &count) in &self.bigrams {
+            if count <= config.min_count {
+                continue;
+            }
+            let mut words = bigram.splitn(2, ' ');
+            let first = words.next().and_then(|w| self.words.get(w));
+            let second = words.next().and_then(|w| self.words.get(w));
+            if let (Some(&first), Some(&second)) = (first, second) {
+                let score = (count - config.min_count) as f64 / (first * second) as f64
+                    * vocabulary;
+                if score > config.threshold {
+                    phrases.insert(bigram.clone(), score);
+                }
+            }
+        }
+        Phrases { phrases }
+    }
+}
+
+/// Bigrams detected as phrases, with their score
+#[derive(Debug, Default)]
+pub struct Phrases {
+    phrases: HashMap<String, f64>,
+}
+
+impl Phrases {
+    /// Detect the phrases of the given lines.
+    pub fn learn<'a, I: IntoIterator<Item=&'a str>>(lines: I, config: &PhraseConfig)
+            -> Phrases {
+        let mut counts = Counts::new(config.max_vocabulary);
+        for line in lines {
+            counts.add_line(line);
+        }
+        counts.phrases(config)
+    }
+
+    /// Number of phrases.
+    pub fn len(&self) -> usize {
+        self.phrases.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.phrases.is_empty()
+    }
+
+    /// Score of a bigram, if it is a phrase.
+    pub fn score(&self, first: &str, second: &str) -> Option<f64> {
+        let mut key = String::new();
+        bigram_key(&mut key, first, second);
+        self.phrases.get(&key).cloned()
+    }
+
+    /// Join the phrases of a line, from left to right. The line break, if any, is not part of the
+    /// result.
+    pub fn apply(&self, line: &str) -> String {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let mut output = String::with_capacity(line.len());
+        let mut key = String::new();
+        let mut index = 0;
+        while index < words.len() {
+            if !output.is_empty() {
+                output.push(' ');
+            }
+            output.push_str(words[index]);
+            if index + 1 < words.len() {
+                bigram_key(&mut key, words[index], words[index + 1]);
+                if self.phrases.contains_key(&key) {
+                    output.push(DELIMITER);
+                    output.push_str(words[index + 1]);
+                    index += 1;
+                }
+            }
+            index += 1;
+        }
+        output
+    }
+}
+
+/// Join the phrases of a text held in memory, in the configured number of passes.
+pub fn detect_in_memory(text: &str, config: &PhraseConfig) -> String {
+    let mut lines: Vec<String> = text.lines().map(String::from).collect();
+    for _ in 0..config.passes.max(1) {
+        let phrases = Phrases::learn(lines.iter().map(|l| l.as_str()), config);
+        if phrases.is_empty() {
+            break;
+        }
+        lines = lines.iter().map(|l| phrases.apply(l)).collect();
+    }
+    let mut output = lines.join("\n");
+    if text.ends_with('\n') {
+        output.push('\n');
+    }
+    output
+}
+
+// count one file, then write it with the phrases joined
+fn phrase_pass(input: &Path, output: &Path, config: &PhraseConfig) -> Result<usize> {
+    let mut counts = Counts::new(config.max_vocabulary);
+    for line in BufReader::new(File::open(input)?).lines() {
+        counts.add_line(&line?);
+    }
+    let phrases = counts.phrases(config);
+    drop(counts);
+    let mut writer = BufWriter::new(File::create(output)?);
+    for line in BufReader::new(File::open(input)?).lines() {
+        writeln!(writer, "{}", phrases.apply(&line?))?;
+    }
+    writer.flush()?;
+    Ok(phrases.len())
+}
+
+/// Join the phrases of a corpus file and write the result to another file.
+///
+/// Each pass writes its result next to the output; the last one replaces the output, which may
+/// hence be the input itself. Intermediate results are removed, also if a pass fails. Returns the
+/// number of phrases found in each pass.
+pub fn detect_in_file(input: &Path, output: &Path, config: &PhraseConfig)
+        -> Result<Vec<usize>> {
+    let intermediate = |pass: usize| PathBuf::from(format!("{}.pass{}",
+            output.to_string_lossy(), pass));
+    let mut found = Vec::new();
+    let mut source = input.to_path_buf();
+    for pass in 0..config.passes.max(1) {
+        let target = intermediate(pass);
+        let result = phrase_pass(&source, &target, config).and_then(|phrases| {
+            if source != input {
+                fs::remove_file(&source)?;
+            }
+            Ok(phrases)
+        });
+        match result {
+            Ok(phrases) => found.push(phrases),
+            Err(e) => {
+                for path in &[&source, &target] {
+                    if *path != input && path.exists() {
+                        let _ = fs::remove_file(path);
+                    }
+                }
+                return Err(e);
+            },
+        }
+        source = target;
+    }
+    if let Err(e) = fs::rename(&source, output) {
+        let _ = fs::remove_file(&source);
+        return Err(e.into());
+    }
+    Ok(found)
+}
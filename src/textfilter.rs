@@ -1,16 +1,32 @@
 //! Convert any document to "plain plain text"
 //!
 //! Word2vec requires text to be without any punctuation, not intermixed with numbers, equal
-//! spacing, etc. This module contains two things:
+//! spacing, etc. This module contains three things:
 //!
 //! 1.  Functions to call Pandoc and use its abstract document tree to iterate over it and extract
 //!     _only_ text and to preserve context information for Word2vec on a pargraph level.
 //! 2.  A function to strip white spaces, punctuation and surrounding characters, e.g. parenthesis.
+//!     Characters are classified by their Unicode general category, see
+//!     [`CharClasses`](struct.CharClasses.html).
+//! 3.  Normalizers which reduce inflected forms to a stem or lemma, so that morphologically rich
+//!     languages do not spread over too many rare word forms.
+//!
+//! Languages like Chinese, Japanese or Thai do not separate words by spaces; their text is
+//! segmented at the Unicode word boundaries (UAX #29) and, given a lexicon, by maximum matching,
+//! see [`Segmenter`](enum.Segmenter.html).
+use isolang::Language;
 use json::{self, object, JsonValue};
 use pandoc;
-use std::collections::HashSet;
+use rust_stemmers::{Algorithm, Stemmer};
+use unic_ucd_category::GeneralCategory;
+use unicode_segmentation::UnicodeSegmentation;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::Path;
 
-use input_source::{Entity, Result, TransformationError};
+use common;
+use input_source::{Entity, Metadata, PositionType, Result, TransformationError};
 
 /// An internal escape sequence for newlines, see [`stringify_text`](fn.stringify_text.html)
 pub static RETURN_ESCAPE_SEQUENCE: char = '\x07';
@@ -22,17 +38,34 @@ pub static RETURN_ESCAPE_SEQUENCE: char = '\x07';
 /// method `call_pandoc` transparently pipes the given String into pandoc and reads its output back
 /// into a json String.
 pub fn call_pandoc(input_format: pandoc::InputFormat, input: Entity) -> Result<Entity> {
+    let Entity { content, position, metadata } = input;
+    run_pandoc(input_format, pandoc::InputKind::Pipe(content), position, metadata)
+}
+
+/// Convert a file with pandoc
+///
+/// In contrast to [`call_pandoc`](fn.call_pandoc.html), pandoc reads the document itself from the
+/// given file. This is required for binary formats like DOCX or ODT. The content of the entity is
+/// replaced by the JSON representation of the document.
+pub fn call_pandoc_with_file(input_format: pandoc::InputFormat, path: &Path, input: Entity)
+        -> Result<Entity> {
+    run_pandoc(input_format, pandoc::InputKind::Files(vec![path.to_path_buf()]), input.position,
+            input.metadata)
+}
+
+fn run_pandoc(input_format: pandoc::InputFormat, input: pandoc::InputKind,
+        position: PositionType, metadata: Metadata) -> Result<Entity> {
     let mut p = pandoc::new();
     p.set_output_format(pandoc::OutputFormat::Json, vec![]);
     p.set_input_format(input_format, vec![]);
     p.set_output(pandoc::OutputKind::Pipe);
-    p.set_input(pandoc::InputKind::Pipe(input.content));
+    p.set_input(input);
     match p.execute() {
         Ok(pandoc::PandocOutput::ToBuffer(data)) =>
-            Ok(Entity { content: data, position: input.position.clone() }),
+            Ok(Entity { content: data, position, metadata }),
         Ok(_) => panic!(format!("Expected converted data, got file name instead\nThis is a bug and needs to be fixed before continuing.")),
         Err(x) => Err(TransformationError::ErrorneousStructure(format!("{}\n",
-               x), input.position.clone()))
+               x), position))
     }
 }
 
@@ -214,57 +247,255 @@ pub fn stringify_text(mut pandoc_dump: Entity) -> Result<Entity> {
 ////////////////////////////////////////////////////////////////////////////////
 // strip punctuation
 
-// Test whether given character is a enclosing character like quotes or
-// parenthesis.
-#[inline]
-fn is_enclosing_character(c: char) -> bool {
-    match c {
-        '(' | ')' | '[' | ']' | '{' | '}' | '"' | '„' | '”' | '“' | '‚' 
-            | '’' | '‘' | '«' | '»'  => true,
-        _ => false,
+/// Class of a character, as far as the word filter is concerned
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CharClass {
+    /// quotes, parenthesis and alike, stripped from both ends of a word
+    Enclosing,
+    /// stripped from both ends of a word; a word containing punctuation is dropped
+    Punctuation,
+    /// allowed within words (O'Neill) and marks elisions and clitics
+    Apostrophe,
+    /// allowed within words, e.g. hyphens or `_`, which joins annotations like a POS tag
+    Joiner,
+}
+
+/// Treatment of elided articles (l'homme) and clitics (John's)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AffixHandling {
+    /// leave the word untouched
+    Keep,
+    /// write the elision or clitic as a word of its own: l' homme, John 's
+    Split,
+    /// remove the elision or clitic: homme, John
+    Drop,
+}
+
+impl AffixHandling {
+    /// Parse the configuration value: keep, split or drop.
+    pub fn from_name(name: &str) -> Option<AffixHandling> {
+        match name {
+            "keep" => Some(AffixHandling::Keep),
+            "split" => Some(AffixHandling::Split),
+            "drop" => Some(AffixHandling::Drop),
+            _ => None,
+        }
     }
 }
 
-#[inline]
-fn is_punctuation(c: char) -> bool {
+// default class of a character, derived from its Unicode general category
+fn default_char_class(c: char) -> Option<CharClass> {
     match c {
-        '.' | ',' | ':' | ';' | '?' | '!' | '…' | '–' => true,
-        _ => false,
+        '\'' | '`' | '‘' | '’' | '‚' | 'ʼ' => return Some(CharClass::Apostrophe),
+        '-' | '‐' | '_' => return Some(CharClass::Joiner),
+        '"' => return Some(CharClass::Enclosing),
+        _ => (),
+    }
+    // the look-up of the category is comparatively slow: classify ASCII directly, letters and
+    // digits are never punctuation
+    if c.is_ascii() {
+        return match c {
+            '(' | ')' | '[' | ']' | '{' | '}' => Some(CharClass::Enclosing),
+            '!' | '#' | '%' | '&' | '*' | ',' | '.' | '/' | ':' | ';' | '?' | '@' | '\\'
+                => Some(CharClass::Punctuation),
+            _ => None,
+        };
+    }
+    if c.is_alphanumeric() {
+        return None;
+    }
+    match GeneralCategory::of(c) {
+        GeneralCategory::OpenPunctuation | GeneralCategory::ClosePunctuation
+            | GeneralCategory::InitialPunctuation | GeneralCategory::FinalPunctuation
+            => Some(CharClass::Enclosing),
+        GeneralCategory::OtherPunctuation | GeneralCategory::DashPunctuation
+            => Some(CharClass::Punctuation),
+        _ => None,
     }
 }
 
-// test whether character is an apostrophe or alike
-#[inline]
-fn is_apostrophe(c: char) -> bool {
-    match c {
-        '\'' | '`' | '‚' | '‘' | '’' => true,
-        _ => false,
+/// Character classes and affix rules of a language
+///
+/// By default, characters are classified by their Unicode general category: opening, closing
+/// and quotation marks enclose words, all other punctuation (Arabic comma, Devanagari danda,
+/// ideographic full stop, ¿, ¡, ...) is stripped, except for hyphens. Apostrophes are kept
+/// within words. Single characters can be assigned to another class with
+/// [`set_class`](#method.set_class).
+///
+/// Elided articles (French l'homme) and clitics (English John's) are kept by default; for
+/// French, Italian, Catalan and English, lists of them are predefined.
+#[derive(Clone, Debug)]
+pub struct CharClasses {
+    overrides: HashMap<char, Option<CharClass>>,
+    /// treatment of elided prefixes
+    pub elision: AffixHandling,
+    /// treatment of clitic suffixes
+    pub clitics: AffixHandling,
+    // lower-case, without the apostrophe
+    elisions: HashSet<String>,
+    // lower-case, the apostrophe written as '
+    clitic_suffixes: Vec<String>,
+}
+
+impl Default for CharClasses {
+    fn default() -> CharClasses {
+        CharClasses { overrides: HashMap::new(), elision: AffixHandling::Keep,
+            clitics: AffixHandling::Keep, elisions: HashSet::new(),
+            clitic_suffixes: Vec::new() }
     }
 }
 
+impl CharClasses {
+    /// Character classes with the elisions and clitics of the given language; both are kept
+    /// until enabled with the `elision` and `clitics` fields.
+    pub fn for_language(language: &Language) -> CharClasses {
+        let (elisions, clitics): (&[&str], &[&str]) = match language.to_639_3() {
+            "fra" => (&["c", "d", "j", "l", "m", "n", "s", "t", "qu", "jusqu", "lorsqu",
+                    "puisqu", "quoiqu", "presqu"], &[]),
+            "ita" => (&["c", "d", "l", "m", "n", "s", "t", "v", "un", "all", "dall", "dell",
+                    "nell", "sull", "coll", "quell", "quest", "bell"], &[]),
+            "cat" => (&["d", "l", "m", "n", "s", "t"], &["'l", "'ls", "'m", "'n", "'ns", "'s",
+                    "'t", "'ho", "'hi"]),
+            "eng" => (&[], &["'s", "'re", "'ve", "'ll", "'d", "'m", "n't"]),
+            _ => (&[], &[]),
+        };
+        let mut classes = CharClasses::default();
+        classes.set_elisions(elisions.iter().cloned());
+        classes.set_clitics(clitics.iter().cloned());
+        classes
+    }
+
+    /// Assign a character to a class; `None` makes it an ordinary word character.
+    pub fn set_class(&mut self, c: char, class: Option<CharClass>) {
+        self.overrides.insert(c, class);
+    }
+
+    /// Class of a character, if it is no ordinary word character.
+    pub fn class_of(&self, c: char) -> Option<CharClass> {
+        if self.overrides.is_empty() {
+            return default_char_class(c);
+        }
+        match self.overrides.get(&c) {
+            Some(class) => *class,
+            None => default_char_class(c),
+        }
+    }
+
+    /// Replace the elided prefixes, given without apostrophe (l, qu, ...).
+    pub fn set_elisions<S: AsRef<str>, I: IntoIterator<Item=S>>(&mut self, prefixes: I) {
+        self.elisions = prefixes.into_iter().map(|p| p.as_ref().to_lowercase()).collect();
+    }
+
+    /// Replace the clitic suffixes, given with apostrophe ('s, n't, ...); empty suffixes are
+    /// ignored.
+    pub fn set_clitics<S: AsRef<str>, I: IntoIterator<Item=S>>(&mut self, suffixes: I) {
+        self.clitic_suffixes = suffixes.into_iter()
+            .map(|s| s.as_ref().chars().map(|c| match self.class_of(c) {
+                Some(CharClass::Apostrophe) => '\'',
+                _ => c,
+            }).flat_map(char::to_lowercase).collect::<String>())
+            .filter(|suffix| !suffix.is_empty())
+            .collect();
+        // longest first, so that 'ls is preferred over 's
+        self.clitic_suffixes.sort_by_key(|suffix| ::std::cmp::Reverse(suffix.chars().count()));
+    }
+
+    // whether the character is removed from the ends of a word
+    fn is_stripped(&self, c: char) -> bool {
+        match self.class_of(c) {
+            Some(CharClass::Enclosing) | Some(CharClass::Punctuation) => true,
+            // typographic apostrophes double as single quotation marks
+            Some(CharClass::Apostrophe) => !self.overrides.contains_key(&c)
+                && matches!(GeneralCategory::of(c), GeneralCategory::OpenPunctuation
+                    | GeneralCategory::InitialPunctuation | GeneralCategory::FinalPunctuation),
+            _ => false,
+        }
+    }
+
+    /// Remove enclosing characters and punctuation from both ends of a word.
+    pub fn strip<'a>(&self, word: &'a str) -> &'a str {
+        word.trim_matches(|c| self.is_stripped(c))
+    }
+
+    /// Split a stripped word into an elided prefix, the word itself and a clitic suffix.
+    ///
+    /// Affixes which are kept are part of the word, dropped ones are omitted.
+    pub fn split_affixes<'a>(&self, word: &'a str) -> (Option<&'a str>, &'a str, Option<&'a str>) {
+        let (mut prefix, mut word, mut suffix) = (None, word, None);
+        if self.elision != AffixHandling::Keep {
+            let apostrophe = word.char_indices()
+                .find(|&(_, c)| self.class_of(c) == Some(CharClass::Apostrophe));
+            if let Some((index, c)) = apostrophe {
+                let end = index + c.len_utf8();
+                if end < word.len() && self.elisions.contains(&word[..index].to_lowercase()) {
+                    if self.elision == AffixHandling::Split {
+                        prefix = Some(&word[..end]);
+                    }
+                    word = &word[end..];
+                }
+            }
+        }
+        if self.clitics != AffixHandling::Keep {
+            if let Some(start) = self.clitic_start(word) {
+                if self.clitics == AffixHandling::Split {
+                    suffix = Some(&word[start..]);
+                }
+                word = &word[..start];
+            }
+        }
+        (prefix, word, suffix)
+    }
+
+    // byte index of the clitic at the end of a word, which has to leave a word in front of it
+    fn clitic_start(&self, word: &str) -> Option<usize> {
+        for clitic in &self.clitic_suffixes {
+            let length = clitic.chars().count();
+            let start = match word.char_indices().rev().nth(length - 1) {
+                Some((0, _)) | None => continue,
+                Some((start, _)) => start,
+            };
+            let matches = word[start..].chars().map(|c| match self.class_of(c) {
+                Some(CharClass::Apostrophe) => '\'',
+                _ => c,
+            }).flat_map(char::to_lowercase).eq(clitic.chars());
+            if matches {
+                return Some(start);
+            }
+        }
+        None
+    }
+}
 
+// combining marks which are not alphabetic, e.g. Thai tone marks, belong to the preceding letter
+#[inline]
+fn is_mark(c: char) -> bool {
+    !c.is_ascii() && GeneralCategory::of(c).is_mark()
+}
 
 // Test whether all characters are alphabetical or a number; could be a closure, but early
-// return might make it SLIGHTLY more efficient; "-" is part  valid within a
-// word, too
-// Note: apostrophes count as alphabetical, too.
+// return might make it SLIGHTLY more efficient; joiners like "-" are valid within a word, too, as
+// is "_", which joins annotations like a POS tag (lemma_NOUN)
+// Note: apostrophes and combining marks count as alphabetical, too.
 #[inline]
-fn word_should_be_included(word: &String) -> bool {
-    match word.len() {
+fn word_should_be_included(word: &str, characters: &CharClasses) -> bool {
+    // count characters, not bytes, so that single CJK characters are words, too
+    match word.chars().count() {
         0 => false,
-        1 | 2 => word.chars().all(|x: char| x.is_digit(10) || x.is_alphabetic()),
+        1 | 2 => word.chars().enumerate().all(|(i, x)| x.is_ascii_digit() || x.is_alphabetic()
+                || (i > 0 && is_mark(x))),
         _ => {
             // make sure that a word not only consists of dashes: or apostrophes
             let first = word.chars().next().unwrap();
-            if !(first.is_digit(10) || first.is_alphabetic()) {
+            if !(first.is_ascii_digit() || first.is_alphabetic()) {
                 return false;
             }
             // check rest of word less restrictively: allow hyphens and dashes
             let mut found_one_alphabetical_character = false;
             for character in word.chars().skip(1) {
-                let valid_char = character.is_digit(10) || character.is_alphabetic();
-                let is_apostrophe = is_apostrophe(character);
-                if !(is_apostrophe || valid_char || character == '-') {
+                let valid_char = character.is_ascii_digit() || character.is_alphabetic()
+                    || is_mark(character);
+                if !valid_char && !matches!(characters.class_of(character),
+                        Some(CharClass::Apostrophe) | Some(CharClass::Joiner)) {
                     return false;
                 }
                 if valid_char {
@@ -276,34 +507,16 @@ fn word_should_be_included(word: &String) -> bool {
     }
 }
 
-// remove parenthesis and similar from word
-fn remove_enclosing_characters(input: &mut String) {
-    while let Some(x) = input.chars().rev().next() {
-        let _ = match is_enclosing_character(x) {
-            true => {
-                input.pop();
-            },
-            false => break,
-        };
-    }
-
-    // remove enclosing characters at beginning
-    while let Some(y) = input.chars().next() {
-        let _ = match is_enclosing_character(y) {
-            true => input.remove(0),
-            false => break
-        };
-    }
-}
-
-// strip punctuation and return whether punctuation has been stripped
-fn remove_punctuation(input: &mut String) {
-    while let Some(punct) = input.chars().rev().next() {
-        let _ = match is_punctuation(punct) {
-            true => input.pop(),
-            false => break
-        };
-    }
+/// Options of the word filter
+///
+/// Unset options fall back to the defaults of [`text2words`](fn.text2words.html): no stop words,
+/// no normalisation, white space segmentation and the Unicode character classes.
+#[derive(Clone, Copy, Default)]
+pub struct FilterOptions<'a> {
+    pub stopwords: Option<&'a HashSet<String>>,
+    pub normalizer: Option<&'a Normalizer>,
+    pub segmenter: Option<&'a Segmenter>,
+    pub characters: Option<&'a CharClasses>,
 }
 
 /// Strip punctuation, parenthesis, numbers and useless white space.
@@ -313,34 +526,518 @@ fn remove_punctuation(input: &mut String) {
 /// (surrounded by a space), which will enforce a line break.
 /// The returned String ends on `\n`, unless empty.
 pub fn text2words(input: String, stopwords: Option<HashSet<String>>) -> String {
-    // ToDo: make this more efficient
-    let stopwords = stopwords.unwrap_or(HashSet::new());
-    let mut words = String::new();
+    text2words_normalized(input, stopwords, None)
+}
+
+/// Strip punctuation like [`text2words`](fn.text2words.html) and normalise each word.
+///
+/// The normalizer is applied to the words which remain after stripping and stop word removal,
+/// i.e. stop words are matched against the inflected forms.
+pub fn text2words_normalized(input: String, stopwords: Option<HashSet<String>>,
+        normalizer: Option<&Normalizer>) -> String {
+    text2words_segmented(input, stopwords, normalizer, &Segmenter::Whitespace)
+}
+
+/// Strip punctuation like [`text2words_normalized`](fn.text2words_normalized.html), splitting the
+/// text into words with the given segmenter.
+pub fn text2words_segmented(input: String, stopwords: Option<HashSet<String>>,
+        normalizer: Option<&Normalizer>, segmenter: &Segmenter) -> String {
+    text2words_with(&input, &FilterOptions { stopwords: stopwords.as_ref(), normalizer,
+        segmenter: Some(segmenter), characters: None })
+}
 
-    for word in input.split_whitespace() {
+/// Strip punctuation like [`text2words`](fn.text2words.html) with the given options.
+///
+/// Elided prefixes and clitics which are split off are written as words of their own, even
+/// though they contain an apostrophe.
+pub fn text2words_with(input: &str, options: &FilterOptions) -> String {
+    let mut words = Vec::with_capacity(input.len());
+    text2words_into(input, options, &mut words).expect("writing to memory cannot fail");
+    // only slices of the input and normalised words, both valid UTF-8, have been written
+    String::from_utf8(words).expect("words are valid UTF-8")
+}
+
+/// Strip punctuation like [`text2words_with`](fn.text2words_with.html) and write the words to
+/// the given writer.
+///
+/// The words are written as slices of the input; only normalised words are allocated. Since
+/// each word and separator is a single write, the writer should be buffered.
+pub fn text2words_into<W: Write>(input: &str, options: &FilterOptions, writer: &mut W)
+        -> io::Result<()> {
+    let empty = HashSet::new();
+    let stopwords = options.stopwords.unwrap_or(&empty);
+    let default_characters = CharClasses::default();
+    let characters = options.characters.unwrap_or(&default_characters);
+    let segmenter = options.segmenter.unwrap_or(&Segmenter::Whitespace);
+    // whether a word has been written since the last line break, i.e. a space or the final line
+    // break is required
+    let mut in_line = false;
+
+    for word in segmenter.words(input) {
         // according to fn doc, escape sequence has length of 1, check whether newline requested:
         if word.len() == 1 && word.starts_with(RETURN_ESCAPE_SEQUENCE) {
-            words.push('\n');
-        } else {
-            // remove punctuation, then  enclosing characters (quotations or parenthesis) and then
-            // remove cpunctuation again
-            let mut word = String::from(word);
-            remove_punctuation(&mut word);
-            remove_enclosing_characters(&mut word);
-            remove_punctuation(&mut word);
-            if word_should_be_included(&word) && !stopwords.contains(&word) {
-                if words.chars().last() != Some('\n') && !words.is_empty() {
-                    words.push(' ');
+            writer.write_all(b"\n")?;
+            in_line = false;
+            continue;
+        }
+        // remove punctuation and enclosing characters (quotations or parenthesis)
+        let (prefix, word, suffix) = characters.split_affixes(characters.strip(word));
+        if !word_should_be_included(word, characters) {
+            continue;
+        }
+        for word in prefix.into_iter().chain(Some(word)).chain(suffix) {
+            if stopwords.contains(word) {
+                continue;
+            }
+            if in_line {
+                writer.write_all(b" ")?;
+            }
+            match options.normalizer {
+                Some(normalizer) => writer.write_all(normalizer.normalize(word).as_bytes())?,
+                None => writer.write_all(word.as_bytes())?,
+            }
+            in_line = true;
+        }
+    }
+
+    if in_line {
+        writer.write_all(b"\n")?;
+    }
+    Ok(())
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// normalisation of inflected forms
+
+/// Reduce the inflected forms of a word to a common form
+///
+/// Normalizers are applied by [`text2words_normalized`](fn.text2words_normalized.html) to each
+/// word after punctuation has been stripped.
+pub trait Normalizer {
+    /// Return the normalised form of a word.
+    fn normalize<'a>(&self, word: &'a str) -> Cow<'a, str>;
+}
+
+/// Snowball stemmer
+///
+/// Words are lower-cased before stemming, as the Snowball algorithms expect.
+pub struct SnowballStemmer {
+    stemmer: Stemmer,
+}
+
+impl SnowballStemmer {
+    /// Create a stemmer for the given language; fails for languages without a Snowball
+    /// algorithm.
+    pub fn new(language: &Language) -> Result<SnowballStemmer> {
+        let algorithm = match language.to_639_3() {
+            "ara" => Algorithm::Arabic,
+            "dan" => Algorithm::Danish,
+            "deu" => Algorithm::German,
+            "ell" => Algorithm::Greek,
+            "eng" => Algorithm::English,
+            "fin" => Algorithm::Finnish,
+            "fra" => Algorithm::French,
+            "hun" => Algorithm::Hungarian,
+            "ita" => Algorithm::Italian,
+            "nld" => Algorithm::Dutch,
+            "nob" | "nno" | "nor" => Algorithm::Norwegian,
+            "por" => Algorithm::Portuguese,
+            "ron" => Algorithm::Romanian,
+            "rus" => Algorithm::Russian,
+            "spa" => Algorithm::Spanish,
+            "swe" => Algorithm::Swedish,
+            "tam" => Algorithm::Tamil,
+            "tur" => Algorithm::Turkish,
+            code => return Err(TransformationError::InvalidLanguageError(code.into(),
+                    "no Snowball stemmer available for this language".into(),
+                    PositionType::None)),
+        };
+        Ok(SnowballStemmer { stemmer: Stemmer::create(algorithm) })
+    }
+}
+
+impl Normalizer for SnowballStemmer {
+    fn normalize<'a>(&self, word: &'a str) -> Cow<'a, str> {
+        Cow::Owned(self.stemmer.stem(&word.to_lowercase()).into_owned())
+    }
+}
+
+/// Dictionary-based lemmatizer
+///
+/// The lemmas are read from a list with a form and its lemma per line, separated by a tab. Words
+/// not found are looked up in lower case (e.g. at the beginning of a sentence) and otherwise left
+/// unchanged. If a form has several lemmas, the first one is used.
+#[derive(Debug, Default)]
+pub struct Lemmatizer {
+    lemmas: HashMap<String, String>,
+}
+
+impl Lemmatizer {
+    /// Read a `form<TAB>lemma` list; empty lines and lines starting with `#` are ignored.
+    pub fn from_reader<R: Read>(input: R) -> Result<Lemmatizer> {
+        let mut lemmas = HashMap::new();
+        for line in BufReader::new(input).lines() {
+            let line = line?;
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut columns = line.split('\t');
+            match (columns.next(), columns.next()) {
+                (Some(form), Some(lemma)) if !form.is_empty() && !lemma.trim().is_empty() => {
+                    lemmas.entry(form.to_string()).or_insert_with(|| lemma.trim().to_string());
+                },
+                _ => return Err(TransformationError::ErrorneousStructure(format!(
+                        "expected form and lemma, separated by a tab, got: {}", line),
+                        PositionType::None)),
+            }
+        }
+        Ok(Lemmatizer { lemmas })
+    }
+
+    /// Read a `form<TAB>lemma` list from a file.
+    pub fn from_file(path: &Path) -> Result<Lemmatizer> {
+        Lemmatizer::from_reader(common::open_file(path)?).map_err(|mut e| {
+            e.inject_position(PositionType::InDirectory(path.to_path_buf()));
+            e
+        })
+    }
+
+    /// Number of known forms.
+    pub fn len(&self) -> usize {
+        self.lemmas.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lemmas.is_empty()
+    }
+}
+
+impl Normalizer for Lemmatizer {
+    fn normalize<'a>(&self, word: &'a str) -> Cow<'a, str> {
+        if let Some(lemma) = self.lemmas.get(word) {
+            return Cow::Owned(lemma.clone());
+        }
+        match self.lemmas.get(&word.to_lowercase()) {
+            Some(lemma) => Cow::Owned(lemma.clone()),
+            None => Cow::Borrowed(word),
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// compound splitting
+
+/// Linking morphemes which may join the parts of a compound, e.g. the s in Rechtsvorschrift.
+pub static LINKING_MORPHEMES: [&str; 4] = ["s", "es", "n", "en"];
+
+/// Maximum number of parts a compound is split into.
+pub static MAX_COMPOUND_PARTS: usize = 4;
+
+// sum of the logarithms of the part counts, and the parts with their counts
+type ScoredSplit = (f64, Vec<(String, u64)>);
+
+/// What to write for a split compound
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CompoundMode {
+    /// write the parts instead of the compound
+    Replace,
+    /// write the compound, followed by its parts
+    Keep,
+}
+
+/// Frequency-based compound splitter (Koehn and Knight, 2003)
+///
+/// The word counts of the corpus are collected in a first pass. A word is split into known words
+/// of at least `min_part_length` characters, optionally joined by a linking morpheme; among all
+/// splits into at most `MAX_COMPOUND_PARTS` parts, including the unsplit word, the one with the
+/// highest geometric mean of the part counts wins. Parts are looked up as written, capitalised
+/// and in lower case, so that the parts of German compounds are found as nouns.
+#[derive(Debug)]
+pub struct CompoundSplitter {
+    counts: HashMap<String, u64>,
+    mode: CompoundMode,
+    /// minimum number of characters of a part
+    pub min_part_length: usize,
+    /// minimum count of a part
+    pub min_count: u64,
+}
+
+impl CompoundSplitter {
+    pub fn new(mode: CompoundMode) -> CompoundSplitter {
+        CompoundSplitter { counts: HashMap::new(), mode, min_part_length: 3, min_count: 1 }
+    }
+
+    /// Count the words of a text (as returned by `text2words`).
+    pub fn add_text(&mut self, text: &str) {
+        for word in text.split_whitespace() {
+            match self.counts.get_mut(word) {
+                Some(count) => *count += 1,
+                None => {
+                    self.counts.insert(word.to_string(), 1);
+                },
+            }
+        }
+    }
+
+    // the most frequent spelling of a part and its count
+    fn lookup(&self, part: &str) -> Option<(String, u64)> {
+        let mut capitalised: String = part.chars().take(1).flat_map(|c| c.to_uppercase())
+            .collect();
+        capitalised.push_str(&part[part.chars().next().map(|c| c.len_utf8()).unwrap_or(0)..]);
+        let variants = [part.to_string(), capitalised, part.to_lowercase()];
+        variants.iter().filter_map(|v| self.counts.get(v).map(|&count| (v.clone(), count)))
+            .filter(|&(_, count)| count >= self.min_count)
+            .max_by_key(|&(_, count)| count)
+    }
+
+    // The best splits of a word into one to `MAX_COMPOUND_PARTS` known parts, indexed by the
+    // number of parts minus one. For a given number of parts, the split with the highest sum of
+    // the logarithms of the part counts has the highest geometric mean; the best splits of each
+    // suffix are computed from the end of the word.
+    fn splits(&self, word: &str) -> Vec<Option<ScoredSplit>> {
+        let mut best: Vec<Vec<Option<ScoredSplit>>> = (0..word.len() + 1)
+            .map(|_| vec![None; MAX_COMPOUND_PARTS]).collect();
+        let starts: Vec<usize> = word.char_indices().map(|(i, _)| i).collect();
+        for &start in starts.iter().rev() {
+            let rest = &word[start..];
+            if rest.chars().count() < self.min_part_length {
+                continue;
+            }
+            let ends = rest.char_indices().map(|(i, _)| start + i).skip(self.min_part_length);
+            for end in ends.chain(Some(word.len())) {
+                let part = match self.lookup(&word[start..end]) {
+                    Some(part) => part,
+                    None => continue,
+                };
+                let score = (part.1 as f64).ln();
+                let mut candidates = Vec::new();
+                if end == word.len() {
+                    candidates.push((0, score, vec![part]));
+                } else {
+                    for link in Some("").iter().chain(LINKING_MORPHEMES.iter()) {
+                        if !word[end..].starts_with(link) {
+                            continue;
+                        }
+                        let tails = best[end + link.len()].iter().enumerate()
+                            .take(MAX_COMPOUND_PARTS - 1);
+                        for (parts, tail) in tails {
+                            if let Some((tail_score, ref tail)) = *tail {
+                                let mut split = vec![part.clone()];
+                                split.extend(tail.iter().cloned());
+                                candidates.push((parts + 1, score + tail_score, split));
+                            }
+                        }
+                    }
                 }
+                // the first of equally good splits is kept
+                for (parts, score, split) in candidates {
+                    let better = match best[start][parts] {
+                        Some((best_score, _)) => score > best_score,
+                        None => true,
+                    };
+                    if better {
+                        best[start][parts] = Some((score, split));
+                    }
+                }
+            }
+        }
+        best.swap_remove(0)
+    }
 
-                words.push_str(word.as_str());
+    /// Split a compound into its parts; `None` if the word is better left as it is.
+    pub fn split(&self, word: &str) -> Option<Vec<String>> {
+        if word.chars().count() < 2 * self.min_part_length
+                || !word.chars().all(|c| c.is_alphabetic()) {
+            return None;
+        }
+        let unsplit = self.counts.get(word).cloned().unwrap_or(0) as f64;
+        let mut best: Option<ScoredSplit> = None;
+        // fewer parts are preferred if the scores are equal
+        for (parts, split) in self.splits(word).into_iter().enumerate().skip(1) {
+            let (score, split) = match split {
+                Some(split) => split,
+                None => continue,
+            };
+            let score = (score / (parts + 1) as f64).exp();
+            let better = match best {
+                Some((best_score, _)) => score > best_score,
+                None => true,
+            };
+            if better {
+                best = Some((score, split));
             }
         }
+        match best {
+            Some((score, split)) if score > unsplit =>
+                Some(split.into_iter().map(|(part, _)| part).collect()),
+            _ => None,
+        }
+    }
+}
+
+impl Normalizer for CompoundSplitter {
+    fn normalize<'a>(&self, word: &'a str) -> Cow<'a, str> {
+        match (self.split(word), self.mode) {
+            (Some(parts), CompoundMode::Replace) => Cow::Owned(parts.join(" ")),
+            (Some(parts), CompoundMode::Keep) => Cow::Owned(format!("{} {}", word,
+                    parts.join(" "))),
+            (None, _) => Cow::Borrowed(word),
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// word segmentation
+
+// whether a character belongs to a script written without spaces between words
+fn is_scriptio_continua(c: char) -> bool {
+    match c as u32 {
+        0x0E00..=0x0EFF // Thai, Lao
+            | 0x0F00..=0x0FFF // Tibetan
+            | 0x1000..=0x109F // Myanmar
+            | 0x1780..=0x17FF // Khmer
+            | 0x3040..=0x30FF // Hiragana, Katakana
+            | 0x3400..=0x4DBF // CJK Unified Ideographs Extension A
+            | 0x4E00..=0x9FFF // CJK Unified Ideographs
+            | 0xF900..=0xFAFF // CJK Compatibility Ideographs
+            | 0x20000..=0x2FA1F => true, // further CJK extensions
+        _ => false,
+    }
+}
+
+/// Word list for the segmentation of scripts without spaces
+///
+/// The list has one word per line; further columns, separated by white space (e.g. the
+/// frequencies of a jieba dictionary), are ignored.
+#[derive(Debug, Default)]
+pub struct Lexicon {
+    words: HashSet<String>,
+    /// length of the longest word, in characters
+    max_length: usize,
+}
+
+impl Lexicon {
+    pub fn from_reader<R: Read>(input: R) -> Result<Lexicon> {
+        let mut lexicon = Lexicon::default();
+        for line in BufReader::new(input).lines() {
+            if let Some(word) = line?.split_whitespace().next() {
+                lexicon.insert(word);
+            }
+        }
+        Ok(lexicon)
+    }
+
+    pub fn from_file(path: &Path) -> Result<Lexicon> {
+        Lexicon::from_reader(common::open_file(path)?).map_err(|mut e| {
+            e.inject_position(PositionType::InDirectory(path.to_path_buf()));
+            e
+        })
     }
 
-    if !words.is_empty() && !words.ends_with('\n') {
-        words.push('\n')
+    pub fn insert(&mut self, word: &str) {
+        self.max_length = self.max_length.max(word.chars().count());
+        self.words.insert(word.to_string());
+    }
+
+    pub fn len(&self) -> usize {
+        self.words.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.words.is_empty()
+    }
+
+    // split a run of characters by forward maximum matching; words end at grapheme cluster
+    // boundaries and unknown grapheme clusters, e.g. a Thai consonant with its vowel and tone
+    // marks, become words of their own
+    fn max_match<'a>(&self, run: &'a str, words: &mut Vec<&'a str>) {
+        let mut rest = run;
+        while !rest.is_empty() {
+            let ends: Vec<usize> = rest.grapheme_indices(true).map(|(i, _)| i).skip(1)
+                .chain(Some(rest.len())).take(self.max_length.max(1)).collect();
+            let end = ends.iter().rev().find(|&&end| self.words.contains(&rest[..end])).cloned()
+                .unwrap_or(ends[0]);
+            words.push(&rest[..end]);
+            rest = &rest[end..];
+        }
     }
-    words
 }
 
+/// How text is split into words
+#[derive(Debug)]
+pub enum Segmenter {
+    /// split at white space only, the default for languages which separate words by spaces
+    Whitespace,
+    /// split at white space and at the Unicode word boundaries (UAX #29); ideographs and Thai
+    /// letters become single-character words
+    Unicode,
+    /// like `Unicode`, but runs of ideographs, kana and Thai (or similar) letters are segmented
+    /// by maximum matching against a lexicon
+    Dictionary(Lexicon),
+}
+
+impl Segmenter {
+    /// Choose the segmenter for the script of a language: languages written without spaces are
+    /// segmented with the lexicon, if given, or at the Unicode word boundaries.
+    pub fn for_language(language: &Language, lexicon: Option<Lexicon>) -> Segmenter {
+        match language.to_639_3() {
+            "zho" | "cmn" | "yue" | "wuu" | "lzh" | "jpn" | "tha" | "lao" | "khm" | "mya"
+                    | "bod" => match lexicon {
+                Some(lexicon) => Segmenter::Dictionary(lexicon),
+                None => Segmenter::Unicode,
+            },
+            _ => Segmenter::Whitespace,
+        }
+    }
+
+    /// Iterate over the words of a text like [`segment`](#method.segment); text separated by
+    /// white space only is not collected into a vector.
+    pub fn words<'a>(&'a self, text: &'a str) -> Box<Iterator<Item=&'a str> + 'a> {
+        match *self {
+            Segmenter::Whitespace => Box::new(text.split_whitespace()),
+            _ => Box::new(self.segment(text).into_iter()),
+        }
+    }
+
+    /// Split a text into words; the `RETURN_ESCAPE_SEQUENCE` is kept as a word of its own.
+    /// Punctuation may be returned as separate words.
+    pub fn segment<'a>(&self, text: &'a str) -> Vec<&'a str> {
+        let lexicon = match *self {
+            Segmenter::Whitespace => return text.split_whitespace().collect(),
+            Segmenter::Unicode => None,
+            Segmenter::Dictionary(ref lexicon) => Some(lexicon),
+        };
+        let mut words = Vec::new();
+        for chunk in text.split_whitespace() {
+            if chunk.len() == 1 && chunk.starts_with(RETURN_ESCAPE_SEQUENCE) {
+                words.push(chunk);
+                continue;
+            }
+            let lexicon = match lexicon {
+                Some(lexicon) => lexicon,
+                None => {
+                    words.extend(chunk.split_word_bounds());
+                    continue;
+                },
+            };
+            // runs of characters of scripts with and without spaces are segmented separately;
+            // a space marks the end of the chunk
+            let mut start = 0;
+            let mut continua = None;
+            for (index, c) in chunk.char_indices().chain(Some((chunk.len(), ' '))) {
+                let current = is_scriptio_continua(c);
+                if continua == Some(current) && index < chunk.len() {
+                    continue;
+                }
+                let run = &chunk[start..index];
+                match continua {
+                    Some(true) => lexicon.max_match(run, &mut words),
+                    _ => words.extend(run.split_word_bounds()),
+                }
+                start = index;
+                continua = Some(current);
+            }
+        }
+        words
+    }
+}
@@ -0,0 +1,316 @@
+//! Thesaurus generation from word vectors
+//!
+//! The most similar words of a word, measured by the cosine similarity of their vectors, are
+//! candidates for synonyms. Comparing each word with all others is quadratic in the size of the
+//! vocabulary, so the vectors are stored in an approximate nearest-neighbour index, a
+//! Hierarchical Navigable Small World graph (HNSW, Malkov and Yashunin, 2016): the vectors are the
+//! nodes of a hierarchy of proximity graphs, which become sparser towards the top. A search
+//! starts at the top layer and greedily descends to the closest nodes.
+//!
+//! For each headword, the `k` most similar words above a similarity threshold are written,
+//! either as tab-separated values or as TEI dictionary entries with cross-references (`<xr
+//! type="syn">`), which can be merged into a FreeDict dictionary.
+
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, HashSet};
+use std::io::{self, Write};
+
+use word2vec::Model;
+
+/// Parameters of the HNSW index.
+#[derive(Clone, Debug)]
+pub struct IndexConfig {
+    /// number of neighbours of a node per layer (twice as many on the lowest layer)
+    pub connections: usize,
+    /// number of candidates considered while inserting a node
+    pub ef_construction: usize,
+    /// number of candidates considered while searching, at least the number of results
+    pub ef_search: usize,
+    /// seed for the random layer assignment
+    pub seed: u64,
+}
+
+impl Default for IndexConfig {
+    fn default() -> IndexConfig {
+        IndexConfig { connections: 16, ef_construction: 100, ef_search: 64, seed: 1 }
+    }
+}
+
+// a node and its distance to the query, ordered by distance
+#[derive(Clone, Copy, PartialEq)]
+struct Candidate {
+    distance: f32,
+    node: usize,
+}
+
+impl Eq for Candidate {}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Candidate) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Candidate) -> Ordering {
+        self.distance.partial_cmp(&other.distance).unwrap_or(Ordering::Equal)
+            .then_with(|| self.node.cmp(&other.node))
+    }
+}
+
+/// Approximate nearest-neighbour index over normalised vectors
+pub struct Index {
+    config: IndexConfig,
+    dimensions: usize,
+    /// normalised vectors, one after another
+    vectors: Vec<f32>,
+    /// neighbours of each node, per layer from the lowest one up to the layer of the node
+    neighbours: Vec<Vec<Vec<usize>>>,
+    entry_point: Option<usize>,
+    random: u64,
+}
+
+impl Index {
+    /// Build an index over all vectors of a model.
+    pub fn build(model: &Model, config: IndexConfig) -> Index {
+        let mut index = Index { random: config.seed.max(1), config,
+            dimensions: model.dimensions, vectors: Vec::with_capacity(model.vectors.len()),
+            neighbours: Vec::new(), entry_point: None };
+        for vector in model.vectors.chunks(model.dimensions) {
+            index.insert(vector);
+        }
+        index
+    }
+
+    /// Number of vectors in the index.
+    pub fn len(&self) -> usize {
+        self.neighbours.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.neighbours.is_empty()
+    }
+
+    fn vector(&self, node: usize) -> &[f32] {
+        &self.vectors[node * self.dimensions..(node + 1) * self.dimensions]
+    }
+
+    // cosine distance between a normalised query and a node
+    fn distance(&self, query: &[f32], node: usize) -> f32 {
+        1.0 - query.iter().zip(self.vector(node)).map(|(a, b)| a * b).sum::<f32>()
+    }
+
+    // random layer, exponentially decaying with the number of connections
+    fn random_level(&mut self) -> usize {
+        self.random ^= self.random << 13;
+        self.random ^= self.random >> 7;
+        self.random ^= self.random << 17;
+        let uniform = ((self.random >> 11) as f64 + 1.0) / (1u64 << 53) as f64;
+        (-uniform.ln() / (self.config.connections.max(2) as f64).ln()) as usize
+    }
+
+    fn max_connections(&self, layer: usize) -> usize {
+        match layer {
+            0 => self.config.connections * 2,
+            _ => self.config.connections,
+        }
+    }
+
+    // the `ef` nodes closest to the query on one layer, found from the entry points; sorted by
+    // distance
+    fn search_layer(&self, query: &[f32], entry_points: &[usize], ef: usize, layer: usize)
+            -> Vec<Candidate> {
+        let mut visited: HashSet<usize> = entry_points.iter().cloned().collect();
+        // closest candidate first
+        let mut candidates: BinaryHeap<Reverse<Candidate>> = BinaryHeap::new();
+        // furthest result first
+        let mut results: BinaryHeap<Candidate> = BinaryHeap::new();
+        for &node in entry_points {
+            let candidate = Candidate { distance: self.distance(query, node), node };
+            candidates.push(Reverse(candidate));
+            results.push(candidate);
+        }
+        while let Some(Reverse(closest)) = candidates.pop() {
+            let furthest = results.peek().map(|c| c.distance).unwrap_or(f32::MAX);
+            if closest.distance > furthest && results.len() >= ef {
+                break;
+            }
+            for &neighbour in &self.neighbours[closest.node][layer] {
+                if !visited.insert(neighbour) {
+                    continue;
+                }
+                let distance = self.distance(query, neighbour);
+                let furthest = results.peek().map(|c| c.distance).unwrap_or(f32::MAX);
+                if results.len() < ef || distance < furthest {
+                    let candidate = Candidate { distance, node: neighbour };
+                    candidates.push(Reverse(candidate));
+                    results.push(candidate);
+                    if results.len() > ef {
+                        results.pop();
+                    }
+                }
+            }
+        }
+        results.into_sorted_vec()
+    }
+
+    // descend greedily from the entry point to the given layer
+    fn descend(&self, query: &[f32], entry_point: usize, top: usize, layer: usize) -> usize {
+        let mut current = entry_point;
+        for l in (layer + 1..=top).rev() {
+            current = self.search_layer(query, &[current], 1, l)[0].node;
+        }
+        current
+    }
+
+    fn insert(&mut self, vector: &[f32]) {
+        let norm = vector.iter().map(|v| v * v).sum::<f32>().sqrt();
+        let norm = match norm > 0.0 {
+            true => norm,
+            false => 1.0,
+        };
+        let node = self.neighbours.len();
+        self.vectors.extend(vector.iter().map(|v| v / norm));
+        let level = self.random_level();
+        self.neighbours.push(vec![Vec::new(); level + 1]);
+        let entry_point = match self.entry_point {
+            Some(entry_point) => entry_point,
+            None => {
+                self.entry_point = Some(node);
+                return;
+            },
+        };
+        let query = self.vector(node).to_vec();
+        let top = self.neighbours[entry_point].len() - 1;
+        let mut entry_points = vec![self.descend(&query, entry_point, top, level)];
+        for layer in (0..=level.min(top)).rev() {
+            let found = self.search_layer(&query, &entry_points, self.config.ef_construction,
+                    layer);
+            let selected: Vec<usize> = found.iter().take(self.config.connections)
+                .map(|c| c.node).collect();
+            for &neighbour in &selected {
+                self.neighbours[neighbour][layer].push(node);
+                self.shrink(neighbour, layer);
+            }
+            self.neighbours[node][layer] = selected;
+            entry_points = found.into_iter().map(|c| c.node).collect();
+        }
+        if level > top {
+            self.entry_point = Some(node);
+        }
+    }
+
+    // keep the closest neighbours of a node on a layer, if it has too many
+    fn shrink(&mut self, node: usize, layer: usize) {
+        let max = self.max_connections(layer);
+        if self.neighbours[node][layer].len() <= max {
+            return;
+        }
+        let query = self.vector(node).to_vec();
+        let mut candidates: Vec<Candidate> = self.neighbours[node][layer].iter()
+            .map(|&n| Candidate { distance: self.distance(&query, n), node: n }).collect();
+        candidates.sort();
+        self.neighbours[node][layer] = candidates.into_iter().take(max).map(|c| c.node)
+            .collect();
+    }
+
+    /// The `k` nodes most similar to the given vector, with their cosine similarity, most
+    /// similar first.
+    pub fn search(&self, vector: &[f32], k: usize) -> Vec<(usize, f32)> {
+        let entry_point = match self.entry_point {
+            Some(entry_point) => entry_point,
+            None => return Vec::new(),
+        };
+        let norm = vector.iter().map(|v| v * v).sum::<f32>().sqrt();
+        if norm == 0.0 {
+            return Vec::new();
+        }
+        let query: Vec<f32> = vector.iter().map(|v| v / norm).collect();
+        let top = self.neighbours[entry_point].len() - 1;
+        let closest = self.descend(&query, entry_point, top, 0);
+        self.search_layer(&query, &[closest], self.config.ef_search.max(k), 0).into_iter()
+            .take(k).map(|c| (c.node, 1.0 - c.distance)).collect()
+    }
+}
+
+/// A word similar to a headword.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Neighbour {
+    pub word: String,
+    /// cosine similarity
+    pub similarity: f32,
+}
+
+/// Options for the generation of a thesaurus.
+#[derive(Clone, Debug)]
+pub struct ThesaurusConfig {
+    /// maximum number of similar words per headword
+    pub top: usize,
+    /// minimum cosine similarity of a similar word
+    pub min_similarity: f32,
+    /// if given, only these words become headwords
+    pub allowlist: Option<HashSet<String>>,
+}
+
+impl Default for ThesaurusConfig {
+    fn default() -> ThesaurusConfig {
+        ThesaurusConfig { top: 10, min_similarity: 0.5, allowlist: None }
+    }
+}
+
+/// Find the most similar words for each word of the model (or of the allowlist).
+///
+/// The headwords are returned in the order of the model, i.e. most frequent first.
+pub fn build_thesaurus(model: &Model, index: &Index, config: &ThesaurusConfig)
+        -> Vec<(String, Vec<Neighbour>)> {
+    let mut thesaurus = Vec::new();
+    for (node, word) in model.words.iter().enumerate() {
+        if let Some(ref allowlist) = config.allowlist {
+            if !allowlist.contains(word) {
+                continue;
+            }
+        }
+        let vector = &model.vectors[node * model.dimensions..(node + 1) * model.dimensions];
+        // the word itself is usually the first result
+        let neighbours: Vec<Neighbour> = index.search(vector, config.top + 1).into_iter()
+            .filter(|&(n, similarity)| n != node && similarity >= config.min_similarity)
+            .take(config.top)
+            .map(|(n, similarity)| Neighbour { word: model.words[n].clone(), similarity })
+            .collect();
+        if !neighbours.is_empty() {
+            thesaurus.push((word.clone(), neighbours));
+        }
+    }
+    thesaurus
+}
+
+/// Write a thesaurus as tab-separated values: headword, similar word and similarity.
+pub fn write_tsv<W: Write>(thesaurus: &[(String, Vec<Neighbour>)], output: &mut W)
+        -> io::Result<()> {
+    for (headword, neighbours) in thesaurus {
+        for neighbour in neighbours {
+            writeln!(output, "{}\t{}\t{:.4}", headword, neighbour.word, neighbour.similarity)?;
+        }
+    }
+    Ok(())
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// Write a thesaurus as TEI entries, with the similar words as synonym cross-references and
+/// their similarity as certainty.
+pub fn write_tei<W: Write>(thesaurus: &[(String, Vec<Neighbour>)], output: &mut W)
+        -> io::Result<()> {
+    for (headword, neighbours) in thesaurus {
+        writeln!(output, "<entry>\n  <form><orth>{}</orth></form>\n  <sense>",
+                 escape_xml(headword))?;
+        for neighbour in neighbours {
+            writeln!(output, "    <xr type=\"syn\"><ref cert=\"{:.4}\">{}</ref></xr>",
+                     neighbour.similarity, escape_xml(&neighbour.word))?;
+        }
+        writeln!(output, "  </sense>\n</entry>")?;
+    }
+    Ok(())
+}
@@ -0,0 +1,589 @@
+//! Word2vec training
+//!
+//! A CPU-only reimplementation of the original word2vec tool, so that the corpus written by
+//! crafted can be turned into word vectors without further software. Both architectures are
+//! supported, the continuous skip-gram and the continuous bag-of-words (CBOW) model, trained with
+//! negative sampling. Frequent words are subsampled and rare words below a minimum count are
+//! dropped.
+//!
+//! Each line of the corpus is a context; windows never span lines. Training runs on several
+//! threads, each reading its own part of the corpus file. As in the reference implementation, the
+//! threads update the shared weights without locking ("Hogwild!"); conflicting updates are rare
+//! and do not harm convergence. The weights are stored as atomic integers, read and written with
+//! relaxed ordering, so a conflicting update may be lost, but never tears a value.
+//!
+//! The vectors can be written in the text and binary formats of the word2vec tool, which most
+//! other tools read as well.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+use input_source::{PositionType, Result, TransformationError};
+
+// size of the table from which negative samples are drawn, proportional to count^0.75
+static UNIGRAM_TABLE_SIZE: usize = 10_000_000;
+// the sigmoid function is precomputed for -MAX_EXP..MAX_EXP
+static EXP_TABLE_SIZE: usize = 1000;
+static MAX_EXP: f32 = 6.0;
+// longer lines are split
+static MAX_SENTENCE_LENGTH: usize = 1000;
+
+/// The model architecture.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Architecture {
+    /// predict the context words from a word
+    SkipGram,
+    /// predict a word from the average of its context words
+    Cbow,
+}
+
+/// Training parameters; the defaults are those of the word2vec tool.
+#[derive(Clone, Debug)]
+pub struct Config {
+    pub architecture: Architecture,
+    /// dimensions of the word vectors
+    pub dimensions: usize,
+    /// maximum distance between a word and its context words
+    pub window: usize,
+    /// number of negative samples per word
+    pub negative: usize,
+    /// threshold for subsampling frequent words, 0 to disable
+    pub sample: f32,
+    /// words occurring less often are dropped
+    pub min_count: u64,
+    /// initial learning rate, if not set 0.025 for skip-gram and 0.05 for CBOW
+    pub alpha: Option<f32>,
+    /// number of passes over the corpus
+    pub iterations: usize,
+    pub threads: usize,
+    /// seed of the random number generator
+    pub seed: u64,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            architecture: Architecture::SkipGram,
+            dimensions: 100,
+            window: 5,
+            negative: 5,
+            sample: 1e-3,
+            min_count: 5,
+            alpha: None,
+            iterations: 5,
+            threads: 4,
+            seed: 1,
+        }
+    }
+}
+
+impl Config {
+    fn starting_alpha(&self) -> f32 {
+        self.alpha.unwrap_or(match self.architecture {
+            Architecture::SkipGram => 0.025,
+            Architecture::Cbow => 0.05,
+        })
+    }
+}
+
+/// Words of a corpus with their counts, most frequent first.
+#[derive(Debug, Default)]
+pub struct Vocabulary {
+    words: Vec<String>,
+    counts: Vec<u64>,
+    index: HashMap<String, usize>,
+}
+
+impl Vocabulary {
+    /// Count the words of a corpus file, dropping those occurring less than `min_count` times.
+    pub fn from_corpus(path: &Path, min_count: u64) -> Result<Vocabulary> {
+        let mut counts: HashMap<String, u64> = HashMap::new();
+        for line in BufReader::new(File::open(path)?).lines() {
+            for word in line?.split_whitespace() {
+                if let Some(count) = counts.get_mut(word) {
+                    *count += 1;
+                    continue;
+                }
+                counts.insert(word.to_string(), 1);
+            }
+        }
+        Ok(Vocabulary::from_counts(counts.into_iter(), min_count))
+    }
+
+    /// Build a vocabulary from word counts, dropping words occurring less than `min_count` times.
+    pub fn from_counts<I: Iterator<Item=(String, u64)>>(counts: I, min_count: u64)
+            -> Vocabulary {
+        let mut counts: Vec<(String, u64)> = counts.filter(|&(_, c)| c >= min_count).collect();
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        let index = counts.iter().enumerate().map(|(i, entry)| (entry.0.clone(), i)).collect();
+        let (words, counts) = counts.into_iter().unzip();
+        Vocabulary { words, counts, index }
+    }
+
+    pub fn len(&self) -> usize {
+        self.words.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.words.is_empty()
+    }
+
+    /// Index of a word, if it is part of the vocabulary.
+    pub fn index(&self, word: &str) -> Option<usize> {
+        self.index.get(word).cloned()
+    }
+
+    pub fn word(&self, index: usize) -> &str {
+        &self.words[index]
+    }
+
+    pub fn count(&self, index: usize) -> u64 {
+        self.counts[index]
+    }
+
+    /// Number of occurrences of all words of the vocabulary.
+    pub fn total(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+}
+
+/// Trained word vectors
+#[derive(Debug)]
+pub struct Model {
+    pub words: Vec<String>,
+    pub dimensions: usize,
+    /// vectors of all words, one after another
+    pub vectors: Vec<f32>,
+}
+
+impl Model {
+    /// Vector of a word, if the word is known.
+    pub fn vector(&self, word: &str) -> Option<&[f32]> {
+        let index = get!(self.words.iter().position(|w| w == word));
+        Some(&self.vectors[index * self.dimensions..(index + 1) * self.dimensions])
+    }
+
+    /// Write the vectors in the text format: a header line with the number of words and the
+    /// dimensions, followed by one line per word with the word and its vector components.
+    pub fn write_text<W: Write>(&self, output: &mut W) -> io::Result<()> {
+        writeln!(output, "{} {}", self.words.len(), self.dimensions)?;
+        for (word, vector) in self.words.iter().zip(self.vectors.chunks(self.dimensions)) {
+            write!(output, "{}", word)?;
+            for component in vector {
+                write!(output, " {:.6}", component)?;
+            }
+            writeln!(output)?;
+        }
+        Ok(())
+    }
+
+    /// Write the vectors in the binary format: a header line as in the text format, followed by
+    /// the word, a space and the vector as little-endian 32 bit floats for each word.
+    pub fn write_binary<W: Write>(&self, output: &mut W) -> io::Result<()> {
+        writeln!(output, "{} {}", self.words.len(), self.dimensions)?;
+        for (word, vector) in self.words.iter().zip(self.vectors.chunks(self.dimensions)) {
+            write!(output, "{} ", word)?;
+            for component in vector {
+                output.write_all(&float_to_bytes(*component))?;
+            }
+            writeln!(output)?;
+        }
+        Ok(())
+    }
+
+    /// Read vectors in the text format, see [`write_text`](#method.write_text).
+    pub fn read_text<R: BufRead>(input: R) -> Result<Model> {
+        let mut lines = input.lines();
+        let (count, dimensions) = match lines.next() {
+            Some(header) => parse_header(&header?)?,
+            None => return Err(malformed("empty vector file".into())),
+        };
+        // the header is not trusted, the vectors grow as they are read
+        let mut words = Vec::new();
+        let mut vectors = Vec::new();
+        for line in lines {
+            let line = line?;
+            let mut fields = line.split_whitespace();
+            let word = match fields.next() {
+                Some(word) => word,
+                None => continue,
+            };
+            let start = vectors.len();
+            for field in fields {
+                vectors.push(field.parse::<f32>().map_err(|_| malformed(format!(
+                        "invalid vector component of {}: {}", word, field)))?);
+            }
+            if vectors.len() - start != dimensions {
+                return Err(malformed(format!("vector of {} has {} instead of {} dimensions",
+                        word, vectors.len() - start, dimensions)));
+            }
+            words.push(word.to_string());
+        }
+        if words.len() != count {
+            return Err(malformed(format!("expected {} vectors, found {}", count, words.len())));
+        }
+        Ok(Model { words, dimensions, vectors })
+    }
+
+    /// Read vectors in the binary format, see [`write_binary`](#method.write_binary).
+    pub fn read_binary<R: BufRead>(mut input: R) -> Result<Model> {
+        let mut header = String::new();
+        input.read_line(&mut header)?;
+        let (count, dimensions) = parse_header(&header)?;
+        let vector_size = dimensions.checked_mul(4).ok_or_else(|| malformed(format!(
+                "invalid number of dimensions: {}", dimensions)))?;
+        // the header is not trusted, the vectors grow as they are read
+        let mut words = Vec::new();
+        let mut vectors = Vec::new();
+        let mut bytes = Vec::new();
+        for _ in 0..count {
+            let mut word = Vec::new();
+            input.read_until(b' ', &mut word)?;
+            if word.pop() != Some(b' ') {
+                return Err(malformed(format!("expected {} vectors, found {}", count,
+                        words.len())));
+            }
+            bytes.clear();
+            (&mut input).take(vector_size as u64).read_to_end(&mut bytes)?;
+            if bytes.len() != vector_size {
+                return Err(malformed(format!("vector of {} is incomplete",
+                        String::from_utf8_lossy(&word))));
+            }
+            vectors.extend(bytes.chunks(4).map(float_from_bytes));
+            // the line break after the previous vector is optional
+            words.push(String::from_utf8_lossy(&word).trim_start_matches('\n').to_string());
+        }
+        Ok(Model { words, dimensions, vectors })
+    }
+
+    /// Read vectors from a file, in the binary format if the file name ends on `.bin`, otherwise
+    /// in the text format.
+    pub fn from_file(path: &Path) -> Result<Model> {
+        let input = BufReader::new(File::open(path)?);
+        let model = match path.extension().map(|e| e == "bin").unwrap_or(false) {
+            true => Model::read_binary(input),
+            false => Model::read_text(input),
+        };
+        model.map_err(|mut e| {
+            e.inject_position(PositionType::InDirectory(path.to_path_buf()));
+            e
+        })
+    }
+}
+
+fn malformed(message: String) -> TransformationError {
+    TransformationError::ErrorneousStructure(message, PositionType::None)
+}
+
+// parse the header line with the number of words and the (positive) dimensions
+fn parse_header(header: &str) -> Result<(usize, usize)> {
+    let numbers: Vec<usize> = header.split_whitespace().filter_map(|n| n.parse().ok())
+        .collect();
+    match numbers.len() {
+        2 if numbers[1] > 0 && numbers[0].checked_mul(numbers[1]).is_some() =>
+            Ok((numbers[0], numbers[1])),
+        _ => Err(malformed(format!("invalid header of vector file: {}", header.trim()))),
+    }
+}
+
+fn float_to_bytes(value: f32) -> [u8; 4] {
+    let bits = value.to_bits();
+    [bits as u8, (bits >> 8) as u8, (bits >> 16) as u8, (bits >> 24) as u8]
+}
+
+fn float_from_bytes(bytes: &[u8]) -> f32 {
+    f32::from_bits(bytes.iter().rev().fold(0u32, |bits, &b| (bits << 8) | b as u32))
+}
+
+// linear congruential generator of the reference implementation
+#[derive(Clone, Copy)]
+struct Random(u64);
+
+impl Random {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_mul(25_214_903_917).wrapping_add(11);
+        self.0
+    }
+
+    // uniformly distributed in [0, 1)
+    fn next_float(&mut self) -> f32 {
+        (self.next() & 0xFFFF) as f32 / 65536.0
+    }
+}
+
+// table of word indices, each word occurring proportionally to count^0.75
+fn unigram_table(vocabulary: &Vocabulary) -> Vec<usize> {
+    let size = UNIGRAM_TABLE_SIZE.min(vocabulary.len() * 1000).max(1);
+    let power = 0.75;
+    let total: f64 = vocabulary.counts.iter().map(|&c| (c as f64).powf(power)).sum();
+    let mut table = Vec::with_capacity(size);
+    let mut word = 0;
+    let mut cumulated = (vocabulary.counts[0] as f64).powf(power) / total;
+    for position in 0..size {
+        table.push(word);
+        if position as f64 / size as f64 > cumulated && word + 1 < vocabulary.len() {
+            word += 1;
+            cumulated += (vocabulary.counts[word] as f64).powf(power) / total;
+        }
+    }
+    table
+}
+
+fn sigmoid_table() -> Vec<f32> {
+    (0..EXP_TABLE_SIZE).map(|i| {
+        let exp = ((i as f32 / EXP_TABLE_SIZE as f32 * 2.0 - 1.0) * MAX_EXP).exp();
+        exp / (exp + 1.0)
+    }).collect()
+}
+
+// Weights shared by the training threads, the bits of each f32 in an atomic integer. The threads
+// update them without synchronisation, see the module documentation.
+struct SharedWeights {
+    input: Vec<AtomicU32>,
+    output: Vec<AtomicU32>,
+}
+
+#[inline]
+fn load(weight: &AtomicU32) -> f32 {
+    f32::from_bits(weight.load(Ordering::Relaxed))
+}
+
+#[inline]
+fn store(weight: &AtomicU32, value: f32) {
+    weight.store(value.to_bits(), Ordering::Relaxed);
+}
+
+// state shared by all training threads
+struct Training {
+    config: Config,
+    corpus: PathBuf,
+    corpus_size: u64,
+    vocabulary: Vocabulary,
+    table: Vec<usize>,
+    sigmoid: Vec<f32>,
+    weights: SharedWeights,
+    /// words processed by all threads, for the decay of the learning rate
+    words_processed: AtomicUsize,
+}
+
+impl Training {
+    fn sigmoid(&self, x: f32) -> Option<f32> {
+        match x > -MAX_EXP && x < MAX_EXP {
+            true => Some(self.sigmoid[((x + MAX_EXP) * (EXP_TABLE_SIZE as f32 / MAX_EXP / 2.0))
+                    as usize]),
+            false => None,
+        }
+    }
+
+    // gradient of one (word, target) pair with the given label, accumulated in `error`
+    fn train_pair(&self, hidden: &[f32], target: usize, label: f32, alpha: f32,
+            error: &mut [f32]) {
+        let dimensions = self.config.dimensions;
+        let output = &self.weights.output[target * dimensions..(target + 1) * dimensions];
+        let dot: f32 = hidden.iter().zip(output).map(|(h, o)| h * load(o)).sum();
+        let gradient = match self.sigmoid(dot) {
+            Some(prediction) => (label - prediction) * alpha,
+            None if dot > 0.0 => (label - 1.0) * alpha,
+            None => label * alpha,
+        };
+        for i in 0..dimensions {
+            let weight = load(&output[i]);
+            error[i] += gradient * weight;
+            store(&output[i], weight + gradient * hidden[i]);
+        }
+    }
+
+    // train the prediction of `word` from `hidden` with negative sampling
+    fn negative_sampling(&self, hidden: &[f32], word: usize, alpha: f32, random: &mut Random,
+            error: &mut [f32]) {
+        self.train_pair(hidden, word, 1.0, alpha, error);
+        for _ in 0..self.config.negative {
+            let target = self.table[(random.next() >> 16) as usize % self.table.len()];
+            if target != word {
+                self.train_pair(hidden, target, 0.0, alpha, error);
+            }
+        }
+    }
+
+    fn train_sentence(&self, sentence: &[usize], alpha: f32, random: &mut Random) {
+        let dimensions = self.config.dimensions;
+        let input = &self.weights.input;
+        let mut hidden = vec![0.0f32; dimensions];
+        let mut error = vec![0.0f32; dimensions];
+        for (position, &word) in sentence.iter().enumerate() {
+            // the effective window is sampled between 1 and `window`
+            let reduced = (random.next() % self.config.window as u64) as usize;
+            let start = (position + reduced).saturating_sub(self.config.window);
+            let end = (position + self.config.window + 1 - reduced).min(sentence.len());
+            let context = (start..end).filter(|&c| c != position).map(|c| sentence[c]);
+            match self.config.architecture {
+                Architecture::Cbow => {
+                    for value in hidden.iter_mut().chain(error.iter_mut()) {
+                        *value = 0.0;
+                    }
+                    let mut context_size = 0;
+                    for c in context.clone() {
+                        for (h, i) in hidden.iter_mut().zip(&input[c * dimensions..]) {
+                            *h += load(i);
+                        }
+                        context_size += 1;
+                    }
+                    if context_size == 0 {
+                        continue;
+                    }
+                    for h in hidden.iter_mut() {
+                        *h /= context_size as f32;
+                    }
+                    self.negative_sampling(&hidden, word, alpha, random, &mut error);
+                    for c in context {
+                        for (i, e) in input[c * dimensions..(c + 1) * dimensions].iter()
+                                .zip(&error) {
+                            store(i, load(i) + *e);
+                        }
+                    }
+                },
+                Architecture::SkipGram => for c in context {
+                    for value in error.iter_mut() {
+                        *value = 0.0;
+                    }
+                    for (h, i) in hidden.iter_mut().zip(&input[c * dimensions..]) {
+                        *h = load(i);
+                    }
+                    self.negative_sampling(&hidden, word, alpha, random, &mut error);
+                    for (i, e) in input[c * dimensions..(c + 1) * dimensions].iter().zip(&error) {
+                        store(i, load(i) + *e);
+                    }
+                },
+            }
+        }
+    }
+
+    // current learning rate, decaying linearly to 0.01 % of the initial one
+    fn alpha(&self) -> f32 {
+        let starting_alpha = self.config.starting_alpha();
+        let total = (self.config.iterations as u64 * self.vocabulary.total()) as f32 + 1.0;
+        let processed = self.words_processed.load(Ordering::Relaxed) as f32;
+        (starting_alpha * (1.0 - processed / total)).max(starting_alpha * 0.0001)
+    }
+
+    // train on the part of the corpus assigned to the given thread
+    fn run_thread(&self, thread: usize) -> Result<()> {
+        let threads = self.config.threads as u64;
+        let start = self.corpus_size * thread as u64 / threads;
+        let end = self.corpus_size * (thread as u64 + 1) / threads;
+        let mut random = Random(self.config.seed.wrapping_add(thread as u64));
+        let total = self.vocabulary.total() as f32;
+        let threshold = self.config.sample * total;
+        for _ in 0..self.config.iterations {
+            // a part starts with the first line beginning within it; begin one byte earlier to
+            // detect whether the part starts at the beginning of a line
+            let offset = start.saturating_sub(1);
+            let mut file = File::open(&self.corpus)?;
+            file.seek(SeekFrom::Start(offset))?;
+            let mut reader = BufReader::new(file).take(end - offset);
+            let mut line = String::new();
+            if start > 0 {
+                let mut skipped = Vec::new();
+                reader.read_until(b'\n', &mut skipped)?;
+            }
+            let mut sentence = Vec::with_capacity(MAX_SENTENCE_LENGTH);
+            let mut words_read = 0;
+            loop {
+                line.clear();
+                // the last line may extend beyond the end of the part
+                let read = match reader.limit() {
+                    0 => 0,
+                    _ => {
+                        let read = reader.read_line(&mut line)?;
+                        if !line.ends_with('\n') && read > 0 && reader.limit() == 0 {
+                            reader.get_mut().read_line(&mut line)?;
+                        }
+                        read
+                    },
+                };
+                if read == 0 {
+                    break;
+                }
+                for word in line.split_whitespace().filter_map(|w| self.vocabulary.index(w)) {
+                    words_read += 1;
+                    if threshold > 0.0 {
+                        let count = self.vocabulary.count(word) as f32;
+                        let keep = ((count / threshold).sqrt() + 1.0) * threshold / count;
+                        if keep < random.next_float() {
+                            continue;
+                        }
+                    }
+                    sentence.push(word);
+                    if sentence.len() == MAX_SENTENCE_LENGTH {
+                        self.train_sentence(&sentence, self.alpha(), &mut random);
+                        sentence.clear();
+                    }
+                }
+                self.train_sentence(&sentence, self.alpha(), &mut random);
+                sentence.clear();
+                if words_read > 10_000 {
+                    self.words_processed.fetch_add(words_read, Ordering::Relaxed);
+                    words_read = 0;
+                }
+            }
+            self.words_processed.fetch_add(words_read, Ordering::Relaxed);
+        }
+        Ok(())
+    }
+}
+
+/// Train word vectors on a corpus file, as written by crafted.
+pub fn train(corpus: &Path, config: &Config) -> Result<Model> {
+    if config.dimensions == 0 || config.window == 0 {
+        return Err(malformed("dimensions and window must be at least 1".into()));
+    }
+    let vocabulary = Vocabulary::from_corpus(corpus, config.min_count)?;
+    if vocabulary.is_empty() {
+        return Err(TransformationError::ErrorneousStructure(format!(
+                "no word occurs at least {} times", config.min_count),
+                PositionType::InDirectory(corpus.to_path_buf())));
+    }
+    let dimensions = config.dimensions;
+    let len = vocabulary.len() * dimensions;
+    // input vectors are initialised randomly, output vectors with zero
+    let mut random = Random(config.seed);
+    let input = (0..len).map(|_| AtomicU32::new(
+                ((random.next_float() - 0.5) / dimensions as f32).to_bits())).collect();
+    let output = (0..len).map(|_| AtomicU32::new(0.0f32.to_bits())).collect();
+    let training = Arc::new(Training {
+        config: Config { threads: config.threads.max(1), ..config.clone() },
+        corpus: corpus.to_path_buf(),
+        corpus_size: corpus.metadata()?.len(),
+        table: unigram_table(&vocabulary),
+        sigmoid: sigmoid_table(),
+        vocabulary,
+        weights: SharedWeights { input, output },
+        words_processed: AtomicUsize::new(0),
+    });
+    let handles: Vec<_> = (0..training.config.threads).map(|thread| {
+        let training = Arc::clone(&training);
+        thread::spawn(move || training.run_thread(thread))
+    }).collect();
+    let mut result = Ok(());
+    for handle in handles {
+        let thread_result = handle.join().unwrap_or_else(|_| Err(TransformationError::IoError(
+                io::Error::new(io::ErrorKind::Other, "training thread panicked"),
+                PositionType::None)));
+        if result.is_ok() {
+            result = thread_result;
+        }
+    }
+    result?;
+    let training = match Arc::try_unwrap(training) {
+        Ok(training) => training,
+        Err(_) => unreachable!("all training threads have been joined"),
+    };
+    let vectors = training.weights.input.into_iter().map(|weight| f32::from_bits(
+            weight.into_inner())).collect();
+    Ok(Model { words: training.vocabulary.words, dimensions, vectors })
+}
@@ -0,0 +1,21 @@
+//! Helpers shared by the tests of the word vector tools
+#![allow(dead_code)]
+
+use craft::word2vec::Model;
+
+/// Deterministic pseudo-random vectors, the words are numbered and prefixed with `prefix`.
+pub fn random_model(words: usize, dimensions: usize, prefix: &str, seed: u64) -> Model {
+    let mut state = seed;
+    let vectors = (0..words * dimensions).map(|_| {
+        state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        (state >> 40) as f32 / (1u64 << 24) as f32 - 0.5
+    }).collect();
+    Model { words: (0..words).map(|i| format!("{}{}", prefix, i)).collect(), dimensions,
+        vectors }
+}
+
+pub fn cosine(a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm = |v: &[f32]| v.iter().map(|x| x * x).sum::<f32>().sqrt();
+    dot / (norm(a) * norm(b))
+}
@@ -0,0 +1,99 @@
+#[cfg(test)]
+extern crate craft;
+
+mod common;
+
+use common::random_model;
+use craft::alignment::*;
+use craft::word2vec::Model;
+use std::env;
+use std::fs::File;
+use std::io::Write;
+
+// rotation in the plane of each pair of neighbouring axes
+fn rotate(model: &Model, prefix: &str) -> Model {
+    let d = model.dimensions;
+    let mut vectors = model.vectors.clone();
+    for vector in vectors.chunks_mut(d) {
+        for axis in 0..d - 1 {
+            let angle = 0.3 + axis as f32 * 0.2;
+            let (x, y) = (vector[axis], vector[axis + 1]);
+            vector[axis] = angle.cos() * x - angle.sin() * y;
+            vector[axis + 1] = angle.sin() * x + angle.cos() * y;
+        }
+    }
+    Model { words: (0..model.words.len()).map(|i| format!("{}{}", prefix, i)).collect(),
+        dimensions: d, vectors }
+}
+
+#[test]
+fn test_that_seed_pairs_are_read_from_tab_separated_file() {
+    let path = env::temp_dir().join("craft_alignment_seed.tsv");
+    File::create(&path).unwrap().write_all(b"haus\thouse\nbaum\ttree\nkaputt\n\n").unwrap();
+    let pairs = read_seed_pairs(&path).unwrap();
+    assert_eq!(pairs, vec![("haus".to_string(), "house".to_string()),
+        ("baum".to_string(), "tree".to_string())]);
+}
+
+#[test]
+fn test_that_split_keeps_translations_of_a_word_together() {
+    let pairs: Vec<(String, String)> = (0..1000).flat_map(|i|
+            vec![(format!("s{}", i), format!("a{}", i)), (format!("s{}", i), format!("b{}", i))])
+        .collect();
+    let (train, test) = split_seed(pairs.clone(), 0.2, 1);
+    assert_eq!(train.len() + test.len(), 2000);
+    assert!(test.len() > 300 && test.len() < 500, "{} pairs held out", test.len());
+    for (source, _) in &test {
+        assert!(!train.iter().any(|p| p.0 == *source));
+    }
+    assert_eq!(split_seed(pairs, 0.2, 1).1, test);
+}
+
+#[test]
+fn test_that_procrustes_recovers_rotation() {
+    let source = random_model(50, 4, "s", 7);
+    let target = rotate(&source, "t");
+    let pairs: Vec<(&[f32], &[f32])> = source.vectors.chunks(4).zip(target.vectors.chunks(4))
+        .collect();
+    let mapping = procrustes(&pairs, 4);
+    for (x, y) in pairs {
+        for (mapped, expected) in mapping.apply(x).iter().zip(y) {
+            assert!((mapped - expected).abs() < 1e-4);
+        }
+    }
+}
+
+#[test]
+fn test_that_rotated_space_is_translated_and_evaluated() {
+    let source = random_model(300, 8, "s", 7);
+    let target = rotate(&source, "t");
+    let pairs: Vec<(String, String)> = (0..300).map(|i| (format!("s{}", i), format!("t{}", i)))
+        .collect();
+    let (train, test) = split_seed(pairs, 0.3, 1);
+    let alignment = Alignment::new(&source, &target, &train, DEFAULT_NEIGHBOURHOOD).unwrap();
+    let candidates = alignment.translate("s7", 3);
+    assert_eq!(candidates.len(), 3);
+    assert_eq!(candidates[0].0, "t7");
+    assert!(candidates[0].1 > candidates[1].1);
+    assert!(alignment.translate("unknown", 3).is_empty());
+    let evaluation = evaluate(&alignment, &test, &[1, 5]);
+    assert_eq!(evaluation.evaluated, test.len());
+    assert_eq!(evaluation.precision, vec![(1, 1.0), (5, 1.0)]);
+}
+
+#[test]
+fn test_that_spaces_of_different_dimensions_are_rejected() {
+    let seed = vec![("s0".to_string(), "t0".to_string())];
+    assert!(Alignment::new(&random_model(10, 4, "s", 7), &random_model(10, 5, "t", 7), &seed, 10)
+        .is_err());
+}
+
+#[test]
+fn test_that_candidates_are_written_as_tab_separated_values() {
+    let candidates = vec![("haus".to_string(), vec![("house".to_string(), 0.61234f32),
+        ("home".to_string(), 0.5f32)])];
+    let mut output = Vec::new();
+    write_candidates(&candidates, &mut output).unwrap();
+    assert_eq!(String::from_utf8(output).unwrap(),
+               "haus\thouse\t0.6123\nhaus\thome\t0.5000\n");
+}
@@ -0,0 +1,104 @@
+#[cfg(test)]
+extern crate craft;
+
+use craft::modules::alto::*;
+use std::env;
+use std::fs::{self, File};
+use std::io::Write;
+
+fn blocks(xml: &str, min_confidence: f32) -> Vec<TextBlock> {
+    parse_alto(xml.as_bytes(), min_confidence).unwrap()
+}
+
+static PAGE: &'static str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<alto xmlns="http://www.loc.gov/standards/alto/ns-v2#">
+<Layout><Page ID="P1"><PrintSpace>
+<TextBlock ID="TB1">
+  <TextLine><String CONTENT="Der" WC="0.9"/><SP/><String CONTENT="Kaiser" WC="0.8"/></TextLine>
+  <TextLine><String CONTENT="kam" WC="0.95"/><SP/><String CONTENT="heu" WC="0.9"/><HYP CONTENT="-"/></TextLine>
+  <TextLine><String CONTENT="te" WC="0.9"/><SP/><String CONTENT="an." WC="0.9"/></TextLine>
+</TextBlock>
+<TextBlock ID="TB2">
+  <TextLine><String CONTENT="Zweiter" WC="0.9"/><SP/><String CONTENT="~^x" WC="0.1"/></TextLine>
+</TextBlock>
+</PrintSpace></Page></Layout>
+</alto>"#;
+
+#[test]
+fn test_that_text_blocks_and_lines_are_reconstructed() {
+    let parsed = blocks(PAGE, 0.0);
+    assert_eq!(parsed.len(), 2);
+    assert_eq!(parsed[0].id, "TB1");
+    assert_eq!(parsed[0].text, "Der Kaiser kam heute an.");
+}
+
+#[test]
+fn test_that_words_with_low_confidence_are_dropped() {
+    let parsed = blocks(PAGE, 0.5);
+    assert_eq!(parsed[1].text, "Zweiter");
+}
+
+#[test]
+fn test_that_blocks_without_confident_words_are_omitted() {
+    let parsed = blocks(PAGE, 0.99);
+    assert!(parsed.is_empty());
+}
+
+#[test]
+fn test_that_substitution_content_is_used_for_hyphenated_words() {
+    let xml = r#"<alto><TextBlock ID="B">
+        <TextLine><String CONTENT="Zei" SUBS_TYPE="HypPart1" SUBS_CONTENT="Zeitung"/><HYP CONTENT="-"/></TextLine>
+        <TextLine><String CONTENT="tung" SUBS_TYPE="HypPart2" SUBS_CONTENT="Zeitung"/><SP/><String CONTENT="heute"/></TextLine>
+        </TextBlock></alto>"#;
+    assert_eq!(blocks(xml, 0.5)[0].text, "Zeitung heute");
+}
+
+// a page with the given text blocks, one line each
+fn page(blocks: &[(&str, &str)]) -> String {
+    let mut xml = String::from("<?xml version=\"1.0\"?>\n<alto><Layout><Page><PrintSpace>");
+    for &(id, text) in blocks {
+        xml.push_str(&format!("<TextBlock ID=\"{}\"><TextLine>", id));
+        for word in text.split(' ') {
+            xml.push_str(&format!("<String CONTENT=\"{}\"/>", word));
+        }
+        xml.push_str("</TextLine></TextBlock>");
+    }
+    xml + "</PrintSpace></Page></Layout></alto>"
+}
+
+static METS: &'static str = r#"<?xml version="1.0"?>
+<mets xmlns="http://www.loc.gov/METS/" xmlns:xlink="http://www.w3.org/1999/xlink">
+<fileSec><fileGrp USE="FULLTEXT">
+  <file ID="ALTO1"><FLocat LOCTYPE="URL" xlink:href="pages/page1.xml"/></file>
+  <file ID="ALTO2"><FLocat LOCTYPE="URL" xlink:href="file://pages/page2.xml"/></file>
+</fileGrp></fileSec>
+<structMap TYPE="PHYSICAL"><div TYPE="page"><fptr><area FILEID="ALTO1" BEGIN="B1"/></fptr></div></structMap>
+<structMap TYPE="LOGICAL"><div TYPE="Newspaper"><div TYPE="ISSUE">
+  <div TYPE="ARTICLE" LABEL="Aus der Hauptstadt">
+    <div TYPE="BODY"><fptr><area FILEID="ALTO1" BEGIN="B1"/></fptr>
+      <fptr><area FILEID="ALTO2" BEGIN="B1"/></fptr></div>
+  </div>
+  <div TYPE="ARTICLE" LABEL="Wetter">
+    <fptr><area FILEID="ALTO9" BEGIN="B1"/></fptr><fptr><area FILEID="ALTO1" BEGIN="B2"/></fptr>
+  </div>
+</div></div></structMap>
+</mets>"#;
+
+#[test]
+fn test_that_mets_articles_group_blocks_of_several_pages() {
+    let directory = env::temp_dir().join("craft_alto_mets");
+    fs::create_dir_all(directory.join("pages")).unwrap();
+    File::create(directory.join("mets.xml")).unwrap().write_all(METS.as_bytes()).unwrap();
+    File::create(directory.join("pages/page1.xml")).unwrap().write_all(page(&[
+            ("B1", "Der Kaiser kam"), ("B2", "Morgen Regen")]).as_bytes()).unwrap();
+    File::create(directory.join("pages/page2.xml")).unwrap().write_all(page(&[
+            ("B1", "heute an."), ("B2", "Anzeigen")]).as_bytes()).unwrap();
+    let articles = AltoDocuments::new(&directory).unwrap().map(|article| article.unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(articles.len(), 2);
+    assert_eq!(articles[0].content, "Der Kaiser kam \x07 heute an.");
+    assert_eq!(articles[0].metadata.title, Some("Aus der Hauptstadt".to_string()));
+    // the unknown file ID is skipped, the pages are not emitted again block by block
+    assert_eq!(articles[1].content, "Morgen Regen");
+    assert_eq!(articles[1].metadata.title, Some("Wetter".to_string()));
+}
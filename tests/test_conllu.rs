@@ -0,0 +1,42 @@
+#[cfg(test)]
+extern crate craft;
+
+use craft::modules::conllu::*;
+
+static SENTENCE: &'static str = "# sent_id = 1
+# text = Wir gehen zum Haus.
+1\tWir\twir\tPRON\tPPER\t_\t2\tnsubj\t_\t_
+2\tgehen\tgehen\tVERB\tVVFIN\t_\t0\troot\t_\t_
+3-4\tzum\t_\t_\t_\t_\t_\t_\t_\t_
+3\tzu\tzu\tADP\tAPPR\t_\t5\tcase\t_\t_
+4\tdem\tder\tDET\tART\t_\t5\tdet\t_\t_
+5\tHaus\tHaus\tNOUN\tNN\t_\t2\tobl\t_\t_
+5.1\tgeht\tgehen\tVERB\tVVFIN\t_\t_\t_\t_\t_
+6\t.\t.\tPUNCT\t$.\t_\t2\tpunct\t_\t_";
+
+fn tokens(form: TokenForm) -> String {
+    sentence_tokens(SENTENCE.lines(), form).join(" ")
+}
+
+#[test]
+fn test_that_surface_forms_use_multiword_tokens() {
+    assert_eq!(tokens(TokenForm::Surface), "Wir gehen zum Haus .");
+}
+
+#[test]
+fn test_that_lemmas_use_syntactic_words() {
+    assert_eq!(tokens(TokenForm::Lemma), "wir gehen zu der Haus .");
+}
+
+#[test]
+fn test_that_lemmas_are_annotated_with_pos() {
+    assert_eq!(tokens(TokenForm::LemmaPos),
+               "wir_PRON gehen_VERB zu_ADP der_DET Haus_NOUN ._PUNCT");
+}
+
+#[test]
+fn test_that_token_forms_are_parsed_from_config_names() {
+    assert_eq!(TokenForm::from_name("lemma_pos"), Some(TokenForm::LemmaPos));
+    assert_eq!(TokenForm::from_name("Surface"), Some(TokenForm::Surface));
+    assert_eq!(TokenForm::from_name("stem"), None);
+}
@@ -0,0 +1,78 @@
+#[cfg(test)]
+extern crate craft;
+extern crate isolang;
+
+use craft::coverage::*;
+use isolang::Language;
+use std::collections::BTreeSet;
+use std::env;
+use std::fs::File;
+use std::io::Write;
+
+fn vocabulary() -> Vocabulary {
+    let mut vocabulary = Vocabulary::new();
+    vocabulary.add_text("wikipedia", "Das Haus ist alt\n\u{7}\nDas Haus steht\n");
+    vocabulary.add_text("tatoeba", "Haus und Hof\nad_hoc\n");
+    vocabulary
+}
+
+fn headwords(words: &[&str]) -> BTreeSet<String> {
+    words.iter().map(|w| w.to_string()).collect()
+}
+
+#[test]
+fn test_that_words_are_counted_per_module() {
+    let vocabulary = vocabulary();
+    assert_eq!(vocabulary.count("Haus"), 3);
+    assert_eq!(vocabulary.count_in("wikipedia", "Haus"), 2);
+    assert_eq!(vocabulary.count_in("tatoeba", "alt"), 0);
+    assert_eq!(vocabulary.modules(), vec!["tatoeba", "wikipedia"]);
+}
+
+#[test]
+fn test_that_vocabulary_survives_writing_and_reading() {
+    let mut written = Vec::new();
+    vocabulary().write(&mut written).unwrap();
+    let read = Vocabulary::read(&written[..]).unwrap();
+    assert_eq!(read.count("Haus"), 3);
+    assert_eq!(read.count_in("tatoeba", "Hof"), 1);
+}
+
+#[test]
+fn test_that_malformed_vocabulary_lines_are_rejected() {
+    assert!(Vocabulary::read("wikipedia\tHaus\n".as_bytes()).is_err());
+}
+
+#[test]
+fn test_that_coverage_is_reported_per_threshold() {
+    let report = CoverageReport::new(&headwords(&["Haus", "Hof", "Garten"]), &vocabulary(),
+            &[2, 1]);
+    assert_eq!(report.headwords, 3);
+    assert_eq!(report.thresholds, vec![(1, 2), (2, 1)]);
+    assert_eq!(report.missing, vec!["Garten"]);
+}
+
+#[test]
+fn test_that_coverage_is_broken_down_by_module() {
+    let report = CoverageReport::new(&headwords(&["Haus", "Hof", "alt"]), &vocabulary(), &[1]);
+    assert_eq!(report.modules, vec![("tatoeba".to_string(), 2), ("wikipedia".to_string(), 2)]);
+}
+
+#[test]
+fn test_that_multi_word_headwords_match_joined_phrases() {
+    let report = CoverageReport::new(&headwords(&["ad hoc"]), &vocabulary(), &[1]);
+    assert!(report.missing.is_empty());
+    assert_eq!(report.percentage(1), 100.0);
+}
+
+#[test]
+fn test_that_terms_of_a_term_base_are_read_as_headwords() {
+    let path = env::temp_dir().join("craft_coverage_terms.tbx");
+    File::create(&path).unwrap().write_all(br#"<martif type="TBX"><text><body><termEntry>
+        <langSet xml:lang="en"><tig><term>house</term></tig></langSet>
+        <langSet xml:lang="de"><tig><term>Haus</term></tig></langSet>
+        </termEntry></body></text></martif>"#).unwrap();
+    let german = Language::from_639_3("deu").unwrap();
+    assert_eq!(read_headwords(&path, Some(&german)).unwrap(), headwords(&["Haus"]));
+    assert_eq!(read_headwords(&path, None).unwrap(), headwords(&["Haus", "house"]));
+}
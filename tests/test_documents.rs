@@ -0,0 +1,25 @@
+#[cfg(test)]
+extern crate craft;
+
+use craft::input_source::{Entity, PositionType, Unformatter};
+use craft::modules::documents::*;
+use std::path::PathBuf;
+
+#[test]
+fn test_that_every_format_has_its_extension() {
+    let extensions: Vec<&str> = FORMATS.iter().map(|f| f.extension()).collect();
+    assert_eq!(extensions, vec!["docx", "odt", "rtf"]);
+}
+
+#[test]
+fn test_that_documents_are_read_from_their_file() {
+    let path = PathBuf::from("texts/brief.odt");
+    let entity = Entity::with_path(String::new(), path.clone());
+    assert_eq!(OfficeDocument(DocumentFormat::Odt).input_file(&entity).unwrap(), Some(path));
+}
+
+#[test]
+fn test_that_entities_without_path_are_an_error() {
+    let entity = Entity::new("text".into(), PositionType::None);
+    assert!(OfficeDocument(DocumentFormat::Docx).input_file(&entity).is_err());
+}
@@ -0,0 +1,56 @@
+#[cfg(test)]
+extern crate craft;
+
+use craft::modules::epub::*;
+
+static PACKAGE: &'static str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<package xmlns="http://www.idpf.org/2007/opf" version="3.0">
+<metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+  <dc:title>Die Leiden des jungen Werthers</dc:title>
+  <dc:language>de</dc:language>
+  <dc:rights>Public domain in the USA.</dc:rights>
+</metadata>
+<manifest>
+  <item id="nav" href="toc.xhtml" properties="nav" media-type="application/xhtml+xml"/>
+  <item id="c1" href="text/chapter%201.xhtml" media-type="application/xhtml+xml"/>
+  <item id="c2" href="text/chapter-2.xhtml#start" media-type="application/xhtml+xml"/>
+  <item id="notes" href="text/notes.xhtml" media-type="application/xhtml+xml"/>
+</manifest>
+<spine>
+  <itemref idref="nav"/>
+  <itemref idref="c2"/>
+  <itemref idref="c1"/>
+  <itemref idref="notes" linear="no"/>
+</spine>
+</package>"#;
+
+#[test]
+fn test_that_rootfile_is_found() {
+    let container = r#"<?xml version="1.0"?>
+<container xmlns="urn:oasis:names:tc:opendocument:xmlns:container" version="1.0">
+<rootfiles><rootfile full-path="epub/content.opf" media-type="application/oebps-package+xml"/>
+</rootfiles></container>"#;
+    assert_eq!(parse_container(container.as_bytes()).unwrap(), "epub/content.opf");
+}
+
+#[test]
+fn test_that_spine_gives_reading_order_without_nav_and_non_linear_items() {
+    let package = parse_package(PACKAGE.as_bytes(), "epub/content.opf").unwrap();
+    assert_eq!(package.chapters, vec!["epub/text/chapter-2.xhtml".to_string(),
+        "epub/text/chapter 1.xhtml".to_string()]);
+}
+
+#[test]
+fn test_that_dublin_core_metadata_is_read() {
+    let package = parse_package(PACKAGE.as_bytes(), "content.opf").unwrap();
+    assert_eq!(package.title, Some("Die Leiden des jungen Werthers".into()));
+    assert_eq!(package.language, Some("de".into()));
+    assert_eq!(package.rights, Some("Public domain in the USA.".into()));
+    assert_eq!(package.date, None);
+}
+
+#[test]
+fn test_that_relative_references_are_resolved() {
+    assert_eq!(resolve_href("OEBPS/text/ch1.xhtml", "../images/a.png"), "OEBPS/images/a.png");
+    assert_eq!(resolve_href("content.opf", "ch1.xhtml"), "ch1.xhtml");
+}
@@ -0,0 +1,78 @@
+#[cfg(test)]
+extern crate craft;
+
+use craft::modules::europeana::*;
+use std::env;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::PathBuf;
+
+// create a directory with a well-scanned and a garbled edition
+fn write_editions(name: &str) -> PathBuf {
+    let directory = env::temp_dir().join(name);
+    fs::create_dir_all(&directory).unwrap();
+    File::create(directory.join("good.json")).unwrap().write_all(r#"{
+        "title": ["Berliner Tageblatt", "Morgenausgabe"], "date": " 1901-03-02 ",
+        "language": "de", "provider": "Staatsbibliothek zu Berlin",
+        "contentAsText": ["Der Kaiser ist heute in Berlin angekommen ",
+            "und wurde vom Volke begrüßt."]}"#.as_bytes()).unwrap();
+    File::create(directory.join("garbled.json")).unwrap().write_all(r#"{
+        "title": "Garbled", "contentAsText": ["D3r ~^| KaIs3r »^~ wArd ||| gEgr§"]}"#
+        .as_bytes()).unwrap();
+    directory
+}
+
+#[test]
+fn test_that_proper_text_has_high_quality() {
+    let text = "Der Kaiser ist heute in Berlin angekommen und wurde vom Volke begrüßt.";
+    assert!(ocr_quality(text) > 0.9);
+}
+
+#[test]
+fn test_that_garbage_runs_lower_quality() {
+    let clean = "Die Stadt wurde im Jahre gegründet und wuchs rasch.";
+    let garbled = "Die St^~dt wurde ~~ im Ja|)re gegr»^ndet und wuchs ra§ch.";
+    assert!(ocr_quality(garbled) < ocr_quality(clean));
+    assert!(ocr_quality(garbled) < 0.5);
+}
+
+#[test]
+fn test_that_fragmented_words_lower_quality() {
+    let text = "i . , ; tl ii ll il .. ,, fi ffl";
+    assert!(ocr_quality(text) < 0.5);
+}
+
+#[test]
+fn test_that_mixed_case_and_digits_are_not_dictionary_words() {
+    assert!(ocr_quality("D3r KaIs3r wArd") < 0.1);
+}
+
+#[test]
+fn test_that_empty_text_has_zero_quality() {
+    assert_eq!(ocr_quality(""), 0.0);
+}
+
+#[test]
+fn test_that_no_article_is_dropped_by_default() {
+    let directory = write_editions("craft_europeana_default");
+    let mut titles = Articles::new(&directory).map(|article| article.unwrap().metadata.title)
+        .collect::<Vec<_>>();
+    titles.sort();
+    assert_eq!(titles, vec![Some("Berliner Tageblatt, Morgenausgabe".to_string()),
+            Some("Garbled".to_string())]);
+}
+
+#[test]
+fn test_that_articles_below_threshold_are_dropped() {
+    let directory = write_editions("craft_europeana_threshold");
+    let mut articles = Articles::new(&directory);
+    articles.set_quality_threshold(0.5);
+    let articles = articles.map(|article| article.unwrap()).collect::<Vec<_>>();
+    assert_eq!(articles.len(), 1);
+    assert_eq!(articles[0].content,
+            "Der Kaiser ist heute in Berlin angekommen und wurde vom Volke begrüßt.");
+    let metadata = &articles[0].metadata;
+    assert_eq!(metadata.date, Some("1901-03-02".to_string()));
+    assert_eq!(metadata.language, Some("de".to_string()));
+    assert_eq!(metadata.provider, Some("Staatsbibliothek zu Berlin".to_string()));
+}
@@ -0,0 +1,75 @@
+#[cfg(test)]
+extern crate craft;
+extern crate isolang;
+
+use craft::modules::freedict::*;
+use isolang::Language;
+use std::path::Path;
+
+static DICTIONARY: &'static str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<TEI xmlns="http://www.tei-c.org/ns/1.0">
+  <teiHeader><fileDesc><titleStmt><title>German-English FreeDict Dictionary</title>
+  </titleStmt></fileDesc></teiHeader>
+  <text><body>
+    <entry>
+      <form><orth>Haus</orth><pron>haʊ̯s</pron></form>
+      <gramGrp><pos>n</pos><gen>neut</gen></gramGrp>
+      <sense n="1">
+        <cit type="trans"><quote>house</quote></cit>
+        <cit type="trans"><quote>home</quote></cit>
+        <cit type="example"><quote>Das Haus ist
+          alt.</quote><cit type="trans"><quote>The house is old.</quote></cit></cit>
+      </sense>
+      <sense n="2">
+        <def>Familie, Geschlecht</def>
+        <cit type="trans"><quote>dynasty</quote></cit>
+      </sense>
+    </entry>
+    <entry>
+      <form><orth>gehen</orth></form>
+      <cit type="trans"><quote>to go</quote></cit>
+    </entry>
+  </body></text>
+</TEI>"#;
+
+#[test]
+fn test_that_headwords_and_part_of_speech_are_read() {
+    let entries: Vec<DictEntry> = EntryReader::new(DICTIONARY.as_bytes())
+        .map(|e| e.unwrap()).collect();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0].headwords, vec!["Haus"]);
+    assert_eq!(entries[0].part_of_speech, Some("n".into()));
+    assert_eq!(entries[1].headwords, vec!["gehen"]);
+    assert_eq!(entries[1].part_of_speech, None);
+}
+
+#[test]
+fn test_that_senses_keep_translations_and_definitions_apart() {
+    let entry = EntryReader::new(DICTIONARY.as_bytes()).next().unwrap().unwrap();
+    assert_eq!(entry.senses.len(), 2);
+    assert_eq!(entry.senses[0].translations, vec!["house", "home"]);
+    assert_eq!(entry.senses[1].definitions, vec!["Familie, Geschlecht"]);
+    assert_eq!(entry.senses[1].translations, vec!["dynasty"]);
+}
+
+#[test]
+fn test_that_examples_exclude_their_translation() {
+    let entry = EntryReader::new(DICTIONARY.as_bytes()).next().unwrap().unwrap();
+    assert_eq!(entry.examples(), vec!["Das Haus ist alt."]);
+}
+
+#[test]
+fn test_that_translations_without_sense_get_an_implicit_sense() {
+    let entry = EntryReader::new(DICTIONARY.as_bytes()).nth(1).unwrap().unwrap();
+    assert_eq!(entry.senses, vec![Sense { translations: vec!["to go".into()],
+        ..Sense::default() }]);
+}
+
+#[test]
+fn test_that_the_language_pair_is_taken_from_the_file_name() {
+    let path = Path::new("/usr/share/freedict/deu-eng.tei");
+    assert_eq!(dictionary_languages(path), Some(("deu".into(), "eng".into())));
+    assert!(is_source_language(path, &Language::Deu));
+    assert!(!is_source_language(path, &Language::Eng));
+    assert!(is_source_language(Path::new("dictionary.tei"), &Language::Eng));
+}
@@ -6,7 +6,7 @@ use craft::input_source::*;
 
 fn preproc(data: &str) -> Result<Entity> {
     let g = Gutenberg;
-    let input = Entity { content: data.into(), position: PositionType::None };
+    let input = Entity::new(data.into(), PositionType::None);
     g.preprocess(&input)
 }
 
@@ -0,0 +1,109 @@
+#[cfg(test)]
+extern crate craft;
+
+use craft::modules::html::*;
+use std::io::Cursor;
+
+static PAGE: &'static str = r#"<!DOCTYPE html>
+<html lang="de-DE">
+<head><title>Ein Artikel &amp; mehr</title><script>var x = "<p>versteckt</p>";</script></head>
+<body>
+<nav><ul><li><a href="/">Start</a></li><li><a href="/b">Blog</a></li></ul></nav>
+<div class="cookie-banner"><p>Wir verwenden Cookies, um Ihnen das beste Erlebnis auf unserer
+Webseite zu bieten, bitte stimmen Sie der Verwendung aller Cookies zu.</p></div>
+<article>
+<h1>Überschrift</h1>
+<p>Dies ist ein langer Absatz mit genügend Wörtern, damit er als Inhalt der Seite erkannt wird
+und nicht als Navigation.</p>
+<p>Ein kurzer Absatz steht dazwischen.</p>
+<p>Auch dieser Absatz ist lang genug, um als Inhalt zu gelten, er enthält nur einen
+<a href="/x">Verweis</a> und sonst ganz gewöhnlichen Text.</p>
+</article>
+<p><a href="/1">Impressum</a> <a href="/2">Datenschutz</a> <a href="/3">Kontakt</a></p>
+</body></html>"#;
+
+#[test]
+fn test_that_main_content_is_kept_and_boilerplate_removed() {
+    let document = extract_main_content(PAGE, DEFAULT_MIN_WORDS);
+    assert!(document.text.starts_with("Überschrift \x07 Dies ist ein langer Absatz"));
+    assert!(document.text.contains("Ein kurzer Absatz steht dazwischen."));
+    assert!(document.text.contains("einen Verweis und sonst"));
+    for boilerplate in &["Start", "Cookies", "Impressum", "versteckt"] {
+        assert!(!document.text.contains(boilerplate), "found {}", boilerplate);
+    }
+}
+
+#[test]
+fn test_that_title_and_language_are_read() {
+    let document = extract_main_content(PAGE, DEFAULT_MIN_WORDS);
+    assert_eq!(document.title, Some("Ein Artikel & mehr".into()));
+    assert_eq!(document.language, Some("de-DE".into()));
+}
+
+#[test]
+fn test_that_character_references_are_decoded() {
+    assert_eq!(decode_entities("a&nbsp;&lt;b&gt; &#228;&#xFC; &unknown; & x"),
+               "a <b> äü &unknown; & x");
+}
+
+#[test]
+fn test_that_latin1_pages_are_decoded() {
+    let page = b"<meta charset=\"iso-8859-1\"><p>Gr\xfc\xdfe</p>";
+    assert!(decode_page(page, None).contains("Grüße"));
+    assert!(decode_page(b"<p>Gr\xfc\xdfe</p>", Some("text/html; charset=windows-1252"))
+            .contains("Grüße"));
+}
+
+#[test]
+fn test_that_iso_8859_15_pages_are_decoded() {
+    let page = b"<p>5 \xa4 f\xfcr \xbcuvre, \xa6koda und \xbe</p>";
+    assert!(decode_page(page, Some("text/html; charset=ISO-8859-15"))
+            .contains("5 € für Œuvre, Škoda und Ÿ"));
+}
+
+#[test]
+fn test_that_warc_records_and_http_responses_are_parsed() {
+    let http = "HTTP/1.1 200 OK\r\nContent-Type: text/html\r\nContent-Language: de\r\n\r\n<p>x</p>";
+    let warc = format!("WARC/1.0\r\nWARC-Type: warcinfo\r\nContent-Length: 3\r\n\r\nabc\r\n\r\n\
+            WARC/1.0\r\nWARC-Type: response\r\nContent-Length: {}\r\n\r\n{}\r\n\r\n",
+            http.len(), http);
+    let mut reader = WarcReader::new(Cursor::new(warc.into_bytes()));
+    let info = reader.next_record().unwrap().unwrap();
+    assert_eq!(info.headers.get("warc-type"), Some(&"warcinfo".to_string()));
+    let response = reader.next_record().unwrap().unwrap();
+    let (headers, body) = parse_http_response(&response.payload).unwrap();
+    assert_eq!(headers.get("content-language"), Some(&"de".to_string()));
+    assert_eq!(body, b"<p>x</p>");
+    assert!(reader.next_record().unwrap().is_none());
+}
+
+// a page with the given element in front of its content
+fn page_with(element: &str) -> String {
+    format!("<html><body class=\"has-cookie-banner\">{}<main><p>Dies ist ein langer Absatz mit \
+            genügend Wörtern, damit er als Inhalt der Seite erkannt wird und nicht als \
+            Navigation.</p></main></body></html>", element)
+}
+
+#[test]
+fn test_that_void_boilerplate_elements_do_not_hide_the_page() {
+    for element in &["<link rel='stylesheet' id='cookie-law-info-css' href='x.css'>",
+            "<img class=\"share-icon\" src=\"a.png\">"] {
+        let document = extract_main_content(&page_with(element), DEFAULT_MIN_WORDS);
+        assert!(document.text.starts_with("Dies ist ein langer Absatz"), "{}", element);
+    }
+}
+
+#[test]
+fn test_that_markers_match_whole_words_of_class_names() {
+    let document = extract_main_content(&page_with("<div class=\"shared-content menuitem\">\
+            <p>Ein weiterer Absatz, der lang genug ist, um als Inhalt der Seite zu gelten und \
+            nicht übersprungen zu werden.</p></div>"), DEFAULT_MIN_WORDS);
+    assert!(document.text.starts_with("Ein weiterer Absatz"), "{}", document.text);
+    assert!(document.text.contains("Dies ist ein langer Absatz"));
+}
+
+#[test]
+fn test_that_truncated_warc_records_are_rejected() {
+    let warc = "WARC/1.0\r\nWARC-Type: response\r\nContent-Length: 99999999999\r\n\r\nabc";
+    assert!(WarcReader::new(Cursor::new(warc.as_bytes())).next_record().is_err());
+}
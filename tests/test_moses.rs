@@ -0,0 +1,61 @@
+#[cfg(test)]
+extern crate craft;
+
+use craft::modules::moses::*;
+use std::env;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+// create a directory with a corpus in two languages
+fn write_corpus(name: &str, german: &str, english: &str) -> PathBuf {
+    let directory = env::temp_dir().join(name);
+    fs::create_dir_all(&directory).unwrap();
+    File::create(directory.join("corpus.de")).unwrap().write_all(german.as_bytes()).unwrap();
+    File::create(directory.join("corpus.en")).unwrap().write_all(english.as_bytes()).unwrap();
+    directory
+}
+
+#[test]
+fn test_that_counterpart_exchanges_suffix() {
+    assert_eq!(counterpart(Path::new("data/Europarl.de-en.de"), "de", "en"),
+               Some(PathBuf::from("data/Europarl.de-en.en")));
+}
+
+#[test]
+fn test_that_counterpart_keeps_compression_suffix() {
+    assert_eq!(counterpart(Path::new("corpus.de.gz"), "de", "en"),
+               Some(PathBuf::from("corpus.en.gz")));
+    assert_eq!(counterpart(Path::new("corpus.de.bz2"), "de", "en"),
+               Some(PathBuf::from("corpus.en.bz2")));
+}
+
+#[test]
+fn test_that_files_with_other_suffix_have_no_counterpart() {
+    assert_eq!(counterpart(Path::new("corpus.fr"), "de", "en"), None);
+}
+
+#[test]
+fn test_that_pairs_are_aligned_by_line() {
+    let directory = write_corpus("craft_moses_aligned", "Das Haus.\n\nDer Baum.\n",
+            "The house.\nNothing.\nThe tree.\n");
+    let pairs: Vec<(String, String)> = MosesPairs::new(&directory, "de", "en").unwrap()
+        .map(|pair| pair.unwrap()).map(|pair| (pair.source, pair.target)).collect();
+    assert_eq!(pairs, vec![("Das Haus.".to_string(), "The house.".to_string()),
+        ("Der Baum.".to_string(), "The tree.".to_string())]);
+    fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn test_that_differing_line_counts_are_reported() {
+    let directory = write_corpus("craft_moses_misaligned", "Das Haus.\nDer Baum.\n",
+            "The house.\n");
+    let mut pairs = MosesPairs::new(&directory, "de", "en").unwrap();
+    match pairs.next() {
+        Some(Err(error)) => assert!(error.to_string().contains("line counts differ"), "{}",
+                error),
+        _ => panic!("expected an error about the differing line counts"),
+    }
+    assert!(pairs.next().is_none());
+    fs::remove_dir_all(&directory).unwrap();
+}
@@ -0,0 +1,93 @@
+#[cfg(test)]
+extern crate craft;
+
+use craft::phrases::*;
+use std::env;
+use std::fs::{self, File};
+use std::io::{Read, Write};
+
+fn config(threshold: f64, min_count: u64, passes: usize) -> PhraseConfig {
+    PhraseConfig { threshold, min_count, passes, ..PhraseConfig::default() }
+}
+
+// "new york times" in 30 lines, surrounded by rare words
+fn corpus() -> String {
+    let mut text = String::new();
+    for i in 0..30 {
+        text.push_str(&format!("w{} new york times w{}\n", i, i + 100));
+    }
+    for i in 0..60 {
+        text.push_str(&format!("a{} b{} c{}\n", i, i, i));
+    }
+    text
+}
+
+#[test]
+fn test_that_bigrams_are_scored_with_mikolov_formula() {
+    let phrases = Phrases::learn(vec!["a b", "a b", "a b c", "b c"], &config(0.0, 1, 1));
+    // 3 words and 2 bigrams; count(a b) = 3, count(a) = 3, count(b) = 4
+    let score = phrases.score("a", "b").unwrap();
+    assert!((score - (3.0 - 1.0) / (3.0 * 4.0) * 5.0).abs() < 1e-9);
+    // count(b c) = 2: (2 - 1) / (4 * 2) * 5
+    assert!((phrases.score("b", "c").unwrap() - 0.625).abs() < 1e-9);
+    assert_eq!(phrases.score("c", "a"), None);
+}
+
+#[test]
+fn test_that_rare_bigrams_are_no_phrases() {
+    let phrases = Phrases::learn(vec!["a b", "a b", "a b"], &config(0.0, 3, 1));
+    assert!(phrases.is_empty());
+}
+
+#[test]
+fn test_that_phrases_are_joined_from_left_to_right_within_lines() {
+    let phrases = Phrases::learn(vec!["a b", "b a", "a b"], &config(0.0, 0, 1));
+    assert_eq!(phrases.len(), 2);
+    assert_eq!(phrases.apply("a b a"), "a_b a");
+    assert_eq!(phrases.apply("c b a b"), "c b_a b");
+    assert_eq!(detect_in_memory("a\nb\na b\n", &config(0.0, 0, 1)), "a\nb\na_b\n");
+}
+
+#[test]
+fn test_that_further_passes_find_longer_phrases() {
+    let text = corpus();
+    let one_pass = detect_in_memory(&text, &config(5.0, 5, 1));
+    assert!(one_pass.starts_with("w0 new_york times w100\n"), "{}", one_pass);
+    let two_passes = detect_in_memory(&text, &config(5.0, 5, 2));
+    assert!(two_passes.starts_with("w0 new_york_times w100\n"), "{}", two_passes);
+    assert!(two_passes.ends_with("a59 b59 c59\n"));
+}
+
+#[test]
+fn test_that_file_mode_matches_memory_mode() {
+    let input = env::temp_dir().join("craft_phrases_input.txt");
+    let output = env::temp_dir().join("craft_phrases_output.txt");
+    File::create(&input).unwrap().write_all(corpus().as_bytes()).unwrap();
+    let found = detect_in_file(&input, &output, &config(5.0, 5, 2)).unwrap();
+    assert_eq!(found.len(), 2);
+    let mut written = String::new();
+    File::open(&output).unwrap().read_to_string(&mut written).unwrap();
+    assert_eq!(written, detect_in_memory(&corpus(), &config(5.0, 5, 2)));
+    assert!(!env::temp_dir().join("craft_phrases_output.txt.pass0").exists());
+    fs::remove_file(&output).unwrap();
+}
+
+#[test]
+fn test_that_corpus_can_be_overwritten() {
+    let corpus_path = env::temp_dir().join("craft_phrases_in_place.txt");
+    File::create(&corpus_path).unwrap().write_all(corpus().as_bytes()).unwrap();
+    detect_in_file(&corpus_path, &corpus_path, &config(5.0, 5, 2)).unwrap();
+    let mut written = String::new();
+    File::open(&corpus_path).unwrap().read_to_string(&mut written).unwrap();
+    assert_eq!(written, detect_in_memory(&corpus(), &config(5.0, 5, 2)));
+    fs::remove_file(&corpus_path).unwrap();
+}
+
+#[test]
+fn test_that_no_intermediate_files_are_left_on_failure() {
+    let output = env::temp_dir().join("craft_phrases_failed.txt");
+    let missing = env::temp_dir().join("craft_phrases_does_not_exist.txt");
+    assert!(detect_in_file(&missing, &output, &config(5.0, 5, 2)).is_err());
+    assert!(!output.exists());
+    assert!(!env::temp_dir().join("craft_phrases_failed.txt.pass0").exists());
+}
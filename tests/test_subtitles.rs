@@ -0,0 +1,62 @@
+#[cfg(test)]
+extern crate craft;
+
+use craft::modules::subtitles::*;
+
+static SRT: &'static str = "1\r\n00:00:01,000 --> 00:00:02,500\r\n<i>Where are you</i>\r\n\r\n\
+    2\r\n00:00:02,600 --> 00:00:04,000\r\ngoing, {\\an8}Anna?\r\n\r\n\
+    3\r\n00:00:04,100 --> 00:00:05,000\r\n[door slams]\r\nHome.\r\n\r\n\
+    4\r\n00:00:20,000 --> 00:00:22,000\r\n♪ La la ♪\r\n";
+
+#[test]
+fn test_that_timestamps_are_parsed() {
+    assert_eq!(parse_timestamp("01:02:03,500"), Some(3723.5));
+    assert_eq!(parse_timestamp("02:03.250"), Some(123.25));
+    assert_eq!(parse_timestamp("abc"), None);
+}
+
+#[test]
+fn test_that_srt_cues_are_parsed_without_tags() {
+    let cues = parse_cues(SRT);
+    assert_eq!(cues.len(), 4);
+    assert_eq!(cues[0], Cue { start: 1.0, end: 2.5, text: "Where are you".into() });
+    assert_eq!(cues[1].text, "going, Anna?");
+    assert_eq!(cues[2].text, "Home.");
+    assert_eq!(cues[3].text, "La la");
+}
+
+#[test]
+fn test_that_unclosed_brackets_are_kept() {
+    assert_eq!(strip_tags("I <3 you"), "I <3 you");
+    assert_eq!(strip_tags("<i>{x</i> [sighs] 2 > 1 [really"), "{x 2 > 1 [really");
+}
+
+#[test]
+fn test_that_vtt_header_and_cue_settings_are_ignored() {
+    let vtt = "WEBVTT\n\nNOTE a comment\n\n00:01.000 --> 00:02.000 align:start\n<v Bob>Hi!</v>\n";
+    assert_eq!(parse_cues(vtt), vec![Cue { start: 1.0, end: 2.0, text: "Hi!".into() }]);
+}
+
+#[test]
+fn test_that_split_sentences_are_merged_and_scenes_split_by_gap() {
+    let scenes = group_scenes(&parse_cues(SRT), DEFAULT_SCENE_GAP);
+    assert_eq!(scenes, vec![vec!["Where are you going, Anna?".to_string(), "Home.".to_string()],
+        vec!["La la".to_string()]]);
+}
+
+#[test]
+fn test_that_opensubtitles_sentences_are_read() {
+    let xml = r#"<?xml version="1.0" encoding="utf-8"?>
+<document id="1">
+  <s id="1">
+    <time id="T1S" value="00:00:51,819" />
+    <w id="1.1">Hallo</w>
+    <w id="1.2">!</w>
+    <time id="T1E" value="00:00:53,000" />
+  </s>
+  <s id="2"><w id="2.1">Wer</w> <w id="2.2">da</w><w id="2.3">?</w></s>
+</document>"#;
+    let cues = parse_opensubtitles(xml.as_bytes()).unwrap();
+    assert_eq!(cues, vec![Cue { start: 51.819, end: 53.0, text: "Hallo !".into() },
+        Cue { start: 53.0, end: 53.0, text: "Wer da ?".into() }]);
+}
@@ -0,0 +1,68 @@
+#[cfg(test)]
+extern crate craft;
+extern crate isolang;
+
+use craft::modules::tatoeba::*;
+use isolang::Language;
+use std::env;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::PathBuf;
+
+// create a directory with an export of a few sentences and their links
+fn write_export(name: &str) -> PathBuf {
+    let directory = env::temp_dir().join(name);
+    fs::create_dir_all(&directory).unwrap();
+    File::create(directory.join("sentences.csv")).unwrap().write_all(b"1\tdeu\tDas Haus.\n\
+            2\teng\tThe house.\n3\tfra\tLa maison.\n4\tdeu\tDer Baum.\nkaputt\n\
+            5\teng\tThe tree.\n").unwrap();
+    File::create(directory.join("links.csv")).unwrap().write_all(b"1\t2\n1\t3\n2\t1\n\
+            4\t5\n4\t9\n").unwrap();
+    directory
+}
+
+fn lang(code: &str) -> Language {
+    Language::from_639_3(code).unwrap()
+}
+
+#[test]
+fn test_that_sentence_lines_are_parsed() {
+    assert_eq!(parse_sentence("1276\teng\tLet's try something.\n"),
+               Some(Sentence { id: 1276, language: "eng".into(),
+                   text: "Let's try something.".into() }));
+}
+
+#[test]
+fn test_that_tabs_within_text_are_kept() {
+    assert_eq!(parse_sentence("7\tdeu\tA\tB").map(|s| s.text), Some("A\tB".to_string()));
+}
+
+#[test]
+fn test_that_malformed_lines_are_ignored() {
+    assert_eq!(parse_sentence("abc\teng\tText"), None);
+    assert_eq!(parse_sentence("12\teng"), None);
+}
+
+#[test]
+fn test_that_links_are_parsed() {
+    assert_eq!(parse_link("1\t77"), Some((1, 77)));
+    assert_eq!(parse_link("1"), None);
+}
+
+#[test]
+fn test_that_sentences_are_filtered_by_language() {
+    let directory = write_export("craft_tatoeba_sentences");
+    let texts: Vec<String> = TatoebaSentences::new(&directory, lang("deu")).unwrap()
+        .map(|entity| entity.unwrap().content).collect();
+    assert_eq!(texts, vec!["Das Haus. \u{7} Der Baum. \u{7} ".to_string()]);
+}
+
+#[test]
+fn test_that_sentences_are_paired_through_links() {
+    let directory = write_export("craft_tatoeba_pairs");
+    let pairs: Vec<(String, String)> = TatoebaPairs::new(&directory.join("sentences.csv"),
+            lang("deu"), lang("eng")).unwrap()
+        .map(|pair| pair.unwrap()).map(|pair| (pair.source, pair.target)).collect();
+    assert_eq!(pairs, vec![("Das Haus.".to_string(), "The house.".to_string()),
+        ("Der Baum.".to_string(), "The tree.".to_string())]);
+}
@@ -0,0 +1,80 @@
+#[cfg(test)]
+extern crate craft;
+extern crate isolang;
+
+use craft::modules::tei::*;
+use craft::textfilter::RETURN_ESCAPE_SEQUENCE;
+use isolang::Language;
+
+static DOCUMENT: &'static str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<TEI xmlns="http://www.tei-c.org/ns/1.0" xml:lang="de">
+  <teiHeader>
+    <fileDesc><titleStmt><title type="main">Die
+        Räuber</title><title type="sub">Ein Schauspiel</title></titleStmt></fileDesc>
+  </teiHeader>
+  <text>
+    <front><p>Vorrede, nicht im Rumpf</p></front>
+    <body>
+      <div>
+        <head>Erster Akt</head>
+        <fw type="header">12</fw>
+        <p>Aber ist Euch auch wohl, Va<lb break="no"/>ter?<note place="foot">Anmerkung</note>
+        Ihr seht so <choice><sic>blaß</sic><corr>bleich</corr></choice> aus.</p>
+        <p xml:lang="la">Quod erat demonstrandum.</p>
+        <lg><l>Erste Zeile</l><l>zweite <foreign xml:lang="fr">très</foreign> Zeile</l></lg>
+      </div>
+    </body>
+  </text>
+</TEI>"#;
+
+fn contexts(text: &str) -> Vec<String> {
+    text.split(RETURN_ESCAPE_SEQUENCE).map(|c| c.trim().to_string()).collect()
+}
+
+#[test]
+fn test_that_only_the_body_is_extracted() {
+    let document = parse_tei(DOCUMENT.as_bytes(), &Language::Deu).unwrap();
+    assert!(!document.text.contains("Vorrede"));
+    assert!(!document.text.contains("Schauspiel"));
+    assert_eq!(document.title, Some("Die Räuber".into()));
+}
+
+#[test]
+fn test_that_notes_forme_work_and_corrected_readings_are_skipped() {
+    let document = parse_tei(DOCUMENT.as_bytes(), &Language::Deu).unwrap();
+    assert!(!document.text.contains("Anmerkung"));
+    assert!(!document.text.contains("12"));
+    assert!(!document.text.contains("blaß"));
+    assert!(document.text.contains("so bleich aus."));
+}
+
+#[test]
+fn test_that_hyphenated_line_breaks_are_joined() {
+    let document = parse_tei(DOCUMENT.as_bytes(), &Language::Deu).unwrap();
+    assert!(document.text.contains("wohl, Vater?"));
+}
+
+#[test]
+fn test_that_indentation_after_hyphenated_line_breaks_is_skipped() {
+    let tei = "<TEI><text><body><p>Va<lb break=\"no\"/>\n   ter und Mut<lb break=\"no\"/>\n   \
+            <hi>ter</hi> kom<lb/>\n   men</p></body></text></TEI>";
+    let document = parse_tei(tei.as_bytes(), &Language::Deu).unwrap();
+    assert_eq!(document.text, "Vater und Mutter kom men");
+}
+
+#[test]
+fn test_that_paragraphs_and_verse_lines_are_context_lines() {
+    let document = parse_tei(DOCUMENT.as_bytes(), &Language::Deu).unwrap();
+    assert_eq!(contexts(&document.text), vec!["Erster Akt",
+        "Aber ist Euch auch wohl, Vater? Ihr seht so bleich aus.", "Erste Zeile",
+        "zweite Zeile"]);
+}
+
+#[test]
+fn test_that_foreign_passages_are_skipped() {
+    let document = parse_tei(DOCUMENT.as_bytes(), &Language::Deu).unwrap();
+    assert!(!document.text.contains("demonstrandum"));
+    assert!(!document.text.contains("très"));
+    let latin = parse_tei(DOCUMENT.as_bytes(), &Language::Lat).unwrap();
+    assert_eq!(latin.text, "Quod erat demonstrandum.");
+}
@@ -1,11 +1,13 @@
 #[cfg(test)]
 extern crate craft;
+extern crate isolang;
 
 
 
 use craft::*;
 use craft::input_source::*;
 use craft::textfilter::*;
+use isolang::Language;
 
 fn art2words(input: &str) -> String {
     text2words(input.to_string(), None)
@@ -27,6 +29,11 @@ fn test_numbers_are_included() {
     assert_eq!(art2words("my 1st test"), "my 1st test\n");
 }
 
+#[test]
+fn test_underscores_join_annotations() {
+    assert_eq!(art2words("Haus_NOUN bauen_VERB"), "Haus_NOUN bauen_VERB\n");
+}
+
 #[test]
 fn test_that_punctuation_is_removed_and_words_preserved() {
     assert_eq!(art2words("However, I like it. :)"), "However I like it\n");
@@ -82,7 +89,7 @@ fn test_newline_markers_keep_newline() {
 // this function calls the JSON2text function and replaces all " \u{7}" sequuuences through \n;
 // this is partly what textfilter::text2words does, but this version is for tests
 fn call_filter(js_str: String) -> String {
-    let js_str = Entity { content: js_str, position: PositionType::None };
+    let js_str = Entity::new(js_str, PositionType::None);
     let result = textfilter::stringify_text(js_str).unwrap();
     result.content.replace(&format!(" {} ", textfilter::RETURN_ESCAPE_SEQUENCE), "\n")
 }
@@ -521,3 +528,235 @@ fn test_that_json_documents_with_more_than_unmeta_and_content_are_incorrect() {
     call_filter(json_str);
 }
 
+////////////////////////////////////////////////////////////////////////////////
+// normalisation
+
+#[test]
+fn test_that_stemmer_reduces_inflected_forms() {
+    let stemmer = SnowballStemmer::new(&Language::Deu).unwrap();
+    assert_eq!(text2words_normalized("Häuser, Hauses (Haus)".into(), None, Some(&stemmer)),
+               "haus haus haus\n");
+}
+
+#[test]
+fn test_that_stemmer_is_not_available_for_every_language() {
+    assert!(SnowballStemmer::new(&Language::Pol).is_err());
+}
+
+#[test]
+fn test_that_lemmatizer_looks_up_forms_and_keeps_unknown_words() {
+    let lemmatizer = Lemmatizer::from_reader("# comment\nging\tgehen\nHäuser\tHaus\n\n"
+            .as_bytes()).unwrap();
+    assert_eq!(lemmatizer.len(), 2);
+    assert_eq!(text2words_normalized("Ging er in die Häuser?".into(), None,
+            Some(&lemmatizer)), "gehen er in die Haus\n");
+}
+
+#[test]
+fn test_that_stopwords_are_matched_before_normalisation() {
+    let lemmatizer = Lemmatizer::from_reader("ging\tgehen\n".as_bytes()).unwrap();
+    let stopwords = Some(vec!["gehen".to_string()].into_iter().collect());
+    assert_eq!(text2words_normalized("ging gehen".into(), stopwords, Some(&lemmatizer)),
+               "gehen\n");
+}
+
+#[test]
+fn test_that_lemma_list_without_tab_is_rejected() {
+    assert!(Lemmatizer::from_reader("ging gehen\n".as_bytes()).is_err());
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// compound splitting
+
+fn splitter(mode: CompoundMode) -> CompoundSplitter {
+    let mut splitter = CompoundSplitter::new(mode);
+    splitter.add_text("Recht Recht Recht Vorschrift Vorschrift Angleichung Angleichung \
+                      Rechtsvorschriftenangleichung Haus Haus Tür Tür Haustür Haustür Haustür \
+                      Haustür Haustür Haustür Wagen Wagen Wagen Kinder Kinder Kinderwagen");
+    splitter
+}
+
+#[test]
+fn test_that_compounds_are_split_at_linking_morphemes() {
+    let splitter = splitter(CompoundMode::Replace);
+    assert_eq!(splitter.split("Rechtsvorschriftangleichung"),
+               Some(vec!["Recht".to_string(), "Vorschrift".into(), "Angleichung".into()]));
+    assert_eq!(splitter.split("Rechtsvorschriftenangleichung"),
+               Some(vec!["Recht".to_string(), "Vorschrift".into(), "Angleichung".into()]));
+    assert_eq!(splitter.split("Kinderwagen"), Some(vec!["Kinder".to_string(), "Wagen".into()]));
+}
+
+#[test]
+fn test_that_frequent_compounds_and_unknown_words_are_kept() {
+    let splitter = splitter(CompoundMode::Replace);
+    assert_eq!(splitter.split("Haustür"), None);
+    assert_eq!(splitter.split("Gartenzaun"), None);
+    assert_eq!(splitter.split("Recht"), None);
+}
+
+#[test]
+fn test_that_compounds_are_split_into_few_parts_quickly() {
+    let mut splitter = CompoundSplitter::new(CompoundMode::Replace);
+    splitter.add_text("Haus Haus Tür Tür Haustür Abc Abc Abcd Abcd Abcde Abcde");
+    // five parts would be better, but are too many
+    assert_eq!(splitter.split("Haustürhaustürhaus").map(|parts| parts.len()), Some(4));
+    // the number of splits of this word grows exponentially with its length
+    let word = "abc".repeat(40);
+    assert!(splitter.split(&word).map(|parts| parts.len() <= MAX_COMPOUND_PARTS)
+            .unwrap_or(true));
+}
+
+#[test]
+fn test_that_compound_parts_replace_or_follow_the_compound() {
+    assert_eq!(text2words_normalized("Der Kinderwagen.".into(), None,
+            Some(&splitter(CompoundMode::Replace))), "Der Kinder Wagen\n");
+    assert_eq!(text2words_normalized("Der Kinderwagen.".into(), None,
+            Some(&splitter(CompoundMode::Keep))), "Der Kinderwagen Kinder Wagen\n");
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// segmentation
+
+fn segmented(input: &str, segmenter: &Segmenter) -> String {
+    text2words_segmented(input.to_string(), None, None, segmenter)
+}
+
+fn chinese_lexicon() -> Lexicon {
+    Lexicon::from_reader("北京 100 ns\n天安门 50 ns\n编程\n".as_bytes()).unwrap()
+}
+
+#[test]
+fn test_that_segmenter_is_chosen_by_language() {
+    match Segmenter::for_language(&Language::Deu, Some(chinese_lexicon())) {
+        Segmenter::Whitespace => (),
+        other => panic!("expected white space segmentation, got {:?}", other),
+    }
+    match Segmenter::for_language(&Language::Zho, None) {
+        Segmenter::Unicode => (),
+        other => panic!("expected Unicode segmentation, got {:?}", other),
+    }
+    match Segmenter::for_language(&Language::Jpn, Some(chinese_lexicon())) {
+        Segmenter::Dictionary(ref lexicon) => assert_eq!(lexicon.len(), 3),
+        other => panic!("expected dictionary segmentation, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_that_ideographs_become_single_words_without_lexicon() {
+    assert_eq!(segmented("我爱北京。", &Segmenter::Unicode), "我 爱 北 京\n");
+}
+
+#[test]
+fn test_that_lexicon_words_are_matched_greedily() {
+    let segmenter = Segmenter::Dictionary(chinese_lexicon());
+    assert_eq!(segmented("我爱北京天安门。", &segmenter), "我 爱 北京 天安门\n");
+    assert_eq!(segmented("我用Rust编程", &segmenter), "我 用 Rust 编程\n");
+}
+
+#[test]
+fn test_that_kana_is_segmented_with_lexicon() {
+    let lexicon = Lexicon::from_reader("東京\nタワー\n".as_bytes()).unwrap();
+    assert_eq!(segmented("東京タワーへ", &Segmenter::Dictionary(lexicon)), "東京 タワー へ\n");
+}
+
+#[test]
+fn test_that_unknown_thai_words_are_split_into_grapheme_clusters() {
+    let lexicon = Lexicon::from_reader("ฉัน\nดื่ม\n".as_bytes()).unwrap();
+    assert_eq!(segmented("ฉันดื่มน้ำ", &Segmenter::Dictionary(lexicon)), "ฉัน ดื่ม น้ำ\n");
+}
+
+#[test]
+fn test_that_segmentation_preserves_line_breaks() {
+    assert_eq!(segmented("我爱 \x07 北京", &Segmenter::Dictionary(chinese_lexicon())),
+               "我 爱\n北京\n");
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// character classes
+
+fn classified(input: &str, characters: &CharClasses) -> String {
+    text2words_with(input, &FilterOptions { characters: Some(characters),
+        ..FilterOptions::default() })
+}
+
+#[test]
+fn test_that_punctuation_of_other_scripts_is_stripped() {
+    assert_eq!(art2words("مرحبا، عالم؟"), "مرحبا عالم\n");
+    assert_eq!(art2words("यह घर है। वह"), "यह घर है वह\n");
+    assert_eq!(art2words("「東京」。"), "東京\n");
+}
+
+#[test]
+fn test_that_leading_spanish_punctuation_is_stripped() {
+    assert_eq!(art2words("¿Qué tal? ¡Hola!"), "Qué tal Hola\n");
+}
+
+#[test]
+fn test_that_typographic_quotes_are_stripped_but_apostrophes_kept() {
+    assert_eq!(art2words("‘quoted’ O’Neill’s"), "quoted O’Neill’s\n");
+}
+
+#[test]
+fn test_that_overridden_characters_change_class() {
+    let mut characters = CharClasses::default();
+    assert_eq!(classified("col·lecció", &characters), "");
+    characters.set_class('·', Some(CharClass::Joiner));
+    assert_eq!(classified("col·lecció", &characters), "col·lecció\n");
+    characters.set_class('-', Some(CharClass::Punctuation));
+    assert_eq!(classified("-foo- bar-baz", &characters), "foo\n");
+}
+
+#[test]
+fn test_that_elisions_are_kept_by_default() {
+    let characters = CharClasses::for_language(&Language::Fra);
+    assert_eq!(classified("l'homme d’Artagnan", &characters), "l'homme d’Artagnan\n");
+}
+
+#[test]
+fn test_that_elisions_are_split_or_dropped() {
+    let mut characters = CharClasses::for_language(&Language::Fra);
+    characters.elision = AffixHandling::Split;
+    assert_eq!(classified("L'homme qu’il aujourd'hui", &characters),
+               "L' homme qu’ il aujourd'hui\n");
+    characters.elision = AffixHandling::Drop;
+    assert_eq!(classified("L'homme qu’il l'", &characters), "homme il\n");
+}
+
+#[test]
+fn test_that_clitics_are_split_or_dropped() {
+    let mut characters = CharClasses::for_language(&Language::Eng);
+    characters.clitics = AffixHandling::Split;
+    assert_eq!(classified("John's car doesn’t start 's", &characters),
+               "John 's car does n’t start\n");
+    characters.clitics = AffixHandling::Drop;
+    assert_eq!(classified("John's car", &characters), "John car\n");
+}
+
+#[test]
+fn test_that_empty_clitics_are_ignored() {
+    let mut characters = CharClasses::default();
+    characters.clitics = AffixHandling::Split;
+    characters.set_clitics(&["", "'s"]);
+    assert_eq!(classified("John's car", &characters), "John 's car\n");
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// streaming
+
+#[test]
+fn test_that_words_are_written_like_text2words_returns_them() {
+    for text in &["test this", " \x07 \x07 abc \x07 def (ghi). \x07 ", "jo (''.) moo", ""] {
+        let mut output = Vec::new();
+        text2words_into(text, &FilterOptions::default(), &mut output).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), art2words(text));
+    }
+}
+
+#[test]
+fn test_that_stop_words_are_not_written() {
+    let stopwords = ["the", "a"].iter().map(|w| w.to_string()).collect();
+    let mut output = Vec::new();
+    text2words_into("the cat, a dog \x07 the", &FilterOptions { stopwords: Some(&stopwords),
+        ..FilterOptions::default() }, &mut output).unwrap();
+    assert_eq!(output, b"cat dog\n");
+}
@@ -0,0 +1,80 @@
+#[cfg(test)]
+extern crate craft;
+
+mod common;
+
+use common::{cosine, random_model};
+use craft::thesaurus::*;
+use craft::word2vec::Model;
+
+fn small_model() -> Model {
+    Model { words: vec!["haus".into(), "gebäude".into(), "heim".into(), "apfel".into()],
+        dimensions: 2, vectors: vec![1.0, 0.0, 0.9, 0.1, 0.7, 0.7, -1.0, 0.2] }
+}
+
+#[test]
+fn test_that_index_finds_nearly_all_exact_neighbours() {
+    let model = random_model(1000, 16, "w", 42);
+    let index = Index::build(&model, IndexConfig::default());
+    assert_eq!(index.len(), 1000);
+    let mut found = 0;
+    for query in 0..50 {
+        let vector = &model.vectors[query * 16..(query + 1) * 16];
+        let mut exact: Vec<(usize, f32)> = (0..1000)
+            .map(|n| (n, cosine(vector, &model.vectors[n * 16..(n + 1) * 16]))).collect();
+        exact.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+        let approximate: Vec<usize> = index.search(vector, 10).into_iter().map(|r| r.0)
+            .collect();
+        found += exact.iter().take(10).filter(|e| approximate.contains(&e.0)).count();
+    }
+    // recall of at least 90 %
+    assert!(found >= 450, "only {} of 500 neighbours found", found);
+}
+
+#[test]
+fn test_that_search_returns_most_similar_first() {
+    let model = small_model();
+    let index = Index::build(&model, IndexConfig::default());
+    let results = index.search(&[1.0, 0.0], 2);
+    assert_eq!(results[0].0, 0);
+    assert!((results[0].1 - 1.0).abs() < 1e-6);
+    assert_eq!(results[1].0, 1);
+}
+
+#[test]
+fn test_that_thesaurus_excludes_headword_and_dissimilar_words() {
+    let model = small_model();
+    let index = Index::build(&model, IndexConfig::default());
+    let config = ThesaurusConfig { top: 5, min_similarity: 0.6, allowlist: None };
+    let thesaurus = build_thesaurus(&model, &index, &config);
+    let (ref headword, ref neighbours) = thesaurus[0];
+    assert_eq!(headword, "haus");
+    let words: Vec<&str> = neighbours.iter().map(|n| n.word.as_str()).collect();
+    assert_eq!(words, vec!["gebäude", "heim"]);
+    assert!(thesaurus.iter().all(|(h, _)| h != "apfel"));
+}
+
+#[test]
+fn test_that_only_allowlisted_headwords_are_written() {
+    let model = small_model();
+    let index = Index::build(&model, IndexConfig::default());
+    let config = ThesaurusConfig { allowlist: Some(vec!["heim".to_string()].into_iter()
+            .collect()), ..ThesaurusConfig::default() };
+    let thesaurus = build_thesaurus(&model, &index, &config);
+    assert_eq!(thesaurus.len(), 1);
+    assert_eq!(thesaurus[0].0, "heim");
+}
+
+#[test]
+fn test_output_formats() {
+    let thesaurus = vec![("Haus".to_string(), vec![Neighbour { word: "Heim & Hof".into(),
+        similarity: 0.75 }])];
+    let mut tsv = Vec::new();
+    write_tsv(&thesaurus, &mut tsv).unwrap();
+    assert_eq!(String::from_utf8(tsv).unwrap(), "Haus\tHeim & Hof\t0.7500\n");
+    let mut tei = Vec::new();
+    write_tei(&thesaurus, &mut tei).unwrap();
+    let tei = String::from_utf8(tei).unwrap();
+    assert!(tei.contains("<orth>Haus</orth>"));
+    assert!(tei.contains("<xr type=\"syn\"><ref cert=\"0.7500\">Heim &amp; Hof</ref></xr>"));
+}
@@ -0,0 +1,125 @@
+#[cfg(test)]
+extern crate craft;
+extern crate isolang;
+extern crate zip;
+
+use craft::modules::tmx::*;
+use isolang::Language;
+use std::env;
+use std::fs::File;
+use std::io::Write;
+use std::path::PathBuf;
+
+fn german() -> Language {
+    Language::from_639_3("deu").unwrap()
+}
+
+static TMX: &'static str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<tmx version="1.4"><header srclang="en"/><body>
+<tu>
+  <tuv xml:lang="en-GB"><seg>The <bpt i="1">&lt;b&gt;</bpt>house<ept i="1">&lt;/b&gt;</ept>.</seg></tuv>
+  <tuv xml:lang="de-DE"><seg>Das Haus.</seg></tuv>
+</tu>
+<tu>
+  <tuv lang="EN"><seg>A tree.</seg></tuv>
+  <tuv lang="FR"><seg>Un arbre.</seg></tuv>
+</tu>
+</body></tmx>"#;
+
+#[test]
+fn test_that_language_variants_match() {
+    assert!(language_matches("de-DE", &german()));
+    assert!(language_matches("DE", &german()));
+    assert!(language_matches("de_AT", &german()));
+    assert!(language_matches("deu", &german()));
+    assert!(!language_matches("en-GB", &german()));
+    assert!(!language_matches("", &german()));
+}
+
+#[test]
+fn test_that_all_translation_units_are_read() {
+    let units = read_translation_units(TMX.as_bytes()).unwrap();
+    assert_eq!(units.len(), 2);
+    assert_eq!(units[0].len(), 2);
+    assert_eq!(units[1][1].language, "FR");
+    assert_eq!(units[1][1].text, "Un arbre.");
+}
+
+#[test]
+fn test_that_inline_native_code_is_skipped() {
+    let units = read_translation_units(TMX.as_bytes()).unwrap();
+    assert_eq!(units[0][0].text, "The house.");
+}
+
+#[test]
+fn test_that_tbx_terms_of_requested_language_are_read() {
+    let tbx = r#"<martif type="TBX"><text><body>
+        <termEntry id="1">
+          <langSet xml:lang="en"><tig><term>house</term></tig></langSet>
+          <langSet xml:lang="de"><tig><term>Haus</term></tig><ntig><termGrp><term>Gebäude</term></termGrp></ntig></langSet>
+        </termEntry>
+        <termEntry id="2"><langSet xml:lang="de"><tig><term>Haus</term></tig></langSet></termEntry>
+        </body></text></martif>"#;
+    let terms = parse_tbx_terms(tbx.as_bytes(), Some(&german())).unwrap();
+    assert_eq!(terms, vec!["Haus".to_string(), "Gebäude".to_string()]);
+}
+
+// write a zip archive with the given entries
+fn write_zip(name: &str, entries: &[(&str, Vec<u8>)]) -> PathBuf {
+    let path = env::temp_dir().join(name);
+    let mut archive = zip::ZipWriter::new(File::create(&path).unwrap());
+    for &(ref entry, ref data) in entries {
+        archive.start_file(*entry, zip::write::FileOptions::default()).unwrap();
+        archive.write_all(data).unwrap();
+    }
+    archive.finish().unwrap();
+    path
+}
+
+fn utf16le(text: &str) -> Vec<u8> {
+    let mut data = vec![0xFF, 0xFE];
+    for unit in text.encode_utf16() {
+        data.push((unit & 0xFF) as u8);
+        data.push((unit >> 8) as u8);
+    }
+    data
+}
+
+#[test]
+fn test_that_tmx_entries_of_zip_archives_are_read() {
+    let path = write_zip("craft_tmx_entries.zip", &[("a.tmx", utf16le(TMX)),
+        ("readme.txt", b"not a translation memory".to_vec()),
+        ("b.tmx", TMX.replace("Das Haus.", "Der Baum.").into_bytes())]);
+    let texts: Vec<String> = TmxFiles::new(&path, german()).unwrap()
+        .map(|entity| entity.unwrap().content).collect();
+    assert_eq!(texts.len(), 1);
+    assert!(texts[0].starts_with("Das Haus. \u{7} Der Baum."), "{}", texts[0]);
+    let pairs: Vec<(String, String)> = TmxPairs::new(&path, german(),
+            Language::from_639_3("eng").unwrap()).unwrap()
+        .map(|pair| pair.unwrap()).map(|pair| (pair.source, pair.target)).collect();
+    assert_eq!(pairs, vec![("Das Haus.".to_string(), "The house.".to_string()),
+        ("Der Baum.".to_string(), "The house.".to_string())]);
+}
+
+#[test]
+fn test_that_large_files_are_split_between_translation_units() {
+    let sentences: Vec<String> = (0..50).map(|i| format!("Satz {} steht hier.", i)).collect();
+    let mut tmx = String::from("<tmx version=\"1.4\"><body>");
+    for sentence in &sentences {
+        tmx.push_str(&format!("<tu><tuv xml:lang=\"en\"><seg>Sentence.</seg></tuv>\
+                <tuv xml:lang=\"de\"><seg>{}</seg></tuv></tu>", sentence));
+    }
+    tmx.push_str("</body></tmx>");
+    let path = env::temp_dir().join("craft_tmx_large.tmx");
+    File::create(&path).unwrap().write_all(tmx.as_bytes()).unwrap();
+    let mut files = TmxFiles::new(&path, german()).unwrap();
+    files.set_max_entity_size(200);
+    let texts: Vec<String> = files.map(|entity| entity.unwrap().content).collect();
+    assert!(texts.len() > 1);
+    let mut segments = Vec::new();
+    for text in &texts {
+        assert!(text.len() < 250 && text.ends_with(" \u{7} "), "{}", text);
+        segments.extend(text.split(" \u{7} ").filter(|s| !s.is_empty()).map(String::from));
+    }
+    assert_eq!(segments, sentences);
+}
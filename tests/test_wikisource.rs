@@ -0,0 +1,113 @@
+#[cfg(test)]
+extern crate bzip2;
+extern crate craft;
+
+use bzip2::Compression;
+use bzip2::write::BzEncoder;
+use craft::modules::wikisource::*;
+use std::env;
+use std::fs::{self, File};
+use std::io::Write;
+
+fn templates(names: &[&str]) -> Vec<String> {
+    names.iter().map(|n| n.to_string()).collect()
+}
+
+#[test]
+fn test_that_page_range_is_parsed_from_quoted_and_bare_attributes() {
+    let found = find_transclusions("a <pages index=\"Faust.djvu\" from=3 to='5' /> b", |_| None);
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].2, Transclusion { index: "Faust.djvu".into(), pages: vec![3, 4, 5] });
+    assert_eq!(found[0].0, 2);
+}
+
+#[test]
+fn test_that_include_and_exclude_lists_are_respected() {
+    let found = find_transclusions("<pages index=x include=\"1-4,9\" exclude=\"2\" />", |_| None);
+    assert_eq!(found[0].2.pages, vec![1, 3, 4, 9]);
+}
+
+#[test]
+fn test_that_missing_end_of_range_uses_last_page_of_index() {
+    let found = find_transclusions("<pages index=\"x\" from=\"7\"/>", |_| Some(9));
+    assert_eq!(found[0].2.pages, vec![7, 8, 9]);
+}
+
+#[test]
+fn test_that_ranges_end_at_last_page_of_index() {
+    let found = find_transclusions("<pages index=x from=2 to=999999999 />\
+            <pages index=y include=\"3,5-999999999\" />", |_| Some(6));
+    assert_eq!(found[0].2.pages, vec![2, 3, 4, 5, 6]);
+    assert_eq!(found[1].2.pages, vec![3, 5, 6]);
+}
+
+#[test]
+fn test_that_header_templates_are_stripped_including_nested_ones() {
+    let text = "{{Textdaten|AUTOR={{Autor|Goethe}}|TITEL=Faust}}\nHabe nun, ach!";
+    assert_eq!(strip_templates(text, &templates(&["textdaten"])), "\nHabe nun, ach!");
+}
+
+#[test]
+fn test_that_other_templates_are_kept() {
+    let text = "{{Center|Erster Teil}}";
+    assert_eq!(strip_templates(text, &templates(&["header"])), text);
+}
+
+#[test]
+fn test_that_running_headers_and_section_tags_are_removed() {
+    let page = "<noinclude>{{Seitenkopf|12}}</noinclude><section begin=\"a\" />Text\
+                <section end=\"a\" /><noinclude>{{Fuß}}</noinclude>";
+    assert_eq!(clean_page_text(page), "Text");
+}
+
+fn page(title: &str, namespace: u32, text: &str) -> String {
+    format!("<page><title>{}</title><ns>{}</ns><revision><text>{}</text></revision></page>",
+            title, namespace, text)
+}
+
+// write the pages to a compressed dump and return the works read from it
+fn works(name: &str, siteinfo: &str, pages: &[String]) -> Vec<String> {
+    let dump = format!("<mediawiki>{}{}</mediawiki>", siteinfo, pages.concat());
+    let path = env::temp_dir().join(name);
+    let mut encoder = BzEncoder::new(File::create(&path).unwrap(), Compression::default());
+    encoder.write_all(dump.as_bytes()).unwrap();
+    encoder.finish().unwrap();
+    let works = Works::new(&path).unwrap().map(|w| w.unwrap().content).collect();
+    fs::remove_file(&path).unwrap();
+    works
+}
+
+#[test]
+fn test_that_transcluded_pages_are_read_from_the_dump() {
+    let works = works("craft_wikisource_dump.xml.bz2", "", &[
+        page("Faust", 0, "&lt;pages index=\"Faust.djvu\" from=1 to=999999999 /&gt;"),
+        page("Page:Faust.djvu/1", 104, "&lt;noinclude&gt;Kopf&lt;/noinclude&gt;Habe nun,"),
+        page("Page:Faust.djvu/2", 104, "ach! Philosophie"),
+        page("Page:Other.djvu/1", 104, "Anderes")]);
+    assert_eq!(works, vec!["Habe nun,\nach! Philosophie\n".to_string()]);
+}
+
+#[test]
+fn test_that_only_pages_of_the_page_namespace_are_transcluded() {
+    let works = works("craft_wikisource_talk.xml.bz2", "", &[
+        page("Faust", 0, "&lt;pages index=\"Faust.djvu\" from=1 to=3 /&gt;"),
+        page("Page:Faust.djvu/1", 104, "Habe nun,"),
+        page("Page talk:Faust.djvu/2", 105, "Korrekturlesen"),
+        page("User:Faust.djvu/3", 2, "Entwurf")]);
+    assert_eq!(works, vec!["Habe nun,\n".to_string()]);
+}
+
+#[test]
+fn test_that_page_namespace_is_found_in_site_information() {
+    let siteinfo = "<siteinfo><namespaces><namespace key=\"0\" case=\"first-letter\" />\
+        <namespace key=\"102\" case=\"first-letter\">Seite</namespace>\
+        <namespace key=\"103\" case=\"first-letter\">Seite Diskussion</namespace>\
+        </namespaces></siteinfo>";
+    let works = works("craft_wikisource_siteinfo.xml.bz2", siteinfo, &[
+        page("Faust", 0, "&lt;pages index=\"Faust.djvu\" from=1 to=2 /&gt;"),
+        page("Seite:Faust.djvu/1", 102, "Habe nun,"),
+        page("Seite Diskussion:Faust.djvu/2", 103, "Korrekturlesen"),
+        // the namespace number decides, not the title
+        page("Seite:Faust.djvu/2", 4, "Projektseite")]);
+    assert_eq!(works, vec!["Habe nun,\n".to_string()]);
+}
@@ -0,0 +1,94 @@
+#[cfg(test)]
+extern crate craft;
+
+use craft::modules::wiktionary::*;
+
+static ENGLISH_PAGE: &str = "==English==
+===Etymology===
+From {{inh|en|enm|hous}}.
+
+===Noun===
+{{en-noun}}
+
+# A [[structure]] serving as an [[abode|abode]] of human beings.<ref>Some book</ref>
+#: {{ux|en|This is my '''house'''.}}
+#* 1900, Some Author, ''Some Book''
+#*: The house stood on the hill.
+# {{lb|en|figuratively}} A family.
+
+===Verb===
+# To [[keep]] within a structure.
+
+==Middle English==
+===Noun===
+# house";
+
+static GERMAN_PAGE: &str = "== Haus ({{Sprache|Deutsch}}) ==
+=== {{Wortart|Substantiv|Deutsch}}, {{n}} ===
+{{Bedeutungen}}
+:[1] [[Gebäude]], das Menschen zum Wohnen dient
+{{Herkunft}}
+:von althochdeutsch hūs
+{{Beispiele}}
+:[1] ''Das Haus'' ist groß.<ref>Quelle</ref>";
+
+#[test]
+fn test_that_english_glosses_are_extracted_with_part_of_speech() {
+    let entry = parse_entry(ENGLISH_PAGE, "English");
+    assert_eq!(entry.glosses, vec![
+        Gloss { part_of_speech: "Noun".into(),
+            text: "A structure serving as an abode of human beings.".into() },
+        Gloss { part_of_speech: "Noun".into(), text: "A family.".into() },
+        Gloss { part_of_speech: "Verb".into(), text: "To keep within a structure.".into() }]);
+}
+
+#[test]
+fn test_that_english_examples_and_quotations_are_extracted() {
+    let entry = parse_entry(ENGLISH_PAGE, "English");
+    assert_eq!(entry.examples, vec!["This is my house.".to_string(),
+        "The house stood on the hill.".to_string()]);
+}
+
+#[test]
+fn test_that_other_language_sections_are_ignored() {
+    let entry = parse_entry(ENGLISH_PAGE, "Middle English");
+    assert_eq!(entry.glosses.len(), 1);
+    assert!(parse_entry(ENGLISH_PAGE, "French").glosses.is_empty());
+}
+
+#[test]
+fn test_that_german_layout_is_understood() {
+    let entry = parse_entry(GERMAN_PAGE, "Deutsch");
+    assert_eq!(entry.glosses, vec![Gloss { part_of_speech: "Substantiv".into(),
+        text: "Gebäude, das Menschen zum Wohnen dient".into() }]);
+    assert_eq!(entry.examples, vec!["Das Haus ist groß.".to_string()]);
+}
+
+#[test]
+fn test_that_markup_is_cleaned() {
+    assert_eq!(clean_markup("a {{l|en|[[word|words]]}} {{gloss|note}} <span>b</span>"),
+               "a words (note) b");
+}
+
+#[test]
+fn test_that_templates_after_stray_closing_braces_are_expanded() {
+    assert_eq!(clean_markup("a }} b {{gloss|note}}"), "a }} b (note)");
+}
+
+#[test]
+fn test_that_nested_and_unclosed_templates_are_handled() {
+    assert_eq!(clean_markup("{{ux|en|a {{l|en|big}} house}} {{gloss|open"),
+               "a big house {{gloss|open");
+}
+
+#[test]
+fn test_that_equals_signs_in_positional_text_are_kept() {
+    assert_eq!(clean_markup("{{ux|de|a=b ist wahr}}"), "a=b ist wahr");
+}
+
+#[test]
+fn test_that_known_named_parameters_are_dropped() {
+    assert_eq!(clean_markup("{{ux|de|Das Haus ist groß.|t=The house is big.}}"),
+               "Das Haus ist groß.");
+    assert_eq!(clean_markup("{{l|en|house|gloss=building}}"), "house");
+}
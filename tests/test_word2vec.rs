@@ -0,0 +1,114 @@
+#[cfg(test)]
+extern crate craft;
+
+mod common;
+
+use common::cosine;
+use craft::word2vec::*;
+use std::env;
+use std::fs::File;
+use std::io::Write;
+use std::path::PathBuf;
+
+// write a corpus in which "hund" and "katze" share their contexts, as do "apfel" and "birne"
+fn write_corpus(name: &str) -> PathBuf {
+    let path = env::temp_dir().join(format!("craft-word2vec-{}.txt", name));
+    let mut file = File::create(&path).unwrap();
+    for i in 0..300 {
+        let animal = match i % 2 { 0 => "hund", _ => "katze" };
+        let fruit = match i % 3 { 0 => "apfel", _ => "birne" };
+        writeln!(file, "der {} bellt und läuft im garten herum", animal).unwrap();
+        writeln!(file, "ich esse einen {} saftig süß und reif", fruit).unwrap();
+    }
+    path
+}
+
+#[test]
+fn test_that_vocabulary_is_sorted_by_count_and_pruned() {
+    let counts = vec![("b".to_string(), 3), ("a".to_string(), 3), ("selten".to_string(), 1),
+        ("oft".to_string(), 10)];
+    let vocabulary = Vocabulary::from_counts(counts.into_iter(), 2);
+    assert_eq!(vocabulary.len(), 3);
+    assert_eq!(vocabulary.word(0), "oft");
+    assert_eq!(vocabulary.index("a"), Some(1));
+    assert_eq!(vocabulary.index("selten"), None);
+    assert_eq!(vocabulary.total(), 16);
+}
+
+#[test]
+fn test_that_text_format_has_header_and_one_line_per_word() {
+    let model = Model { words: vec!["a".into(), "b".into()], dimensions: 2,
+        vectors: vec![0.5, -1.0, 0.0, 0.25] };
+    let mut output = Vec::new();
+    model.write_text(&mut output).unwrap();
+    assert_eq!(String::from_utf8(output).unwrap(),
+        "2 2\na 0.500000 -1.000000\nb 0.000000 0.250000\n");
+}
+
+#[test]
+fn test_that_binary_format_uses_little_endian_floats() {
+    let model = Model { words: vec!["a".into()], dimensions: 1, vectors: vec![1.0] };
+    let mut output = Vec::new();
+    model.write_binary(&mut output).unwrap();
+    assert_eq!(output, b"1 1\na \x00\x00\x80\x3f\n".to_vec());
+}
+
+#[test]
+fn test_that_written_vectors_can_be_read_again() {
+    let model = Model { words: vec!["a".into(), "b".into()], dimensions: 2,
+        vectors: vec![0.5, -1.0, 0.0, 0.25] };
+    let mut text = Vec::new();
+    model.write_text(&mut text).unwrap();
+    let read = Model::read_text(&text[..]).unwrap();
+    assert_eq!((read.words, read.vectors), (model.words.clone(), model.vectors.clone()));
+    let mut binary = Vec::new();
+    model.write_binary(&mut binary).unwrap();
+    let read = Model::read_binary(&binary[..]).unwrap();
+    assert_eq!((read.words, read.vectors), (model.words, model.vectors));
+}
+
+#[test]
+fn test_that_vectors_of_wrong_size_are_rejected() {
+    assert!(Model::read_text("1 3\na 0.1 0.2\n".as_bytes()).is_err());
+    assert!(Model::read_text("no header\n".as_bytes()).is_err());
+}
+
+#[test]
+fn test_that_implausible_headers_are_rejected() {
+    assert!(Model::read_text("1 0\na\n".as_bytes()).is_err());
+    assert!(Model::read_binary("1 0\na \n".as_bytes()).is_err());
+    // neither the words nor the vectors are allocated up front
+    let huge = format!("{} 300\na ", usize::MAX / 300);
+    assert!(Model::read_binary(huge.as_bytes()).is_err());
+    assert!(Model::read_text(huge.as_bytes()).is_err());
+    assert!(Model::read_binary(format!("1 {}\na ", usize::MAX).as_bytes()).is_err());
+}
+
+#[test]
+fn test_that_words_with_shared_contexts_get_similar_vectors() {
+    let corpus = write_corpus("similar");
+    for architecture in &[Architecture::SkipGram, Architecture::Cbow] {
+        let config = Config { architecture: *architecture, dimensions: 20, threads: 1,
+            iterations: 10, ..Config::default() };
+        let model = train(&corpus, &config).unwrap();
+        assert_eq!(model.vectors.len(), model.words.len() * 20);
+        assert!(model.vectors.iter().all(|v| v.is_finite()));
+        let hund = model.vector("hund").unwrap();
+        assert!(cosine(hund, model.vector("katze").unwrap())
+                > cosine(hund, model.vector("birne").unwrap()));
+    }
+}
+
+#[test]
+fn test_that_training_runs_on_several_threads() {
+    let corpus = write_corpus("threads");
+    let config = Config { dimensions: 10, threads: 3, iterations: 1, ..Config::default() };
+    let model = train(&corpus, &config).unwrap();
+    assert!(model.vector("garten").is_some());
+}
+
+#[test]
+fn test_that_vectors_without_dimensions_are_not_trained() {
+    let corpus = write_corpus("no_dimensions");
+    assert!(train(&corpus, &Config { dimensions: 0, ..Config::default() }).is_err());
+}